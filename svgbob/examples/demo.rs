@@ -15,7 +15,7 @@ fn main() {
 
 fn get_arg() -> &'static str{
 
-let arg = r#"
+r#"
 
 
  +10-15V           ___0,047R
@@ -834,7 +834,5 @@ resist
                     ===============================  <-- ceramic
                             (cold side)
 
-"#;
-
-arg
+"#
 }
@@ -0,0 +1,26 @@
+//! render any text, the first 8 bytes are the tab width,
+//! any usize, which is clamped by the grid
+//!
+//! ```console
+//! $ cargo +nightly fuzz run render
+//! ```
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use svgbob::{Grid, Settings};
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 8 {
+        return;
+    }
+    let (width, rest) = data.split_at(8);
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(width);
+    let tab_width = u64::from_le_bytes(bytes) as usize;
+    let text = String::from_utf8_lossy(rest);
+    let grid = Grid::from_str_with_tab_width(&text, tab_width);
+    let svg = grid.get_svg(&Settings::default()).to_string();
+    if let Err(e) = roxmltree::Document::parse(&svg) {
+        panic!("{}\n{}", e, svg);
+    }
+    let _ = grid.get_svg(&Settings::no_optimization()).to_string();
+});
@@ -0,0 +1,54 @@
+//! render the diagrams made of the drawing characters mixed with
+//! random unicode, control characters and very long lines
+//!
+//! ```console
+//! $ cargo +nightly fuzz run render_diagram
+//! ```
+#![no_main]
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use svgbob::{Grid, Settings};
+
+/// the characters diagrams are drawn with, and the ones that trip them
+const PALETTE: &[&str] = &[
+    "-", "|", "+", ".", "'", "`", ",", "/", "\\", "_", "*", "o", "O", "<", ">", "^", "v", "V",
+    "(", ")", "[", "]", "=", ":", "~", "#", " ", "a", "\"", "\n", "\r\n",
+    "─", "│", "┌", "┐", "└", "┘", "┼", "╭", "╯", "━", "║", "╱", "╳",
+    "中", "💖", "e\u{301}", "\u{301}", "\u{200b}", "\u{feff}", "\u{fffe}",
+    "\t", "\r", "\u{0}", "\u{1b}", "\u{7f}", "\u{85}", "&", "<!--", "]]>",
+];
+
+#[derive(Arbitrary, Debug)]
+enum Cell {
+    Drawing(u8),
+    Char(char),
+    /// a run of the same character, for the very wide lines
+    Repeat(u8, u16),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Diagram {
+    /// any usize, clamped by the grid
+    tab_width: usize,
+    text_width: u8,
+    text_height: u8,
+    cells: Vec<Cell>,
+}
+
+fuzz_target!(|diagram: Diagram| {
+    let mut text = String::new();
+    for cell in &diagram.cells {
+        match *cell {
+            Cell::Drawing(i) => text.push_str(PALETTE[i as usize % PALETTE.len()]),
+            Cell::Char(ch) => text.push(ch),
+            Cell::Repeat(i, count) => text.push_str(&PALETTE[i as usize % PALETTE.len()].repeat(count as usize)),
+        }
+    }
+    let grid = Grid::from_str_with_tab_width(&text, diagram.tab_width);
+    let settings = Settings::with_size(diagram.text_width as f32 % 32.0 + 1.0,
+                                       diagram.text_height as f32 % 32.0 + 1.0);
+    let svg = grid.get_svg(&settings).to_string();
+    if let Err(e) = roxmltree::Document::parse(&svg) {
+        panic!("{}\n{}", e, svg);
+    }
+});
@@ -0,0 +1,140 @@
+//! the area covered by the drawn elements, the svg is cropped to it
+use super::Element;
+use super::Point;
+use super::Settings;
+use super::text_width;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bounds {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Bounds {
+
+    /// the bounds of nothing, adding any point makes it non empty
+    pub fn empty() -> Bounds {
+        Bounds {
+            left: f32::INFINITY,
+            top: f32::INFINITY,
+            right: f32::NEG_INFINITY,
+            bottom: f32::NEG_INFINITY,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.left > self.right || self.top > self.bottom
+    }
+
+    pub fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> f32 {
+        self.bottom - self.top
+    }
+
+    fn add(&mut self, x: f32, y: f32) {
+        if x.is_finite() && y.is_finite() {
+            self.left = self.left.min(x);
+            self.top = self.top.min(y);
+            self.right = self.right.max(x);
+            self.bottom = self.bottom.max(y);
+        }
+    }
+
+    /// the geometry of the element, without the stroke width and the markers,
+    /// those are covered by the margin
+    pub fn add_element(&mut self, element: &Element, settings: &Settings) {
+        match *element {
+            Element::Circle(ref c, r, _) => {
+                self.add(c.x - r, c.y - r);
+                self.add(c.x + r, c.y + r);
+            }
+            Element::Polygon(ref points, _) => {
+                for p in points {
+                    self.add(p.x, p.y);
+                }
+            }
+            Element::Line(ref s, ref e, _, _) => {
+                self.add(s.x, s.y);
+                self.add(e.x, e.y);
+            }
+            Element::Arc(ref s, ref e, radius, sweep) => self.add_arc(s, e, radius, sweep),
+            Element::Text(ref loc, ref string) => {
+                // the cells the text run occupies
+                let x = loc.x as f32 * settings.text_width;
+                let y = loc.y as f32 * settings.text_height;
+                self.add(x, y);
+                self.add(x + text_width(string) as f32 * settings.text_width, y + settings.text_height);
+            }
+            Element::Path(_, _, ref d, _) => self.add_path(d),
+        }
+    }
+
+    /// the end points and the points of the circle
+    /// furthest in each direction that lie on the arc
+    fn add_arc(&mut self, s: &Point, e: &Point, radius: f32, sweep: bool) {
+        self.add(s.x, s.y);
+        self.add(e.x, e.y);
+        let (dx, dy) = (e.x - s.x, e.y - s.y);
+        let chord = (dx * dx + dy * dy).sqrt();
+        if chord == 0.0 {
+            return;
+        }
+        // the arc bulges out to the left of the chord when drawn counter clockwise,
+        // a radius too small for the chord is scaled up as the svg renderers do
+        let (nx, ny) = if sweep { (dy / chord, -dx / chord) } else { (-dy / chord, dx / chord) };
+        let half = chord / 2.0;
+        let radius = radius.max(half);
+        let distance = (radius * radius - half * half).sqrt();
+        let (mx, my) = ((s.x + e.x) / 2.0, (s.y + e.y) / 2.0);
+        let (cx, cy) = (mx - nx * distance, my - ny * distance);
+        for &(x, y) in &[(cx - radius, cy), (cx + radius, cy), (cx, cy - radius), (cx, cy + radius)] {
+            if (x - mx) * nx + (y - my) * ny > 0.0 {
+                self.add(x, y);
+            }
+        }
+    }
+
+    /// the path data of the optimizer, made of `M`, `L` and `A` commands
+    fn add_path(&mut self, d: &str) {
+        let mut tokens = d.split_whitespace();
+        let mut current = Point::new(0.0, 0.0);
+        while let Some(command) = tokens.next() {
+            let count = match command {
+                "M" | "L" => 2,
+                "A" => 7,
+                _ => continue,
+            };
+            let numbers: Vec<f32> = tokens.by_ref()
+                .take(count)
+                .map(|n| n.parse().unwrap_or(f32::NAN))
+                .collect();
+            if numbers.len() < count {
+                break;
+            }
+            let end = Point::new(numbers[count - 2], numbers[count - 1]);
+            if command == "A" {
+                self.add_arc(&current, &end, numbers[0], numbers[4] != 0.0);
+            } else {
+                self.add(end.x, end.y);
+            }
+            current = end;
+        }
+    }
+}
+
+#[test]
+fn test_arc_bounds(){
+    // the quarter of the circle around 8,8 from the top to the left
+    let mut bounds = Bounds::empty();
+    bounds.add_arc(&Point::new(8.0, 0.0), &Point::new(0.0, 8.0), 8.0, false);
+    assert_eq!(bounds, Bounds { left: 0.0, top: 0.0, right: 8.0, bottom: 8.0 });
+    // the half circle bulging out to the right of the chord
+    let mut bounds = Bounds::empty();
+    bounds.add_path("M 0 0 A 4 4 0 0 1 0 8");
+    assert_eq!(bounds, Bounds { left: 0.0, top: 0.0, right: 4.0, bottom: 8.0 });
+}
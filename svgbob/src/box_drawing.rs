@@ -0,0 +1,391 @@
+//! unicode box drawing characters
+//!
+//! every box drawing character is described by the kind of line
+//! going out of each of its 4 sides, which is then drawn
+//! in the same 5x5 point grid used in `get_elements`
+use super::Element;
+use super::Point;
+use super::Settings;
+use super::Stroke;
+use super::Feature;
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum Arm {
+    Empty,
+    Light,
+    Heavy,
+    Double,
+    Dashed,
+    HeavyDashed,
+    Round,
+}
+
+#[derive(Debug)]
+pub struct BoxChar {
+    pub top: Arm,
+    pub right: Arm,
+    pub bottom: Arm,
+    pub left: Arm,
+}
+
+/// the arms of the box drawing characters in the order of top, right, bottom, left
+/// ' ' empty, 'l' light, 'h' heavy, 'd' double, '-' dashed, '~' heavy dashed, 'a' arc
+const BOX_CHARS: &[(&str, &str)] = &[
+    ("─", " l l"),
+    ("━", " h h"),
+    ("│", "l l "),
+    ("┃", "h h "),
+    ("┄", " - -"),
+    ("┅", " ~ ~"),
+    ("┆", "- - "),
+    ("┇", "~ ~ "),
+    ("┈", " - -"),
+    ("┉", " ~ ~"),
+    ("┊", "- - "),
+    ("┋", "~ ~ "),
+    ("┌", " ll "),
+    ("┍", " hl "),
+    ("┎", " lh "),
+    ("┏", " hh "),
+    ("┐", "  ll"),
+    ("┑", "  lh"),
+    ("┒", "  hl"),
+    ("┓", "  hh"),
+    ("└", "ll  "),
+    ("┕", "lh  "),
+    ("┖", "hl  "),
+    ("┗", "hh  "),
+    ("┘", "l  l"),
+    ("┙", "l  h"),
+    ("┚", "h  l"),
+    ("┛", "h  h"),
+    ("├", "lll "),
+    ("┝", "lhl "),
+    ("┞", "hll "),
+    ("┟", "llh "),
+    ("┠", "hlh "),
+    ("┡", "hhl "),
+    ("┢", "lhh "),
+    ("┣", "hhh "),
+    ("┤", "l ll"),
+    ("┥", "l lh"),
+    ("┦", "h ll"),
+    ("┧", "l hl"),
+    ("┨", "h hl"),
+    ("┩", "h lh"),
+    ("┪", "l hh"),
+    ("┫", "h hh"),
+    ("┬", " lll"),
+    ("┭", " llh"),
+    ("┮", " hll"),
+    ("┯", " hlh"),
+    ("┰", " lhl"),
+    ("┱", " lhh"),
+    ("┲", " hhl"),
+    ("┳", " hhh"),
+    ("┴", "ll l"),
+    ("┵", "ll h"),
+    ("┶", "lh l"),
+    ("┷", "lh h"),
+    ("┸", "hl l"),
+    ("┹", "hl h"),
+    ("┺", "hh l"),
+    ("┻", "hh h"),
+    ("┼", "llll"),
+    ("┽", "lllh"),
+    ("┾", "lhll"),
+    ("┿", "lhlh"),
+    ("╀", "hlll"),
+    ("╁", "llhl"),
+    ("╂", "hlhl"),
+    ("╃", "hllh"),
+    ("╄", "hhll"),
+    ("╅", "llhh"),
+    ("╆", "lhhl"),
+    ("╇", "hhlh"),
+    ("╈", "lhhh"),
+    ("╉", "hlhh"),
+    ("╊", "hhhl"),
+    ("╋", "hhhh"),
+    ("╌", " - -"),
+    ("╍", " ~ ~"),
+    ("╎", "- - "),
+    ("╏", "~ ~ "),
+    ("═", " d d"),
+    ("║", "d d "),
+    ("╒", " dl "),
+    ("╓", " ld "),
+    ("╔", " dd "),
+    ("╕", "  ld"),
+    ("╖", "  dl"),
+    ("╗", "  dd"),
+    ("╘", "ld  "),
+    ("╙", "dl  "),
+    ("╚", "dd  "),
+    ("╛", "l  d"),
+    ("╜", "d  l"),
+    ("╝", "d  d"),
+    ("╞", "ldl "),
+    ("╟", "dld "),
+    ("╠", "ddd "),
+    ("╡", "l ld"),
+    ("╢", "d dl"),
+    ("╣", "d dd"),
+    ("╤", " dld"),
+    ("╥", " ldl"),
+    ("╦", " ddd"),
+    ("╧", "ld d"),
+    ("╨", "dl l"),
+    ("╩", "dd d"),
+    ("╪", "ldld"),
+    ("╫", "dldl"),
+    ("╬", "dddd"),
+    ("╭", " aa "),
+    ("╮", "  aa"),
+    ("╯", "a  a"),
+    ("╰", "aa  "),
+    ("╴", "   l"),
+    ("╵", "l   "),
+    ("╶", " l  "),
+    ("╷", "  l "),
+    ("╸", "   h"),
+    ("╹", "h   "),
+    ("╺", " h  "),
+    ("╻", "  h "),
+    ("╼", " h l"),
+    ("╽", "l h "),
+    ("╾", " l h"),
+    ("╿", "h l "),
+];
+
+const DIAGONALS: &[&str] = &["╱", "╲", "╳"];
+
+fn to_arm(code: char) -> Arm {
+    match code {
+        'l' => Arm::Light,
+        'h' => Arm::Heavy,
+        'd' => Arm::Double,
+        '-' => Arm::Dashed,
+        '~' => Arm::HeavyDashed,
+        'a' => Arm::Round,
+        _ => Arm::Empty,
+    }
+}
+
+pub fn box_char(ch: &str) -> Option<BoxChar> {
+    BOX_CHARS.iter()
+        .find(|&&(c, _)| c == ch)
+        .map(|&(_, arms)| {
+            let arms: Vec<Arm> = arms.chars().map(to_arm).collect();
+            BoxChar {
+                top: arms[0],
+                right: arms[1],
+                bottom: arms[2],
+                left: arms[3],
+            }
+        })
+}
+
+/// the first box drawing character with the arms top, right, bottom and left
+pub fn find_box_char(arms: [Arm; 4]) -> Option<&'static str> {
+    BOX_CHARS.iter()
+        .find(|&&(_, code)| code.chars().map(to_arm).eq(arms.iter().cloned()))
+        .map(|&(c, _)| c)
+}
+
+pub fn is_box_drawing(ch: &str) -> bool {
+    DIAGONALS.contains(&ch) || box_char(ch).is_some()
+}
+
+fn stroke(arm: Arm) -> Stroke {
+    match arm {
+        Arm::Heavy => Stroke::Heavy,
+        Arm::Dashed => Stroke::Dashed,
+        Arm::HeavyDashed => Stroke::HeavyDashed,
+        _ => Stroke::Solid,
+    }
+}
+
+fn is_single(arm: Arm) -> bool {
+    arm != Arm::Empty && arm != Arm::Double
+}
+
+/// where the rail of a double line ends, relative to the center in multiples of the rail offset.
+/// It stops at the rail of a double arm on its side,
+/// continues to the center when the opposite arm is double or a single line crosses it,
+/// otherwise it goes past the center to form the outer corner
+fn rail_end(side: Arm, opposite: Arm, crossed: bool) -> f32 {
+    if side == Arm::Double {
+        1.0
+    } else if opposite == Arm::Double || crossed {
+        0.0
+    } else {
+        -1.0
+    }
+}
+
+/// where a single line starts, relative to the center in multiples of the rail offset.
+/// It starts at the near rail when a double line passes through it,
+/// at the far rail when a double line turns at this corner
+fn single_start(side1: Arm, side2: Arm, opposite: Arm) -> f32 {
+    if side1 == Arm::Double && side2 == Arm::Double {
+        1.0
+    } else if (side1 == Arm::Double || side2 == Arm::Double) && opposite == Arm::Empty {
+        -1.0
+    } else {
+        0.0
+    }
+}
+
+/// the elements of the box drawing character at this location
+pub fn box_elements(ch: &str, x: isize, y: isize, settings: &Settings) -> Vec<Element> {
+    let ax = x as f32 * settings.text_width;
+    let ay = y as f32 * settings.text_height;
+    let cx = ax + settings.text_width / 2.0;
+    let cy = ay + settings.text_height / 2.0;
+    let ex = ax + settings.text_width;
+    let ey = ay + settings.text_height;
+    let dy = ay + settings.text_height * 3.0 / 4.0;
+    let by = ay + settings.text_height / 4.0;
+    let arc_radius = settings.text_width / 2.0;
+    // distance of the rails of the double lines from the center
+    let off = settings.text_width / 4.0;
+
+    let line = |x1: f32, y1: f32, x2: f32, y2: f32, stroke: Stroke| {
+        Element::line(&Point::new(x1, y1), &Point::new(x2, y2), stroke, Feature::Nothing)
+    };
+
+    let mut elements = vec![];
+    if DIAGONALS.contains(&ch) {
+        if ch != "╲" {
+            elements.push(line(ax, ey, ex, ay, Stroke::Solid));
+        }
+        if ch != "╱" {
+            elements.push(line(ax, ay, ex, ey, Stroke::Solid));
+        }
+        return elements;
+    }
+    let bc = match box_char(ch) {
+        Some(bc) => bc,
+        None => return elements,
+    };
+    let top = bc.top;
+    let right = bc.right;
+    let bottom = bc.bottom;
+    let left = bc.left;
+
+    // rounded corners
+    if top == Arm::Round || bottom == Arm::Round {
+        if bottom == Arm::Round {
+            elements.push(line(cx, dy, cx, ey, Stroke::Solid));
+            if right == Arm::Round {
+                elements.push(Element::arc(&Point::new(ex, cy), &Point::new(cx, dy), arc_radius, false));
+            } else {
+                elements.push(Element::arc(&Point::new(cx, dy), &Point::new(ax, cy), arc_radius, false));
+            }
+        } else {
+            elements.push(line(cx, ay, cx, by, Stroke::Solid));
+            if right == Arm::Round {
+                elements.push(Element::arc(&Point::new(cx, by), &Point::new(ex, cy), arc_radius, false));
+            } else {
+                elements.push(Element::arc(&Point::new(ax, cy), &Point::new(cx, by), arc_radius, false));
+            }
+        }
+        return elements;
+    }
+
+    let crossed_horizontally = is_single(left) || is_single(right);
+    let crossed_vertically = is_single(top) || is_single(bottom);
+
+    // vertical
+    let top_start = single_start(left, right, bottom) * off;
+    let bottom_start = single_start(left, right, top) * off;
+    if top == bottom && is_single(top) && top_start == 0.0 {
+        elements.push(line(cx, ay, cx, ey, stroke(top)));
+    } else {
+        match top {
+            Arm::Empty => (),
+            Arm::Double => {
+                let left_end = rail_end(left, bottom, crossed_horizontally) * off;
+                let right_end = rail_end(right, bottom, crossed_horizontally) * off;
+                elements.push(line(cx - off, ay, cx - off, cy - left_end, Stroke::Solid));
+                elements.push(line(cx + off, ay, cx + off, cy - right_end, Stroke::Solid));
+            }
+            _ => elements.push(line(cx, ay, cx, cy - top_start, stroke(top))),
+        }
+        match bottom {
+            Arm::Empty => (),
+            Arm::Double => {
+                let left_end = rail_end(left, top, crossed_horizontally) * off;
+                let right_end = rail_end(right, top, crossed_horizontally) * off;
+                elements.push(line(cx - off, cy + left_end, cx - off, ey, Stroke::Solid));
+                elements.push(line(cx + off, cy + right_end, cx + off, ey, Stroke::Solid));
+            }
+            _ => elements.push(line(cx, cy + bottom_start, cx, ey, stroke(bottom))),
+        }
+    }
+    // horizontal
+    let left_start = single_start(top, bottom, right) * off;
+    let right_start = single_start(top, bottom, left) * off;
+    if left == right && is_single(left) && left_start == 0.0 {
+        elements.push(line(ax, cy, ex, cy, stroke(left)));
+    } else {
+        match left {
+            Arm::Empty => (),
+            Arm::Double => {
+                let top_end = rail_end(top, right, crossed_vertically) * off;
+                let bottom_end = rail_end(bottom, right, crossed_vertically) * off;
+                elements.push(line(ax, cy - off, cx - top_end, cy - off, Stroke::Solid));
+                elements.push(line(ax, cy + off, cx - bottom_end, cy + off, Stroke::Solid));
+            }
+            _ => elements.push(line(ax, cy, cx - left_start, cy, stroke(left))),
+        }
+        match right {
+            Arm::Empty => (),
+            Arm::Double => {
+                let top_end = rail_end(top, left, crossed_vertically) * off;
+                let bottom_end = rail_end(bottom, left, crossed_vertically) * off;
+                elements.push(line(cx + top_end, cy - off, ex, cy - off, Stroke::Solid));
+                elements.push(line(cx + bottom_end, cy + off, ex, cy + off, Stroke::Solid));
+            }
+            _ => elements.push(line(cx + right_start, cy, ex, cy, stroke(right))),
+        }
+    }
+    elements
+}
+
+#[test]
+fn test_box_char(){
+    let bc = box_char("╠").unwrap();
+    assert_eq!(bc.top, Arm::Double);
+    assert_eq!(bc.right, Arm::Double);
+    assert_eq!(bc.bottom, Arm::Double);
+    assert_eq!(bc.left, Arm::Empty);
+    assert!(is_box_drawing("╳"));
+    assert!(!is_box_drawing("+"));
+}
+
+#[test]
+fn test_double_corner(){
+    let settings = &Settings::default();
+    // outer rail of the corner goes past the center, the inner rail stops at the other rail
+    let elements = box_elements("╔", 0, 0, settings);
+    assert_eq!(elements.len(), 4);
+}
+
+#[test]
+fn test_heavy_dashes(){
+    let settings = &Settings::default();
+    for ch in &["┅", "┇", "┉", "┋", "╍", "╏"] {
+        match box_elements(ch, 0, 0, settings)[..] {
+            [Element::Line(_, _, Stroke::HeavyDashed, _)] => (),
+            ref elements => panic!("{} drawn as {:?}", ch, elements),
+        }
+    }
+    match box_elements("┄", 0, 0, settings)[..] {
+        [Element::Line(_, _, Stroke::Dashed, _)] => (),
+        ref elements => panic!("┄ drawn as {:?}", elements),
+    }
+}
@@ -0,0 +1,106 @@
+//! The overlay drawn with `Settings::set_debug`:
+//! the cell grid, the line and column numbers on the margins
+//! and the 5x5 anchor points of the cells that have a character.
+//!
+use svg::Node;
+use svg::node::element::Group;
+use svg::node::element::Path as SvgPath;
+use svg::node::element::Style;
+use svg::node::element::Text as SvgText;
+
+use super::Grid;
+use super::Settings;
+
+pub fn get_debug_styles() -> Style {
+    let style = r#"
+    g.debug path.grid {
+      stroke: #00a0ff;
+      stroke-width: 0.5;
+      stroke-opacity: 0.3;
+    }
+    g.debug path.anchors {
+      stroke: #ff4000;
+      stroke-width: 1.5;
+      stroke-opacity: 0.6;
+    }
+    g.debug text {
+      fill: #00a0ff;
+      font-family: monospace;
+    }
+    "#;
+    Style::new(style)
+}
+
+/// the overlay, to be drawn below the diagram
+pub fn debug_overlay(grid: &Grid, settings: &Settings) -> Group {
+    let tw = settings.text_width;
+    let th = settings.text_height;
+    let width = grid.columns as f32 * tw;
+    let height = grid.rows as f32 * th;
+
+    let mut lines = String::new();
+    for column in 0..=grid.columns {
+        lines.push_str(&format!(" M {} 0 L {} {}", column as f32 * tw, column as f32 * tw, height));
+    }
+    for row in 0..=grid.rows {
+        lines.push_str(&format!(" M 0 {} L {} {}", row as f32 * th, width, row as f32 * th));
+    }
+
+    // a dot is a zero length segment with a round line cap
+    let mut anchors = String::new();
+    for (y, line) in grid.lines.iter().enumerate() {
+        let mut x = 0;
+        for gchar in line {
+            if gchar.string != " " {
+                for ay in 0..5 {
+                    for ax in 0..5 {
+                        anchors.push_str(&format!(" M {} {} l 0 0",
+                                                  (x as f32 + ax as f32 / 4.0) * tw,
+                                                  (y as f32 + ay as f32 / 4.0) * th));
+                    }
+                }
+            }
+            x += gchar.width as isize;
+        }
+    }
+
+    let mut group = Group::new().set("class", "debug");
+    group.append(SvgPath::new().set("class", "grid").set("d", lines).set("fill", "none"));
+    group.append(SvgPath::new().set("class", "anchors").set("d", anchors).set("fill", "none"));
+
+    // the numbers start at 1, as the line:column of lint and explain
+    let font_size = th / 2.0;
+    for column in 0..grid.columns {
+        // written top to bottom, so the numbers fit in the narrow columns
+        let mut label = SvgText::new()
+            .set("x", (column as f32 + 0.5) * tw)
+            .set("y", height + th)
+            .set("font-size", font_size)
+            .set("text-anchor", "middle")
+            .set("writing-mode", "tb");
+        label.append(svg::node::Text::new(format!("{}", column + 1)));
+        group.append(label);
+    }
+    for row in 0..grid.rows {
+        let mut label = SvgText::new()
+            .set("x", width + tw / 2.0)
+            .set("y", (row as f32 + 0.75) * th)
+            .set("font-size", font_size);
+        label.append(svg::node::Text::new(format!("{}", row + 1)));
+        group.append(label);
+    }
+    group
+}
+
+#[test]
+fn test_debug_overlay(){
+    let grid = Grid::from_str("+-\n |");
+    let overlay = debug_overlay(&grid, &Settings::default()).to_string();
+    assert!(overlay.contains(r#"class="debug""#));
+    // 3 vertical and 3 horizontal lines
+    assert!(overlay.contains("M 16 0 L 16 32"));
+    assert!(overlay.contains("M 0 32 L 16 32"));
+    // 3 characters with 25 anchor points each
+    assert_eq!(overlay.matches("l 0 0").count(), 75);
+    assert_eq!(overlay.matches("<text").count(), 4);
+}
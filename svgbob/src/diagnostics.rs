@@ -0,0 +1,305 @@
+//! Checks for the common mistakes in a diagram,
+//! the places where the drawing is likely not what was intended.
+//!
+use std::fmt;
+
+use super::Element;
+use super::Feature;
+use super::Grid;
+use super::Loc;
+use super::Settings;
+use rules::RuleSet;
+
+use super::{is_vertical, is_horizontal, is_horizontal_dashed, is_vertical_dashed,
+            is_slant_left, is_slant_right, is_round, is_intersection, is_arrow_up,
+            is_arrow_down, is_arrow_left, is_arrow_right};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// the line of the character, starting at 1
+    pub line: usize,
+    /// the column of the character, starting at 1
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(loc: &Loc, severity: Severity, message: String) -> Diagnostic {
+        Diagnostic {
+            line: loc.y as usize + 1,
+            column: loc.x as usize + 1,
+            severity,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}: {}", self.line, self.column, self.severity, self.message)
+    }
+}
+
+// the characters that a line coming from each direction can join into
+fn joins_horizontally(ch: &str) -> bool {
+    is_horizontal(ch) || is_horizontal_dashed(ch) || is_intersection(ch) || is_round(ch)
+}
+
+fn joins_vertically(ch: &str) -> bool {
+    is_vertical(ch) || is_vertical_dashed(ch) || is_intersection(ch) || is_round(ch)
+}
+
+struct Checker<'a> {
+    grid: &'a Grid,
+    settings: Settings,
+    rules: RuleSet,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+
+    fn ch(&self, loc: &Loc) -> &str {
+        match self.grid.get(loc) {
+            Some(gchar) => &gchar.string,
+            None => "",
+        }
+    }
+
+    fn is_char<F>(&self, loc: &Loc, f: F) -> bool
+        where F: Fn(&str) -> bool
+    {
+        self.grid.is_char(loc, f)
+    }
+
+    fn is_blank(&self, loc: &Loc) -> bool {
+        let ch = self.ch(loc);
+        ch.is_empty() || ch == " "
+    }
+
+    fn elements(&self, loc: &Loc) -> Vec<Element> {
+        self.grid.get_elements(loc.x, loc.y, &self.settings, &self.rules).unwrap_or_default()
+    }
+
+    /// the character is drawn, and not rendered as text
+    fn is_drawn(&self, loc: &Loc) -> bool {
+        let elements = self.elements(loc);
+        !elements.is_empty() && !elements.iter().all(|elm| matches!(*elm, Element::Text(_, _)))
+    }
+
+    fn is_drawn_as_arrow(&self, loc: &Loc) -> bool {
+        self.elements(loc).iter().any(|elm| matches!(*elm, Element::Line(_, _, _, Feature::Arrow)))
+    }
+
+    fn report(&mut self, loc: &Loc, severity: Severity, message: String) {
+        self.diagnostics.push(Diagnostic::new(loc, severity, message));
+    }
+
+    fn check(&mut self, loc: &Loc) {
+        if self.grid.is_escaped(loc) || self.grid.is_escape_quote(loc) {
+            return;
+        }
+        let ch = self.ch(loc).to_string();
+        if is_horizontal(&ch) || is_horizontal_dashed(&ch) || is_vertical(&ch) || is_vertical_dashed(&ch) {
+            self.check_line_end(loc, &ch);
+        }
+        if is_intersection(&ch) {
+            self.check_corner(loc);
+        }
+        if is_arrow_up(&ch) || is_arrow_down(&ch) || is_arrow_left(&ch) || is_arrow_right(&ch) {
+            self.check_arrow(loc, &ch);
+        }
+        if is_horizontal_dashed(&ch) || is_vertical_dashed(&ch) {
+            self.check_dashes(loc, &ch);
+        }
+        if is_vertical(&ch) {
+            self.check_alignment(loc, &ch);
+        }
+    }
+
+    /// a line that stops one column or row short of a character it could join
+    fn check_line_end(&mut self, loc: &Loc, ch: &str) {
+        if !self.is_drawn(loc) {
+            return;
+        }
+        let horizontal = is_horizontal(ch) || is_horizontal_dashed(ch);
+        let steps: [fn(&Loc) -> Loc; 2] = if horizontal {
+            [Loc::left, Loc::right]
+        } else {
+            [Loc::top, Loc::bottom]
+        };
+        // a line across the gap is likely a neighboring line or a label box,
+        // only a corner is considered a missed junction
+        let joins: fn(&str) -> bool = if horizontal {
+            |ch| is_intersection(ch) || is_round(ch)
+        } else {
+            is_intersection
+        };
+        for (i, step) in steps.iter().enumerate() {
+            let gap = step(loc);
+            let next = step(&gap);
+            // `- - -` the gaps are part of the dashes
+            let behind = steps[1 - i](loc);
+            let spaced = self.is_blank(&behind) && self.is_char(&steps[1 - i](&behind), |c| c == ch);
+            if !spaced && self.is_char(&gap, |ch| ch == " ") && self.is_char(&next, joins) && self.is_drawn(&next) {
+                let message = format!("line ends one cell short of `{}` at {}:{}",
+                                      self.ch(&next), next.y + 1, next.x + 1);
+                self.report(loc, Severity::Warning, message);
+            }
+        }
+    }
+
+    /// a `+` corner that does not connect at least 2 lines,
+    /// a `+` with no line around it is just text
+    /// and one next to other characters could be a label on the line
+    fn check_corner(&mut self, loc: &Loc) {
+        let around = loc.neighbors();
+        let others = around.iter().filter(|n| !self.is_blank(n)).count();
+        let connections = [
+            self.is_char(&loc.left(), |ch| joins_horizontally(ch) || is_arrow_left(ch)),
+            self.is_char(&loc.right(), |ch| joins_horizontally(ch) || is_arrow_right(ch)),
+            self.is_char(&loc.top(), |ch| joins_vertically(ch) || is_arrow_up(ch)),
+            self.is_char(&loc.bottom(), |ch| joins_vertically(ch) || is_arrow_down(ch)),
+            self.is_char(&loc.top_left(), is_slant_left),
+            self.is_char(&loc.top_right(), is_slant_right),
+            self.is_char(&loc.bottom_left(), is_slant_right),
+            self.is_char(&loc.bottom_right(), is_slant_left),
+        ];
+        let count = connections.iter().filter(|&&c| c).count();
+        if (count == 1 && others == 1) || (count == 0 && self.is_drawn(loc)) {
+            let message = format!("`+` corner has {} connection{}, it needs at least 2",
+                                  count, if count == 1 { "" } else { "s" });
+            self.report(loc, Severity::Error, message);
+        }
+    }
+
+    /// an arrow head that is separated from the line it points along
+    fn check_arrow(&mut self, loc: &Loc, ch: &str) {
+        if self.is_drawn_as_arrow(loc) {
+            return;
+        }
+        let horizontal = is_arrow_right(ch) || is_arrow_left(ch);
+        let back: fn(&Loc) -> Loc = if is_arrow_right(ch) {
+            Loc::left
+        } else if is_arrow_left(ch) {
+            Loc::right
+        } else if is_arrow_up(ch) {
+            Loc::bottom
+        } else {
+            Loc::top
+        };
+        let gap = back(loc);
+        let line = back(&gap);
+        let is_line = if horizontal {
+            self.is_char(&line, |ch| is_horizontal(ch) || is_horizontal_dashed(ch))
+        } else {
+            self.is_char(&line, |ch| is_vertical(ch) || is_vertical_dashed(ch))
+        };
+        if self.is_blank(&gap) && is_line && self.is_drawn(&line) {
+            let message = format!("arrow `{}` is not attached to the line at {}:{}",
+                                  ch, line.y + 1, line.x + 1);
+            self.report(loc, Severity::Error, message);
+        }
+    }
+
+    /// `=` and `:` that are too few to be drawn as a dashed line
+    fn check_dashes(&mut self, loc: &Loc, ch: &str) {
+        if self.is_drawn(loc) {
+            return;
+        }
+        if is_horizontal_dashed(ch) {
+            // report only the first of the `==`, when it is next to a drawing
+            let right = loc.right();
+            if self.is_char(&right, is_horizontal_dashed)
+                && !self.is_char(&loc.left(), is_horizontal_dashed)
+                && (self.is_drawn(&loc.left()) || self.is_drawn(&right.right())) {
+                self.report(loc, Severity::Warning,
+                            "`==` is rendered as text, a dashed line needs at least 3 `=` in a row".into());
+            }
+        } else {
+            let top = loc.top();
+            let bottom = loc.bottom();
+            if self.is_char(&top, |ch| joins_vertically(ch) || is_arrow_up(ch))
+                || self.is_char(&bottom, |ch| joins_vertically(ch) || is_arrow_down(ch)) {
+                self.report(loc, Severity::Warning,
+                            "`:` is rendered as text, a dashed line needs another `:` above or below it".into());
+            }
+        }
+    }
+
+    /// a vertical line that is shifted a column from the line in the row above,
+    /// both lines are at least 2 rows long to tell them apart from symbols
+    fn check_alignment(&mut self, loc: &Loc, ch: &str) {
+        if self.is_char(&loc.top(), joins_vertically) || !self.is_char(&loc.bottom(), is_vertical) {
+            return;
+        }
+        for above in &[loc.top_left(), loc.top_right()] {
+            if self.is_char(above, is_vertical)
+                && self.is_char(&above.top(), is_vertical)
+                && !self.is_char(&above.bottom(), joins_vertically) {
+                let message = format!("`{}` is not aligned with the `{}` at {}:{}, the rows are ragged",
+                                      ch, self.ch(above), above.y + 1, above.x + 1);
+                self.report(loc, Severity::Warning, message);
+            }
+        }
+    }
+}
+
+/// check the grid for the characters that are likely drawn not as intended
+pub fn diagnose(grid: &Grid) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        grid,
+        settings: Settings::default(),
+        rules: RuleSet::builtin(),
+        diagnostics: vec![],
+    };
+    for y in 0..grid.lines.len() as isize {
+        for x in 0..grid.line_width(y) {
+            checker.check(&Loc::new(x, y));
+        }
+    }
+    checker.diagnostics
+}
+
+#[test]
+fn test_corner_connections(){
+    let diagnostics = diagnose(&Grid::from_str("+---+\n|   |\n+---+"));
+    assert!(diagnostics.is_empty());
+    let diagnostics = diagnose(&Grid::from_str("a + b"));
+    assert!(diagnostics.is_empty());
+    let diagnostics = diagnose(&Grid::from_str("----+"));
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].column, 5);
+}
+
+#[test]
+fn test_arrow_gap(){
+    let diagnostics = diagnose(&Grid::from_str("---- >"));
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[0].column, 6);
+    assert!(diagnose(&Grid::from_str("---->")).is_empty());
+}
+
+#[test]
+fn test_ragged_rows(){
+    let diagnostics = diagnose(&Grid::from_str("+--+\n|  |\n|  |\n|   |\n|   |\n+---+"));
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (4, 5));
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+}
@@ -0,0 +1,132 @@
+//! Why a character is drawn the way it is:
+//! the rules that match the cell, the one selected
+//! and the elements it ends up as.
+//!
+use std::fmt;
+
+use super::Element;
+use super::Grid;
+use super::Loc;
+use super::Settings;
+use optimizer::Optimizer;
+use rules::RuleSet;
+
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    /// the line of the character, starting at 1
+    pub line: usize,
+    /// the column of the character, starting at 1
+    pub column: usize,
+    /// the 3 rows of 3 characters around the cell, a space where there is none
+    pub neighborhood: Vec<String>,
+    /// the rules that match the cell, lowest priority first
+    pub matched: Vec<String>,
+    /// the rule that is used for the cell, or why it is rendered as text
+    pub selected: String,
+    /// the elements of the cell before optimization
+    pub elements: Vec<Element>,
+    /// the elements passing through the cell after optimization
+    pub optimized: Vec<Element>,
+    /// the elements of the cell are merged into an element that starts on another cell
+    pub merged: bool,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}:{}", self.line, self.column)?;
+        writeln!(f, "neighborhood:")?;
+        for row in &self.neighborhood {
+            writeln!(f, "    |{}|", row)?;
+        }
+        writeln!(f, "matched rules, lowest priority first:")?;
+        if self.matched.is_empty() {
+            writeln!(f, "    none")?;
+        }
+        for rule in &self.matched {
+            writeln!(f, "    {}", rule)?;
+        }
+        writeln!(f, "selected: {}", self.selected)?;
+        writeln!(f, "elements:")?;
+        for elm in &self.elements {
+            writeln!(f, "    {:?}", elm)?;
+        }
+        if self.merged {
+            writeln!(f, "after optimization, merged into:")?;
+        } else {
+            writeln!(f, "after optimization:")?;
+        }
+        for elm in &self.optimized {
+            writeln!(f, "    {:?}", elm)?;
+        }
+        Ok(())
+    }
+}
+
+/// explain the cell at loc, None when it is outside of the grid
+pub fn explain(grid: &Grid, loc: &Loc, settings: &Settings) -> Option<Explanation> {
+    let gchar = grid.get(loc)?;
+    let rules = RuleSet::builtin();
+    let neighborhood = (-1..2).map(|dy| {
+        (-1..2).map(|dx| {
+            match grid.get(&Loc::new(loc.x + dx, loc.y + dy)) {
+                Some(gchar) => gchar.string.clone(),
+                None => " ".to_string(),
+            }
+        }).collect()
+    }).collect();
+
+    let mut matched: Vec<String> = rules.matching(grid, loc).iter()
+        .map(|&(i, rule)| format!("rule #{}: {}", i, rule.describe()))
+        .collect();
+    matched.extend(settings.rules.iter().enumerate()
+        .filter(|&(_, rule)| rule.matches(grid, loc))
+        .map(|(i, _)| format!("custom rule #{}", i)));
+
+    let selected = if grid.is_escape_quote(loc) {
+        "escape quote, not drawn".to_string()
+    } else if grid.is_escaped(loc) {
+        "escaped, rendered as text".to_string()
+    } else if let Some(rule) = matched.last() {
+        rule.clone()
+    } else if grid.is_in_word(loc) {
+        "part of a word, rendered as text".to_string()
+    } else if gchar.string == " " {
+        "blank".to_string()
+    } else {
+        "no rule matched, rendered as text".to_string()
+    };
+
+    let elements = grid.get_elements(loc.x, loc.y, settings, &rules).unwrap_or_default();
+    let optimizer = Optimizer::new(grid.get_all_elements(settings));
+    let (optimized, merged) = optimizer.reduced_at(loc);
+    Some(Explanation {
+        line: loc.y as usize + 1,
+        column: loc.x as usize + 1,
+        neighborhood,
+        matched,
+        selected,
+        elements,
+        optimized,
+        merged,
+    })
+}
+
+#[test]
+fn test_explain_corner(){
+    let grid = Grid::from_str(".--\n|");
+    let explanation = explain(&grid, &Loc::new(0, 0), &Settings::default()).unwrap();
+    assert_eq!(explanation.neighborhood, vec!["   ", " .-", " | "]);
+    assert!(!explanation.matched.is_empty());
+    assert_eq!(&explanation.selected, explanation.matched.last().unwrap());
+    assert!(explanation.elements.iter().any(|elm| matches!(*elm, Element::Arc(_, _, _, _))));
+    assert!(!explanation.optimized.is_empty());
+}
+
+#[test]
+fn test_explain_text(){
+    let grid = Grid::from_str("a.b");
+    let explanation = explain(&grid, &Loc::new(1, 0), &Settings::default()).unwrap();
+    assert!(explanation.matched.is_empty());
+    assert!(explanation.selected.contains("text"));
+    assert!(explain(&grid, &Loc::new(5, 0), &Settings::default()).is_none());
+}
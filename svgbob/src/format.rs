@@ -0,0 +1,379 @@
+//! Rewrite the source of a diagram consistently:
+//! the corners in a single style, the vertical lines drawn a column off
+//! put back under the line above, and no trailing whitespace.
+//!
+use box_drawing::{box_char, Arm};
+
+use super::Grid;
+use super::Loc;
+use super::cell_offsets;
+use super::{is_horizontal, is_intersection, is_round, is_vertical};
+
+/// how the corners are written by `Grid::format`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CornerStyle {
+    /// `+` for every corner
+    Plus,
+    /// `.` for the top corners and `'` for the bottom corners,
+    /// `+` where more than 2 lines meet
+    Rounded,
+    /// `┌ ┐ └ ┘ ├ ┤ ┬ ┴ ┼`, with `─` and `│` for the lines leading to them
+    BoxDrawing,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+use self::Direction::{Bottom, Left, Right, Top};
+
+impl Direction {
+    fn step(self, x: isize, y: isize) -> (isize, isize) {
+        match self {
+            Top => (x, y - 1),
+            Right => (x + 1, y),
+            Bottom => (x, y + 1),
+            Left => (x - 1, y),
+        }
+    }
+
+    fn opposite(self) -> Direction {
+        match self {
+            Top => Bottom,
+            Right => Left,
+            Bottom => Top,
+            Left => Right,
+        }
+    }
+}
+
+/// the cells of the diagram, one per column,
+/// None for the columns taken by the right half of a wide character
+struct Canvas<'a> {
+    grid: &'a Grid,
+    rows: Vec<Vec<Option<String>>>,
+}
+
+impl<'a> Canvas<'a> {
+    fn new(grid: &'a Grid) -> Canvas<'a> {
+        let rows = grid.lines.iter()
+            .map(|line| {
+                let mut row = vec![];
+                for gchar in line {
+                    row.push(Some(gchar.string.clone()));
+                    for _ in 1..gchar.width {
+                        row.push(None);
+                    }
+                }
+                row
+            })
+            .collect();
+        Canvas { grid, rows }
+    }
+
+    fn get(&self, x: isize, y: isize) -> &str {
+        if x < 0 || y < 0 {
+            return "";
+        }
+        match self.rows.get(y as usize).and_then(|row| row.get(x as usize)) {
+            Some(Some(s)) => s,
+            _ => "",
+        }
+    }
+
+    /// a single column character that is not part of a text
+    fn is_drawing(&self, x: isize, y: isize) -> bool {
+        if self.get(x, y).is_empty() {
+            return false;
+        }
+        let loc = Loc::new(x, y);
+        let is_single = self.rows[y as usize].get(x as usize + 1).is_none_or(|next| next.is_some());
+        is_single && !self.grid.is_escaped(&loc) && !self.grid.is_escape_quote(&loc) && !self.grid.is_in_word(&loc)
+    }
+
+    fn set(&mut self, x: isize, y: isize, s: &str) {
+        let row = &mut self.rows[y as usize];
+        while row.len() <= x as usize {
+            row.push(Some(" ".into()));
+        }
+        row[x as usize] = Some(s.into());
+    }
+
+    /// the character at x, y has a line going out to the direction
+    fn has_arm(&self, x: isize, y: isize, direction: Direction) -> bool {
+        if !self.is_drawing(x, y) {
+            return false;
+        }
+        let s = self.get(x, y);
+        if is_intersection(s) {
+            return true;
+        }
+        if is_horizontal(s) {
+            return direction == Left || direction == Right;
+        }
+        if is_vertical(s) {
+            return direction == Top || direction == Bottom;
+        }
+        match box_char(s) {
+            Some(arms) => {
+                let arm = match direction {
+                    Top => arms.top,
+                    Right => arms.right,
+                    Bottom => arms.bottom,
+                    Left => arms.left,
+                };
+                arm != Arm::Empty
+            }
+            None => false,
+        }
+    }
+
+    /// the neighbor at the direction has a line coming to x, y
+    fn connects(&self, x: isize, y: isize, direction: Direction) -> bool {
+        let (nx, ny) = direction.step(x, y);
+        self.has_arm(nx, ny, direction.opposite())
+    }
+
+    fn text(&self) -> String {
+        let mut lines: Vec<String> = self.rows.iter()
+            .map(|row| {
+                let line: String = row.iter().filter_map(|cell| cell.as_ref().map(|s| s.as_str())).collect();
+                line.trim_end().to_string()
+            })
+            .collect();
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        let mut formatted = lines.join("\n");
+        formatted.push('\n');
+        formatted
+    }
+}
+
+/// a vertical line or a corner that the vertical line below can be aligned to
+fn has_bottom(canvas: &Canvas, x: isize, y: isize) -> bool {
+    canvas.has_arm(x, y, Bottom) || canvas.is_drawing(x, y) && (canvas.get(x, y) == "." || canvas.get(x, y) == ",")
+}
+
+fn has_top(canvas: &Canvas, x: isize, y: isize) -> bool {
+    canvas.has_arm(x, y, Top) || canvas.is_drawing(x, y) && (canvas.get(x, y) == "'" || canvas.get(x, y) == "`")
+}
+
+/// move the `|` that doesn't continue a vertical line
+/// to the column next to it where the line above is
+fn align_vertical_lines(canvas: &mut Canvas) {
+    for y in 1..canvas.rows.len() as isize {
+        for x in 0..canvas.rows[y as usize].len() as isize {
+            if !canvas.is_drawing(x, y) || !is_vertical(canvas.get(x, y)) {
+                continue;
+            }
+            if has_bottom(canvas, x, y - 1) || has_top(canvas, x, y + 1) {
+                continue;
+            }
+            let targets: Vec<isize> = [x - 1, x + 1].iter()
+                .cloned()
+                .filter(|&tx| tx >= 0 && has_bottom(canvas, tx, y - 1))
+                .filter(|&tx| {
+                    let target = canvas.get(tx, y);
+                    target == " " || target.is_empty() && tx as usize >= canvas.rows[y as usize].len()
+                })
+                .collect();
+            if let [tx] = targets[..] {
+                let vertical = canvas.get(x, y).to_string();
+                canvas.set(x, y, " ");
+                canvas.set(tx, y, &vertical);
+            }
+        }
+    }
+}
+
+fn is_corner(s: &str) -> bool {
+    is_intersection(s) || is_round(s) || box_char(s).is_some_and(|arms| {
+        let is_light = |arm: Arm| arm == Arm::Empty || arm == Arm::Light || arm == Arm::Round;
+        is_light(arms.top) && is_light(arms.right) && is_light(arms.bottom) && is_light(arms.left)
+            && !is_horizontal(s) && !is_vertical(s)
+    })
+}
+
+fn corner(style: CornerStyle, top: bool, right: bool, bottom: bool, left: bool) -> &'static str {
+    let joints = [top, right, bottom, left].iter().filter(|&&arm| arm).count();
+    match style {
+        CornerStyle::Plus => "+",
+        CornerStyle::Rounded if joints > 2 => "+",
+        CornerStyle::Rounded => if bottom { "." } else { "'" },
+        CornerStyle::BoxDrawing => match (top, right, bottom, left) {
+            (false, true, true, false) => "┌",
+            (false, false, true, true) => "┐",
+            (true, true, false, false) => "└",
+            (true, false, false, true) => "┘",
+            (true, true, true, false) => "├",
+            (true, false, true, true) => "┤",
+            (false, true, true, true) => "┬",
+            (true, true, false, true) => "┴",
+            _ => "┼",
+        },
+    }
+}
+
+/// rewrite the lines leading out of the corner at x, y in the style
+fn restyle_line(canvas: &mut Canvas, style: CornerStyle, x: isize, y: isize, direction: Direction) {
+    let (ascii, unicode) = match direction {
+        Left | Right => ("-", "─"),
+        Top | Bottom => ("|", "│"),
+    };
+    let (from, to) = match style {
+        CornerStyle::BoxDrawing => (ascii, unicode),
+        _ => (unicode, ascii),
+    };
+    let (mut x, mut y) = direction.step(x, y);
+    while canvas.is_drawing(x, y) && canvas.get(x, y) == from {
+        canvas.set(x, y, to);
+        let next = direction.step(x, y);
+        x = next.0;
+        y = next.1;
+    }
+}
+
+/// write the corners, where a horizontal and a vertical line meet, in the style
+fn restyle_corners(canvas: &mut Canvas, style: CornerStyle) {
+    for y in 0..canvas.rows.len() as isize {
+        for x in 0..canvas.rows[y as usize].len() as isize {
+            if !canvas.is_drawing(x, y) || !is_corner(canvas.get(x, y)) {
+                continue;
+            }
+            let top = canvas.connects(x, y, Top);
+            let right = canvas.connects(x, y, Right);
+            let bottom = canvas.connects(x, y, Bottom);
+            let left = canvas.connects(x, y, Left);
+            if !(top || bottom) || !(left || right) {
+                continue;
+            }
+            canvas.set(x, y, corner(style, top, right, bottom, left));
+            for &(joined, direction) in &[(top, Top), (right, Right), (bottom, Bottom), (left, Left)] {
+                if joined {
+                    restyle_line(canvas, style, x, y, direction);
+                }
+            }
+        }
+    }
+}
+
+fn formatted_canvas(grid: &Grid, style: CornerStyle) -> Canvas<'_> {
+    let mut canvas = Canvas::new(grid);
+    align_vertical_lines(&mut canvas);
+    restyle_corners(&mut canvas, style);
+    canvas
+}
+
+pub fn format(grid: &Grid, style: CornerStyle) -> String {
+    formatted_canvas(grid, style).text()
+}
+
+/// the line with the characters whose cells were changed replaced by the formatted cells,
+/// the others are kept as written, the tab is rewritten as spaces only when a cell of it changed
+fn restore_line(line: &str, tab_width: usize, before: &[Option<String>], after: &[Option<String>]) -> String {
+    let cells = cell_offsets(line, tab_width);
+    // a leading zero width character doesn't have a cell
+    let mut restored = line[..cells.first().map_or(line.len(), |cell| cell.0)].to_string();
+    let mut column = 0;
+    let mut i = 0;
+    while i < cells.len() {
+        // the cells of the same character, as the spaces of a tab
+        let offset = cells[i].0;
+        let mut columns = 0;
+        while i < cells.len() && cells[i].0 == offset {
+            columns += cells[i].1;
+            i += 1;
+        }
+        let end = cells.get(i).map_or(line.len(), |cell| cell.0);
+        let range = column..column + columns;
+        if before[range.clone()] == after[range.clone()] {
+            restored.push_str(&line[offset..end]);
+        } else {
+            for cell in after[range].iter().flatten() {
+                restored.push_str(cell);
+            }
+        }
+        column += columns;
+    }
+    // the vertical lines moved past the end of the line
+    for cell in after[column..].iter().flatten() {
+        restored.push_str(cell);
+    }
+    restored.trim_end().to_string()
+}
+
+pub fn format_source(source: &str, tab_width: usize, style: CornerStyle) -> String {
+    let grid = Grid::from_str_with_tab_width(source, tab_width);
+    let before = Canvas::new(&grid);
+    let after = formatted_canvas(&grid, style);
+    let text = source.trim_start_matches('\u{feff}');
+    let bom = &source[..source.len() - text.len()];
+    let newline = match text.find('\n') {
+        Some(i) if text[..i].ends_with('\r') => "\r\n",
+        _ => "\n",
+    };
+    let mut lines: Vec<String> = text.lines()
+        .enumerate()
+        .map(|(y, line)| restore_line(line, tab_width, &before.rows[y], &after.rows[y]))
+        .collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    // without any line there is no line ending to add
+    if lines.is_empty() {
+        return bom.to_string();
+    }
+    format!("{}{}{}", bom, lines.join(newline), newline)
+}
+
+#[test]
+fn test_corner_styles(){
+    let bob = "+--.\n|  |\n'--+\n";
+    assert_eq!(format(&Grid::from_str(bob), CornerStyle::Plus), "+--+\n|  |\n+--+\n");
+    assert_eq!(format(&Grid::from_str(bob), CornerStyle::Rounded), ".--.\n|  |\n'--'\n");
+    assert_eq!(format(&Grid::from_str(bob), CornerStyle::BoxDrawing), "┌──┐\n│  │\n└──┘\n");
+    let boxes = "┌──┬──┐\n│  │  │\n├──┼──┤\n└──┴──┘\n";
+    assert_eq!(format(&Grid::from_str(boxes), CornerStyle::Plus),
+               "+--+--+\n|  |  |\n+--+--+\n+--+--+\n");
+    assert_eq!(format(&Grid::from_str(boxes), CornerStyle::BoxDrawing), boxes);
+}
+
+#[test]
+fn test_text_is_kept(){
+    let bob = "+-----------+\n| 'a+b' e.g.|\n+-----------+   end.   \n\n\n";
+    assert_eq!(format(&Grid::from_str(bob), CornerStyle::Rounded),
+               ".-----------.\n| 'a+b' e.g.|\n'-----------'   end.\n");
+}
+
+#[test]
+fn test_empty_source(){
+    assert_eq!(format_source("", 8, CornerStyle::Plus), "");
+    assert_eq!(format_source("\n\r\n  \n", 8, CornerStyle::Plus), "");
+    assert_eq!(format_source("\u{feff}", 8, CornerStyle::Plus), "\u{feff}");
+}
+
+#[test]
+fn test_source_is_kept(){
+    // the tabs, the control characters, the byte order mark and the line endings
+    // outside of the changed cells are written back as they were
+    let bob = "\u{feff}+--.\tnote\x07\r\n|  |\r\n'--+ \t\r\n\r\n";
+    assert_eq!(format_source(bob, 8, CornerStyle::Plus), "\u{feff}+--+\tnote\x07\r\n|  |\r\n+--+\r\n");
+    // the vertical line moved into a tab
+    let bob = "+--+\n|  |\n|\t|\n+--+\n";
+    assert_eq!(format_source(bob, 4, CornerStyle::Plus), "+--+\n|  |\n|  |\n+--+\n");
+    // the vertical line moved past the end of the line and the wide characters
+    let bob = "中 +--+\n中 |   |\n中 +--+\n";
+    assert_eq!(format_source(bob, 8, CornerStyle::Plus), "中 +--+\n中 |  |\n中 +--+\n");
+}
+
+#[test]
+fn test_align_vertical_lines(){
+    let bob = "+----+\n|    |\n|     |\n|   |\n+----+";
+    assert_eq!(format(&Grid::from_str(bob), CornerStyle::Plus),
+               "+----+\n|    |\n|    |\n|    |\n+----+\n");
+}
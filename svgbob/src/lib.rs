@@ -32,11 +32,15 @@
 #![deny(warnings)]
 extern crate svg;
 extern crate unicode_width;
+extern crate unicode_segmentation;
 
 
 
+use std::fmt;
+use std::io;
 use svg::Node;
 use svg::node::element::Circle as SvgCircle;
+use svg::node::element::Polygon as SvgPolygon;
 use svg::node::element::Path as SvgPath;
 use svg::node::element::Line as SvgLine;
 use svg::node::element::Text as SvgText;
@@ -45,15 +49,37 @@ use svg::node::element::SVG;
 use svg::node::element::Definitions;
 use svg::node::element::Marker;
 use optimizer::Optimizer;
+use bounds::Bounds;
 use self::Feature::Arrow;
 use self::Feature::Circle;
 use self::Feature::Nothing;
 use self::Stroke::Solid;
 use self::Stroke::Dashed;
+use self::Stroke::Heavy;
+use self::Stroke::HeavyDashed;
 use unicode_width::UnicodeWidthStr;
 use unicode_width::UnicodeWidthChar;
+use unicode_segmentation::UnicodeSegmentation;
+use rules::RuleSet;
+use rules::CustomRule;
+pub use rules::Anchor;
+pub use rules::Fragment;
+pub use rules::Neighborhood;
+pub use rules::anchor;
+pub use diagnostics::Diagnostic;
+pub use diagnostics::Severity;
+pub use explain::Explanation;
+pub use format::CornerStyle;
 
 mod optimizer;
+mod box_drawing;
+mod rules;
+mod diagnostics;
+mod explain;
+mod debug;
+mod format;
+mod unicode;
+mod bounds;
 
 
 /// generate an SVG from the ascii text input
@@ -68,19 +94,50 @@ mod optimizer;
 /// commercial version enhances memes automatically
 pub fn to_svg(input: &str) -> SVG {
     let settings = &Settings::default();
-    Grid::from_str(&input).get_svg(settings)
+    Grid::from_str(input).get_svg(settings)
 }
 
 pub fn to_svg_with_size(input: &str, text_width: f32, text_height: f32) -> SVG {
     let settings = &Settings::with_size(text_width, text_height);
-    Grid::from_str(&input).get_svg(settings)
+    Grid::from_str(input).get_svg(settings)
 }
 
 pub fn to_svg_with_size_nooptimization(input: &str, text_width: f32, text_height: f32) -> SVG {
-    let mut settings = Settings::no_optimization();
-    settings.text_width = text_width;
-    settings.text_height = text_height;
-    Grid::from_str(&input).get_svg(&settings)
+    let settings = Settings {
+        optimize: false,
+        compact_path: false,
+        ..Settings::with_size(text_width, text_height)
+    };
+    Grid::from_str(input).get_svg(&settings)
+}
+
+/// the source of the diagram formatted as `Grid::format` does,
+/// with the characters that are not changed kept as they are written:
+/// the tabs, the control characters, the byte order mark and the line endings.
+/// The trailing empty lines are dropped, a source without any line stays empty
+///
+/// ```
+/// use svgbob::CornerStyle;
+/// assert_eq!(svgbob::format_source("+--.\tnote\r\n'--+\r\n", 8, CornerStyle::Plus),
+///            "+--+\tnote\r\n+--+\r\n");
+/// ```
+pub fn format_source(source: &str, tab_width: usize, style: CornerStyle) -> String {
+    format::format_source(source, tab_width, style)
+}
+
+/// the widest tab stops, a larger tab width is clamped to it
+/// so a single tab can not expand into a line too long to be drawn
+pub const MAX_TAB_WIDTH: usize = 64;
+
+/// the cells of a line of the diagram, as the byte offset in the line where the cell starts
+/// and the columns it takes, to map the columns of the grid back to the text in an editor.
+/// The line is without the line ending and the byte order mark
+///
+/// ```
+/// assert_eq!(svgbob::cell_offsets("a\tb", 4), vec![(0, 1), (1, 1), (1, 1), (1, 1), (2, 1)]);
+/// ```
+pub fn cell_offsets(line: &str, tab_width: usize) -> Vec<(usize, usize)> {
+    line_gchars(line, tab_width).iter().map(|&(offset, ref gchar)| (offset, gchar.width)).collect()
 }
 
 
@@ -92,58 +149,197 @@ pub struct Settings {
     /// if optmization is enabled,
     /// true means all reduceable paths will be in 1 path definition
     compact_path: bool,
+    /// the font size of the text, derived from text_height unless set explicitly
+    font_size: f32,
+    /// the font family used in the text elements
+    font_family: String,
+    /// place every character of a text run at its own grid column
+    /// instead of stretching the whole run with textLength
+    exact_text_position: bool,
+    /// user defined rules, tried before the built-in ones
+    rules: Vec<CustomRule>,
+    /// draw the cell grid, the line and column numbers
+    /// and the anchor points below the diagram
+    debug: bool,
+    /// css added after the default styles
+    styles: Vec<String>,
+    /// size the svg to the drawn elements instead of the whole grid
+    crop: bool,
+    /// the space around the cropped elements
+    margin: f32,
+    /// scale the svg to the width of its container
+    responsive: bool,
 }
 
 impl Settings {
 
+    /// the font size is scaled along with the cell height
+    /// 14px font in a 16px high cell
     pub fn with_size(text_width: f32, text_height: f32) -> Self{
          Settings{
-            text_width: text_width,
-            text_height: text_height,
+            text_width,
+            text_height,
             optimize: true,
             compact_path: true,
+            font_size: text_height * 7.0 / 8.0,
+            font_family: "arial".into(),
+            exact_text_position: false,
+            rules: vec![],
+            debug: false,
+            styles: vec![],
+            crop: true,
+            margin: 8.0,
+            responsive: false,
          }
     }
+
+    /// use a monospace font, so the glyphs fits the cells
+    /// without too much stretching
+    pub fn monospace() -> Settings {
+        Settings {
+            font_family: "monospace".into(),
+            ..Settings::default()
+        }
+    }
+
+    pub fn set_font_size(&mut self, font_size: f32) {
+        self.font_size = font_size;
+    }
+
+    /// a list of font names as in css, it is escaped when written to the svg
+    pub fn set_font_family(&mut self, font_family: &str) {
+        self.font_family = font_family.into();
+    }
+
+    pub fn set_exact_text_position(&mut self, exact_text_position: bool) {
+        self.exact_text_position = exact_text_position;
+    }
+
+    /// merge the fragments of the neighboring cells into longer lines
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    /// put all the reduced paths in 1 path definition
+    pub fn set_compact_path(&mut self, compact_path: bool) {
+        self.compact_path = compact_path;
+    }
+
+    /// overlay the cell grid of text_width x text_height,
+    /// the line and column numbers on the right and bottom margins
+    /// and the 5x5 anchor points of every character,
+    /// to see where the fragments of the rules land
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+
+    /// size the svg to the bounding box of the drawn elements plus the margin,
+    /// the leading indentation and blank lines are left out.
+    /// Without cropping the svg covers the whole grid and 4 more columns and 2 more rows,
+    /// which is also the case with debug, to show the line and column numbers
+    pub fn set_crop(&mut self, crop: bool) {
+        self.crop = crop;
+    }
+
+    /// the space around the cropped elements, enough for the stroke width
+    /// and the arrow heads at the default of 8.
+    /// A negative, infinite or NaN margin is ignored
+    pub fn set_margin(&mut self, margin: f32) {
+        if margin.is_finite() && margin >= 0.0 {
+            self.margin = margin;
+        }
+    }
+
+    /// set the width to 100% and leave out the height,
+    /// so the svg is scaled to its container along the viewBox
+    pub fn set_responsive(&mut self, responsive: bool) {
+        self.responsive = responsive;
+    }
+
+    /// css added after the default styles, to restyle the diagram
+    ///
+    /// ```
+    /// let mut settings = svgbob::Settings::default();
+    /// settings.add_style("line, path { stroke: navy; }");
+    /// ```
+    pub fn add_style(&mut self, css: &str) {
+        self.styles.push(css.into());
+    }
+
+    /// draw the cells matching the predicate with the fragments,
+    /// the points of the fragments are in the 5x5 grid of the cell, see `anchor`.
+    /// Custom rules take precedence over the built-in rules,
+    /// the rule added last wins when more than one matches.
+    ///
+    /// ```
+    /// use svgbob::{Settings, Fragment};
+    /// use svgbob::anchor::{AXCY, EXCY};
+    ///
+    /// let mut settings = Settings::default();
+    /// // `@` is a service, drawn as a circle on the line
+    /// settings.add_rule(|n| n.this() == "@", &[Fragment::OpenCircle]);
+    /// // `%` is a queue, the line goes through it
+    /// settings.add_rule(|n| n.this() == "%" && n.left() == "-",
+    ///                   &[Fragment::Line(AXCY, EXCY), Fragment::OpenCircle]);
+    /// let svg = svgbob::Grid::from_str("--@--%--").get_svg(&settings);
+    /// ```
+    pub fn add_rule<F>(&mut self, predicate: F, fragments: &[Fragment])
+        where F: Fn(&Neighborhood) -> bool + 'static
+    {
+        self.rules.push(CustomRule::new(predicate, fragments));
+    }
+
     pub fn no_optimization() -> Settings {
-        let mut settings = Settings::default();
-        settings.optimize = false;
-        settings.compact_path = false;
-        settings
+        Settings {
+            optimize: false,
+            compact_path: false,
+            ..Settings::default()
+        }
     }
 
     pub fn separate_lines() -> Settings {
-        let mut settings = Settings::default();
-        settings.optimize = true;
-        settings.compact_path = false;
-        settings
+        Settings {
+            optimize: true,
+            compact_path: false,
+            ..Settings::default()
+        }
     }
 
     pub fn compact() -> Settings {
-        let mut settings = Settings::default();
-        settings.optimize = true;
-        settings.compact_path = true;
-        settings
+        Settings {
+            optimize: true,
+            compact_path: true,
+            ..Settings::default()
+        }
     }
 }
 
 impl Default for Settings {
     fn default() -> Settings {
-        Settings {
-            text_width: 8.0,
-            text_height: 16.0,
-            optimize: true,
-            compact_path: true,
-        }
+        Settings::with_size(8.0, 16.0)
     }
 }
 
 enum SvgElement {
     Circle(SvgCircle),
+    Polygon(SvgPolygon),
     Line(SvgLine),
     Path(SvgPath),
     Text(SvgText),
 }
 
+impl fmt::Display for SvgElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SvgElement::Circle(ref circle) => write!(f, "{}", circle),
+            SvgElement::Polygon(ref polygon) => write!(f, "{}", polygon),
+            SvgElement::Line(ref line) => write!(f, "{}", line),
+            SvgElement::Path(ref path) => write!(f, "{}", path),
+            SvgElement::Text(ref text) => write!(f, "{}", text),
+        }
+    }
+}
+
 
 #[derive(PartialEq)]
 #[derive(Debug)]
@@ -151,6 +347,10 @@ enum SvgElement {
 pub enum Stroke {
     Solid,
     Dashed,
+    /// thicker lines of the heavy unicode box drawing characters
+    Heavy,
+    /// thicker dashes of the heavy dashed box drawing characters
+    HeavyDashed,
 }
 
 
@@ -172,21 +372,38 @@ pub struct Point {
 }
 impl Point {
     fn new(x: f32, y: f32) -> Point {
-        Point { x: x, y: y }
+        Point { x, y }
+    }
+
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn y(&self) -> f32 {
+        self.y
     }
 }
 
 #[derive(Debug)]
 #[derive(Clone)]
-#[derive(PartialEq)]
+#[derive(PartialEq, Eq, Hash)]
 pub struct Loc {
     x: isize,
     y: isize,
 }
 
 impl Loc {
-    fn new(x: isize, y: isize) -> Loc {
-        Loc { x: x, y: y }
+    /// the cell at column x and line y, both starting at 0
+    pub fn new(x: isize, y: isize) -> Loc {
+        Loc { x, y }
+    }
+
+    pub fn x(&self) -> isize {
+        self.x
+    }
+
+    pub fn y(&self) -> isize {
+        self.y
     }
 
     pub fn top(&self) -> Loc {
@@ -260,6 +477,7 @@ impl Loc {
 #[derive(Clone)]
 pub enum Element {
     Circle(Point, f32, String),
+    Polygon(Vec<Point>, String),
     Line(Point, Point, Stroke, Feature),
     Arc(Point, Point, f32, bool),
     Text(Loc, String),
@@ -273,6 +491,12 @@ impl Element {
     fn open_circle(c: &Point, r: f32) -> Element{
         Element::Circle(c.clone(), r, "open".into())
     }
+    fn solid_polygon(points: &[Point]) -> Element{
+        Element::Polygon(points.to_vec(), "solid".into())
+    }
+    fn open_polygon(points: &[Point]) -> Element{
+        Element::Polygon(points.to_vec(), "open".into())
+    }
     fn solid_line(s: &Point, e: &Point) -> Element {
         Element::line(s, e, Solid, Nothing)
     }
@@ -358,11 +582,10 @@ impl Element {
                            stroke == stroke2 && *feature == Nothing
                            && *feature2 != Circle
                            {
-                            let reduced = Some(Element::Line(s.clone(),
-                                                             e2.clone(),
-                                                             stroke.clone(),
-                                                             feature2.clone()));
-                            reduced
+                            Some(Element::Line(s.clone(),
+                                               e2.clone(),
+                                               stroke.clone(),
+                                               feature2.clone()))
                         } else {
                             None
                         }
@@ -374,11 +597,10 @@ impl Element {
                 match *other {
                     Element::Text(ref loc2, ref text2) => {
                         // reduce if other is next to it
-                        let uwidth = text.width() as isize;
+                        let uwidth = text_width(text) as isize;
                         if loc.y == loc2.y && loc.x + uwidth == loc2.x {
                             let merged_text = text.clone() + text2;
-                            let reduced = Some(Element::Text(loc.clone(), merged_text));
-                            reduced
+                            Some(Element::Text(loc.clone(), merged_text))
                         } else {
                             None
                         }
@@ -402,6 +624,16 @@ impl Element {
 
                 SvgElement::Circle(svg_circle)
             },
+            Element::Polygon(ref points, ref class) => {
+                let points: Vec<String> = points.iter()
+                    .map(|p| format!("{},{}", p.x, p.y))
+                    .collect();
+                let svg_polygon = SvgPolygon::new()
+                    .set("class", class.clone())
+                    .set("points", points);
+
+                SvgElement::Polygon(svg_polygon)
+            },
             Element::Line(ref s, ref e, ref stroke, ref feature) => {
                 let mut svg_line = SvgLine::new()
                     .set("x1", s.x)
@@ -424,6 +656,14 @@ impl Element {
                         svg_line.assign("stroke-dasharray", (3, 3));
                         svg_line.assign("fill", "none");
                     }
+                    Heavy => {
+                        svg_line.assign("class", "heavy");
+                    }
+                    HeavyDashed => {
+                        svg_line.assign("class", "heavy");
+                        svg_line.assign("stroke-dasharray", HEAVY_DASHES);
+                        svg_line.assign("fill", "none");
+                    }
                 };
 
                 SvgElement::Line(svg_line)
@@ -444,12 +684,26 @@ impl Element {
                 SvgElement::Path(svg_arc)
             }
             Element::Text(ref loc, ref string) => {
-                let sx = loc.x as f32 * settings.text_width + settings.text_width / 4.0;
+                // the text run starts at the left edge of its first cell
+                // and is stretched to span exactly the number of columns it occupies
+                let sx = loc.x as f32 * settings.text_width;
                 let sy = loc.y as f32 * settings.text_height + settings.text_height * 3.0 / 4.0;
                 let mut svg_text = SvgText::new()
-                    .set("x", sx)
                     .set("y", sy);
-                let text_node = svg::node::Text::new(string.to_string());
+                if settings.exact_text_position {
+                    let xs: Vec<f32> = text_columns(string).iter()
+                        .map(|col| sx + *col as f32 * settings.text_width)
+                        .collect();
+                    svg_text.assign("x", xs);
+                } else {
+                    let text_length = text_width(string) as f32 * settings.text_width;
+                    svg_text.assign("x", sx);
+                    svg_text.assign("textLength", text_length);
+                }
+                // the spaces inside the text run are significant, but not the newlines
+                // surrounding the content of the text element, so only the run is preserved
+                let text_node = svg::node::Text::new(format!(r#"<tspan xml:space="preserve">{}</tspan>"#,
+                                                             escape_str(string)));
                 svg_text.append(text_node);
                 SvgElement::Text(svg_text)
             }
@@ -463,6 +717,13 @@ impl Element {
                     Dashed => {
                         path.assign("stroke-dasharray", (3, 3));
                     }
+                    Heavy => {
+                        path.assign("class", "heavy");
+                    }
+                    HeavyDashed => {
+                        path.assign("class", "heavy");
+                        path.assign("stroke-dasharray", HEAVY_DASHES);
+                    }
                 };
                 SvgElement::Path(path)
             }
@@ -471,6 +732,22 @@ impl Element {
 }
 
 
+/// the column offset of each character in the text run relative to the first one.
+/// wide characters (CJK) advance 2 columns,
+/// zero width characters are placed right after the character they combine with
+fn text_columns(s: &str) -> Vec<usize> {
+    let mut columns = Vec::with_capacity(s.len());
+    let mut column = 0;
+    for grapheme in s.graphemes(true) {
+        // the combining marks are placed along with their base character
+        for _ in grapheme.chars() {
+            columns.push(column);
+        }
+        column += grapheme_width(grapheme);
+    }
+    columns
+}
+
 // 3 points are collinear when the area of the triangle connecting them is 0;
 fn collinear(a: &Point, b: &Point, c: &Point) -> bool {
     a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y) == 0.0
@@ -486,28 +763,51 @@ pub struct GChar {
 }
 
 impl GChar{
-    fn new(ch:char) -> Self {
-        let mut s = String::new();
-        s.push(ch);
-        let width =UnicodeWidthStr::width(&*s);
-        GChar{
-            string: s,
-            width: width
-        }
-    }
-
+    /// s is an extended grapheme cluster
     fn from_str(s:&str) -> Self{
-        let width =UnicodeWidthStr::width(s);
         GChar{
             string: s.into(),
-            width: width
+            width: grapheme_width(s),
         }
     }
     
     fn push_str(self, s: &str) -> Self {
         let mut string = self.string.clone();
         string.push_str(s);
-        GChar::from_str(&string)
+        GChar{
+            string,
+            width: self.width,
+        }
+    }
+}
+
+/// the columns the text takes, the sum of the width of its grapheme clusters
+fn text_width(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_width).sum()
+}
+
+/// the columns a grapheme cluster takes on a terminal.
+/// Emoji sequences joined with a zero width joiner, with a skin tone modifier,
+/// an emoji presentation selector or a flag of 2 regional indicators are 2 columns wide,
+/// otherwise it is the width of the base character, the combining marks take no space
+fn grapheme_width(s: &str) -> usize {
+    let mut chars = s.chars();
+    let base = match chars.next() {
+        Some(base) => base,
+        None => return 0,
+    };
+    let is_regional_indicator = |ch: char| ('\u{1f1e6}'..='\u{1f1ff}').contains(&ch);
+    let is_emoji_sequence = s.chars().skip(1).any(|ch| {
+        ch == '\u{200d}' || ch == '\u{fe0f}' || ('\u{1f3fb}'..='\u{1f3ff}').contains(&ch)
+            || (is_regional_indicator(base) && is_regional_indicator(ch))
+    });
+    if is_emoji_sequence {
+        2
+    } else {
+        match base.width() {
+            Some(0) | None => UnicodeWidthStr::width(s),
+            Some(width) => width,
+        }
     }
 }
 
@@ -518,44 +818,38 @@ pub struct Grid {
     rows: usize,
     columns: usize,
     lines: Vec<Vec<GChar>>,
+    /// the index in the line of the character starting at each column,
+    /// None for the columns covered by a wide character
+    starts: Vec<Vec<Option<usize>>>,
+    /// the columns of the characters other than space on each line, in order
+    non_spaces: Vec<Vec<isize>>,
+    /// the columns of the quotes of the escaped text on each line,
+    /// the text in between is rendered as is
+    escapes: Vec<Vec<(isize, isize)>>,
+    /// the first and last columns of the words on each line,
+    /// the characters in a word are not drawn
+    words: Vec<Vec<(isize, isize)>>,
 }
 impl Grid {
-    /// instantiate a grid from input ascii textinstantiate a grid from input ascii text
+    /// instantiate a grid from input ascii text,
+    /// the tabs are expanded to tab stops every 8 columns
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Grid {
-        let lines: Vec<&str> = s.lines().collect();
-        let mut line_gchars = Vec::with_capacity(lines.len());
-        
-        for line in lines{
-            let mut gchars = Vec::with_capacity(line.len());
-            let mut zero_ch = None;
-            for ch in line.chars(){
-                if let Some(unicode_width) = ch.width(){
-                    // if width is zero add the char to previous buffer
-                    if unicode_width == 0 {
-                        zero_ch = Some(ch);
-                    }else{
-                        match zero_ch{
-                            Some(prefend_ch) => {
-                                let mut s = String::new();
-                                s.push(prefend_ch);
-                                s.push(ch);
-                                let last_gchar:Option<GChar> = gchars.pop();
-                                if let Some(last_gchar) = last_gchar{
-                                    let gchar = last_gchar.push_str(&s);
-                                    gchars.push(gchar);
-                                }
-                                zero_ch = None;
-                            }
-                            None => {
-                                let gchar = GChar::new(ch);
-                                gchars.push(gchar);
-                            }
-                        }
-                    }
-                }
-            } 
-            line_gchars.push(gchars);
-        }
+        Grid::from_str_with_tab_width(s, 8)
+    }
+
+    /// instantiate a grid from input ascii text,
+    /// expanding the tabs to the next multiple of tab_width columns,
+    /// tab_width is clamped to 1..=MAX_TAB_WIDTH.
+    /// The byte order mark and stray carriage returns are removed,
+    /// other control characters take up a column as a space
+    pub fn from_str_with_tab_width(s: &str, tab_width: usize) -> Grid {
+        let s = s.trim_start_matches('\u{feff}');
+        let line_gchars: Vec<Vec<GChar>> = s.lines()
+            .map(|line| {
+                line_gchars(line, tab_width).into_iter().map(|(_, gchar)| gchar).collect()
+            })
+            .collect();
         let mut max = 0;
         for lg in &line_gchars{
             let mut line_width = 0;
@@ -567,33 +861,120 @@ impl Grid {
             }
         }
 
+        let starts = line_gchars.iter()
+            .map(|line| {
+                let mut starts = vec![];
+                for (i, gchar) in line.iter().enumerate() {
+                    starts.push(Some(i));
+                    for _ in 1..gchar.width {
+                        starts.push(None);
+                    }
+                }
+                starts
+            })
+            .collect();
+        let non_spaces = line_gchars.iter()
+            .map(|line| {
+                let mut column = 0;
+                let mut non_spaces = vec![];
+                for gchar in line {
+                    if gchar.string != " " {
+                        non_spaces.push(column);
+                    }
+                    column += gchar.width as isize;
+                }
+                non_spaces
+            })
+            .collect();
+        let escapes = line_gchars.iter().map(|line| escaped_spans(line)).collect();
+        let words = line_gchars.iter().map(|line| word_spans(line)).collect();
+
         Grid {
             rows: line_gchars.len(),
             columns: max,
             lines: line_gchars,
+            starts,
+            non_spaces,
+            escapes,
+            words,
         }
     }
 
+    /// report the places in the diagram that are likely not drawn as intended:
+    /// dangling line ends, corners with less than 2 connections,
+    /// detached arrows, dashes too short to be drawn and ragged rows
+    pub fn diagnose(&self) -> Vec<Diagnostic> {
+        diagnostics::diagnose(self)
+    }
+
+    /// show how the character at loc is drawn: its neighborhood,
+    /// the rules that match it, the one selected
+    /// and the resulting elements before and after optimization
+    pub fn explain(&self, loc: &Loc, settings: &Settings) -> Option<Explanation> {
+        explain::explain(self, loc, settings)
+    }
+
+    /// the source of the diagram written consistently: the corners in the style,
+    /// the `|` a column off from the vertical line above moved under it
+    /// and the trailing whitespace removed.
+    /// The tabs are expanded and the lines end with `\n`,
+    /// `format_source` keeps the rest of the source as it is written
+    pub fn format(&self, style: CornerStyle) -> String {
+        format::format(self, style)
+    }
+
+    /// the diagram drawn again as text with the unicode box drawing characters,
+    /// the rounded corners and the arrows, each element in the cell it was read from.
+    /// The lines end with `\n`
+    pub fn get_unicode(&self, settings: &Settings) -> String {
+        let elements: Vec<Element> = self.get_all_elements(settings)
+            .into_iter()
+            .flat_map(|(_, elements)| elements)
+            .collect();
+        unicode::draw(&elements, settings)
+    }
+
+    /// the character starting at the column of loc
     fn get(&self, loc: &Loc) -> Option<&GChar> {
-        match self.lines.get(loc.y as usize) {
-            Some(line) => {
-                let mut total_width = 0;
-                for gchar in line{
-                    if total_width == loc.x{
-                        return Some(gchar)
-                    }
-                    total_width += gchar.width as isize;
-                }
-                None
-            }
-            None => None,
+        if loc.x < 0 || loc.y < 0 {
+            return None;
         }
+        let i = (*self.starts.get(loc.y as usize)?.get(loc.x as usize)?)?;
+        self.lines[loc.y as usize].get(i)
     }
 
 
+    /// the escape quote, which is not rendered
+    fn is_escape_quote(&self, loc: &Loc) -> bool {
+        match self.escapes.get(loc.y as usize) {
+            Some(spans) => spans.iter().any(|&(open, close)| loc.x == open || loc.x == close),
+            None => false,
+        }
+    }
+
+    /// the character is inside the quotes of escaped text
+    fn is_escaped(&self, loc: &Loc) -> bool {
+        match self.escapes.get(loc.y as usize) {
+            Some(spans) => spans.iter().any(|&(open, close)| loc.x > open && loc.x < close),
+            None => false,
+        }
+    }
+
+    /// the character is part of a word
+    fn is_in_word(&self, loc: &Loc) -> bool {
+        match self.words.get(loc.y as usize) {
+            Some(spans) => spans.iter().any(|&(start, end)| loc.x >= start && loc.x <= end),
+            None => false,
+        }
+    }
+
+    /// escaped text and words don't match any character class
     fn is_char<F>(&self, loc: &Loc, f: F) -> bool
         where F: Fn(&str) -> bool
     {
+        if self.is_escaped(loc) || self.is_escape_quote(loc) || self.is_in_word(loc) {
+            return false;
+        }
         if let Some(gchar) = self.get(loc){
              f(&gchar.string)
         }else{
@@ -601,1985 +982,84 @@ impl Grid {
         }
     }
 
+    /// the total width of the characters in this row
+    fn line_width(&self, y: isize) -> isize {
+        match self.starts.get(y as usize) {
+            Some(starts) => starts.len() as isize,
+            None => 0,
+        }
+    }
 
-    
+    /// the locations of the nearest non-space characters
+    /// on the left and on the right of this location, in the same row
+    fn nearest_non_spaces(&self, loc: &Loc) -> (Option<Loc>, Option<Loc>) {
+        let non_spaces = match self.non_spaces.get(loc.y as usize) {
+            Some(non_spaces) => non_spaces,
+            None => return (None, None),
+        };
+        let i = non_spaces.partition_point(|&x| x < loc.x);
+        let left = i.checked_sub(1).map(|i| Loc::new(non_spaces[i], loc.y));
+        let right = non_spaces[i..].iter().find(|&&x| x > loc.x).map(|&x| Loc::new(x, loc.y));
+        (left, right)
+    }
 
+    /// the character on this location is rendered as text
+    fn is_text(&self, loc: &Loc, settings: &Settings, rules: &RuleSet) -> bool {
+        match self.get_elements(loc.x, loc.y, settings, rules) {
+            Some(elements) => elements.iter().all(|elm| matches!(*elm, Element::Text(_, _))),
+            None => false,
+        }
+    }
 
+    /// a space is part of a text run when the nearest non-space characters
+    /// on both sides of it are text, and not drawing characters
+    fn is_space_in_text(&self, loc: &Loc, settings: &Settings, rules: &RuleSet) -> bool {
+        match self.nearest_non_spaces(loc) {
+            (Some(left), Some(right)) => {
+                self.is_text(&left, settings, rules) && self.is_text(&right, settings, rules)
+            }
+            _ => false,
+        }
+    }
 
-    /// get the elements on this location
-    /// variable names:
-    /// the grid is 8x8 divided into 4 equal parts at each vertical and horizontal dimension.
-    /// a,b,c,d,e  is start,quater,center,3quarters, end respectively
-    ///
-    /// combining [a,b,c,d,e] * h]
-    /// ah,bh,ch,dh,eh are horizontal increments derived from dividing the textwidth into 4 equal parts.
-    ///
-    /// combining [a,b,c,d,e] * [v]
-    /// av,bv,cv,dv,ev are vertical increments derived from diving the textheight into 4 equal parts
-    ///
-    /// combining [a,b,c,d,e] * [x] and [a,b,c,d,e] * [y]
-    /// and you will get the location of the points in the grid that describe the relative location
-    /// of the point from the starting location of the elements
-    /// all intersection and junction points fall exactly to any of the grid points
-    ///
-    fn get_elements(&self, x: isize, y: isize, settings: &Settings) -> Option<Vec<Element>> {
-        let text_width = settings.text_width;
-        let text_height = settings.text_height;
-        let measurex = x as f32 * text_width;
-        let measurey = y as f32 * text_height;
-        let arc_radius = text_width / 2.0;
-
-        // horizontal divide
-        let ah = 0.0;
-        let bh = text_width / 4.0;
-        let ch = text_width / 2.0;
-        let dh = text_width * 3.0 / 4.0;
-        let eh = text_width;
-
-        // vertical divide
-        let av = 0.0;
-        let bv = text_height / 4.0;
-        let cv = text_height / 2.0;
-        let dv = text_height * 3.0 / 4.0;
-        let ev = text_height;
-
-        let ax = measurex + ah;
-        let ay = measurey + av;
-        let bx = measurex + bh;
-        let by = measurey + bv;
-        let cx = measurex + ch;
-        let cy = measurey + cv;
-        let dx = measurex + dh;
-        let dy = measurey + dv;
-        let ex = measurex + eh;
-        let ey = measurey + ev;
-
-
-        // point locations
-        let center_top = &Point::new(cx, ay);
-        let center_bottom = &Point::new(cx, ey);
-        let mid_left = &Point::new(ax, cy);
-        let mid_right = &Point::new(ex, cy);
-        let high_left = &Point::new(ax, ay);
-        let high_right = &Point::new(ex, ay);
-        let low_left = &Point::new(ax, ey);
-        let low_right = &Point::new(ex, ey);
-
-        // point 5x5 locations
-        let axay = &Point::new(ax, ay);
-        let bxby = &Point::new(bx, by);
-        let cxcy = &Point::new(cx, cy);
-        let dxdy = &Point::new(dx, dy);
-        let exey = &Point::new(ex, ey);
-
-        let axcy = &Point::new(ax, cy);
-        let bxdy = &Point::new(bx, dy);
-        let bxcy = &Point::new(bx, cy);
-        let cxay = &Point::new(cx, ay);
-        let cxey = &Point::new(cx, ey);
-        let cxdy = &Point::new(cx, dy);
-        let cxby = &Point::new(cx, by);
-        let dxby = &Point::new(dx, by);
-        let dxcy = &Point::new(dx, cy);
-        let excy = &Point::new(ex, cy);
-        let dxey = &Point::new(dx, ey);
-        let dxay = &Point::new(dx, ay);
-        let bxay = &Point::new(bx, ay);
-        let bxey = &Point::new(bx, ey);
-        let axey = &Point::new(ax, ey);
-        let exay = &Point::new(ex, ay);
-
-        // extended points
-        let axbhey = &Point::new(ax - bh, ey);
-        let exbhey = &Point::new(ex + bh, ey);
-        let axbhay = &Point::new(ax - bh, ay);
-        let exbhay = &Point::new(ex + bh, ay);
-        let axchay = &Point::new(ax - ch, ay);
-        let exchey = &Point::new(ex + ch, ey);
-        let exchay = &Point::new(ex + ch, ay);
-        let axchey = &Point::new(ax - ch, ey);
-        let axehey = &Point::new(ax - eh, ey);
-        let axehay = &Point::new(ax - eh, ay);
-        let exdhey = &Point::new(ex + dh, ey);
-        let exehay = &Point::new(ex + eh, ay);
-        let axdhey = &Point::new(ax - dh, ey);
-        let exehey = &Point::new(ex + eh, ey);
-        let axdhay = &Point::new(ax - dh, ay);
-        let exdhay = &Point::new(ex + dh, ay);
-        let exchcy = &Point::new(ex + ch, cy);
-        let axchcy = &Point::new(ax - ch, cy);
-        let exchby = &Point::new(ex + ch, by);
-        let cxeybv = &Point::new(cx, ey + bv);
-        let cxeycv = &Point::new(cx, ey + cv);
-        let exchdy = &Point::new(ex + ch, dy);
-        let cxaybv = &Point::new(cx, ay - bv);
-        let axchby = &Point::new(ax - ch, by);
-        let axchdy = &Point::new(ax - ch, dy);
-        let cxaycv = &Point::new(cx, ay - cv);
-        let excheycv = &Point::new(ex + ch, ey + cv);
-        let axcheycv = &Point::new(ax - ch, ey + cv);
-        let axchaycv = &Point::new(ax - ch, ay - cv);
-        let exchaycv = &Point::new(ex + ch, ay - cv);
-
-
-        // grid lines
-        let axay_bxby = Element::solid_line(axay, bxby);
-        let cxcy_axcy = Element::solid_line(cxcy, axcy);
-        let cxcy_cxay = Element::solid_line(cxcy, cxay);
-        let cxcy_cxey = Element::solid_line(cxcy, cxey);
-        let cxcy_excy = Element::solid_line(cxcy, excy);
-
-        let cxdy_cxey = Element::solid_line(cxdy, cxey);
-        let cxay_cxby = Element::solid_line(cxay, cxby);
-        let dxby_exay = Element::solid_line(dxby, exay);
-        let axey_bxdy = Element::solid_line(axey, bxdy);
-        let exey_dxdy = Element::solid_line(exey, dxdy);
-        let dxcy_excy = Element::solid_line(dxcy, excy);
-        let bxcy_axcy = Element::solid_line(bxcy, axcy);
-        let exay_dxby = Element::solid_line(exay, dxby);
-        let cxey_cxdy = Element::solid_line(cxey, cxdy);
-        let dxdy_exey = Element::solid_line(dxdy, exey);
-        let cxcy_exey = Element::solid_line(cxcy, exey);
-        let cxcy_axey = Element::solid_line(cxcy, axey);
-        let axay_cxcy = Element::solid_line(axay, cxcy);
-        let cxcy_exay = Element::solid_line(cxcy, exay);
-        let cxay_cxcy = Element::solid_line(cxay, cxcy);
-        let axay_excy = Element::solid_line(axay, excy);
-        let axcy_exey = Element::solid_line(axcy, exey);
-        let axcy_exay = Element::solid_line(axcy, exay);
-        let axey_excy = Element::solid_line(axey, excy);
-        let exay_axcy = Element::solid_line(exay, axcy);
-        let excy_axey = Element::solid_line(excy, axey);
-        let axey_cxcy = Element::solid_line(axey, cxcy);
-        let cxey_cxcy = Element::solid_line(cxey, cxcy);
-        let axay_cxby = Element::solid_line(axay, cxby);
-        let cxby_exay = Element::solid_line(cxby, exay);
-        let axcy_cxdy = Element::solid_line(axcy, cxdy);
-        let cxdy_excy = Element::solid_line(cxdy, excy);
-        let cxdy_exey = Element::solid_line(cxdy, exey);
-        let cxdy_axcy = Element::solid_line(cxdy, axcy);
-        let cxdy_axey = Element::solid_line(cxdy, axey);
-        let cxby_axcy = Element::solid_line(cxby, axcy);
-        let cxby_excy = Element::solid_line(cxby, excy);
-
-        let axcy_exchby = Element::solid_line(axcy, exchby);
-        let cxdy_cxeybv = Element::solid_line(cxdy, cxeybv);
-        let cxaybv_cxby = Element::solid_line(cxaybv, cxby);
-
-        let exay_axehey = Element::solid_line(exay, axehey);
-        let axay_exehey = Element::solid_line(axay, exehey);
-        let axcy_exchcy = Element::solid_line(axcy, exchcy);
-        let axchey_exehey = Element::solid_line(axchey, exehey);
-        let excy_axchcy = Element::solid_line(excy, axchcy);
-        let cxdy_cxay = Element::solid_line(cxdy, cxay);
-        let axcy_exchdy = Element::solid_line(axcy, exchdy);
-        let axchby_excy = Element::solid_line(axchby, excy);
-        let axchdy_excy = Element::solid_line(axchdy, excy);
-        let cxay_cxeycv = Element::solid_line(cxay, cxeycv);
-        let cxaycv_cxey = Element::solid_line(cxaycv, cxey);
-        let axay_excheycv = Element::solid_line(axay, excheycv);
-        let exay_axcheycv = Element::solid_line(exay, axcheycv);
-        let exey_axchaycv = Element::solid_line(exey, axchaycv);
-        let axey_exchaycv = Element::solid_line(axey, exchaycv);
-
-        // common arc
-        let arc_axcy_dxby = Element::arc(axcy, dxby, arc_radius * 2.0, false);
-        let arc_bxby_excy = Element::arc(bxby, excy, arc_radius * 2.0, false);
-        let arc_axcy_bxby = Element::arc(axcy, bxby, arc_radius, false);
-        let arc_cxdy_axcy = Element::arc(cxdy, axcy, arc_radius, false);
-        let arc_cxby_excy = Element::arc(cxby, excy, arc_radius, false);
-        let arc_dxdy_axcy = Element::arc(dxdy, axcy, arc_radius * 2.0, false);
-        let arc_excy_cxdy = Element::arc(excy, cxdy, arc_radius, false);
-        let arc_excy_bxdy = Element::arc(excy, bxdy, arc_radius * 2.0, false);
-        let arc_dxby_excy = Element::arc(dxby, excy, arc_radius, false);
-        let arc_bxdy_axcy = Element::arc(bxdy, axcy, arc_radius, false);
-        let arc_excy_dxdy = Element::arc(excy, dxdy, arc_radius, false);
-        let arc_dxcy_bxdy = Element::arc(dxcy, bxdy, arc_radius * 2.0, false);
-        let arc_dxdy_bxcy = Element::arc(dxdy, bxcy, arc_radius * 2.0, false);
-        let arc_bxby_dxcy = Element::arc(bxby, dxcy, arc_radius * 2.0, false);
-        let arc_bxdy_bxby = Element::arc(bxdy, bxby, arc_radius * 2.0, false);
-        let arc_dxby_dxdy = Element::arc(dxby, dxdy, arc_radius * 2.0, false);
-        let arc_dxby_cxdy = Element::arc(dxby, cxdy, arc_radius * 4.0, false);
-        let arc_bxdy_cxby = Element::arc(bxdy, cxby, arc_radius * 4.0, false);
-        let arc_cxby_dxdy = Element::arc(cxby, dxdy, arc_radius * 4.0, false);
-        let arc_cxdy_bxby = Element::arc(cxdy, bxby, arc_radius * 4.0, false);
-        let arc_dxay_dxey = Element::arc(dxay, dxey, arc_radius * 4.0, false);
-        let arc_bxey_bxay = Element::arc(bxey, bxay, arc_radius * 4.0, false);
-        let arc_excy_axcy = Element::arc(excy, axcy, arc_radius * 4.0, false);
-        let arc_axcy_excy = Element::arc(axcy, excy, arc_radius * 4.0, false);
-        let arc_bxcy_dxby = Element::arc(bxcy, dxby, arc_radius * 4.0, false);
-        let arc_axcy_axay = Element::arc(axcy, axay, arc_radius * 4.0, false);
-        let arc_axey_exey = Element::arc(axey, exey, arc_radius * 4.0, false);
-        let arc_cxay_exey = Element::arc(cxay, exey, arc_radius * 8.0, false);
-        let arc_exay_cxey = Element::arc(exay, cxey, arc_radius * 8.0, false);
-        let arc_cxey_axay = Element::arc(cxey, axay, arc_radius * 8.0, false);
-        let arc_axey_cxay = Element::arc(axey, cxay, arc_radius * 8.0, false);
-        let arc_cxey_axcy = Element::arc(cxey, axcy, arc_radius * 2.0, false);
-        let arc_axcy_cxay = Element::arc(axcy, cxay, arc_radius * 2.0, false);
-        let arc_excy_cxey = Element::arc(excy, cxey, arc_radius * 2.0, false);
-        let arc_cxay_excy = Element::arc(cxay, excy, arc_radius * 2.0, false);
-
-        // extended arc
-        let arc_exdhey_axehay = Element::arc(exdhey, axehay, arc_radius * 10.0, false);
-        let arc_exchey_dxay = Element::arc(exchey, dxay, arc_radius * 10.0, false);
-        let arc_exehay_axdhey = Element::arc(exehay, axdhey, arc_radius * 10.0, false);
-        let arc_bxay_axchey = Element::arc(bxay, axchey, arc_radius * 10.0, false);
-        let arc_axdhay_exehey = Element::arc(axdhay, exehey, arc_radius * 10.0, false);
-        let arc_axchay_bxey = Element::arc(axchay, bxey, arc_radius * 10.0, false);
-        let arc_axehey_exdhay = Element::arc(axehey, exdhay, arc_radius * 10.0, false);
-        let arc_dxey_exchay = Element::arc(dxey, exchay, arc_radius * 10.0, false);
-        let arc_axey_cxdy = Element::arc(axey, cxdy, arc_radius, false);
-        let arc_cxdy_exey = Element::arc(cxdy, exey, arc_radius, false);
-        let arc_excy_axbhey = Element::arc(excy, axbhey, arc_radius * 4.0, false);
-        let arc_exbhey_axcy = Element::arc(exbhey, axcy, arc_radius * 4.0, false);
-        let arc_axbhay_excy = Element::arc(axbhay, excy, arc_radius * 4.0, false);
-        let arc_axcy_exbhay = Element::arc(axcy, exbhay, arc_radius * 4.0, false);
-        let arc_axcy_cxby = Element::arc(axcy, cxby, arc_radius, false);
-
-        // common path lines
-        let vertical = Element::solid_line(center_top, center_bottom);
-        let horizontal = Element::solid_line(mid_left, mid_right);
-        let slant_left = Element::solid_line(high_left, low_right);
-        let slant_right = Element::solid_line(low_left, high_right);
-        let low_horizontal = Element::solid_line(low_left, low_right);
-
-        // extended lines
-        let low_horizontal_extend_left_half = Element::solid_line(low_right,
-                                                                  &Point::new(ax - ch, ey));
-        let low_horizontal_extend_right_half = Element::solid_line(low_left,
-                                                                   &Point::new(ex + ch, ey));
-        let low_horizontal_extend_left_full = Element::solid_line(low_right,
-                                                                  &Point::new(ax - eh, ey));
-        let low_horizontal_extend_right_full = Element::solid_line(low_left,
-                                                                   &Point::new(ex + eh, ey));
-
-        // dashed lines
-        let vertical_dashed = Element::line(center_top, center_bottom, Dashed, Nothing);
-        let horizontal_dashed = Element::line(mid_left, mid_right, Dashed, Nothing);
-        let low_horizontal_dashed = Element::line(low_left, low_right, Dashed, Nothing);
-
-        let arrow_down = Element::line(center_top,
-                                       center_bottom,
-                                       Solid,
-                                       Arrow);
-        let arrow_down_dashed = Element::line(center_top,
-                                              center_bottom,
-                                              Dashed,
-                                              Arrow);
-        let arrow_up = Element::line(center_bottom,
-                                     center_top,
-                                     Solid,
-                                     Arrow);
-        let arrow_up_dashed = Element::line(center_bottom,
-                                            center_top,
-                                            Dashed,
-                                            Arrow);
-        let arrow_left = Element::line(mid_right, cxcy, Solid, Arrow);
-        let arrow_left_dashed =
-            Element::line(mid_right, cxcy, Dashed, Arrow);
-        let arrow_right = Element::line(mid_left, cxcy, Solid, Arrow);
-        let arrow_right_dashed =
-            Element::line(mid_left, cxcy, Dashed, Arrow);
-        let arrow_bottom_left =
-            Element::line(high_right, cxcy, Solid, Arrow);
-        let arrow_bottom_right =
-            Element::line(high_left, cxcy, Solid, Arrow);
-        let arrow_top_left = Element::line(low_right, cxcy, Solid, Arrow);
-        let arrow_top_right = Element::line(low_left, cxcy, Solid, Arrow);
-
-        let junction_circle = Element::solid_circle(cxcy,ch); 
-        let open_junction = Element::open_circle(cxcy,ch); 
-
-        // relative location of characters
+    /// get the elements on this location,
+    /// drawn by the highest priority rule that matches the cell
+    /// otherwise the character is rendered as text
+    fn get_elements(&self, x: isize, y: isize, settings: &Settings, rules: &RuleSet) -> Option<Vec<Element>> {
         let this = &Loc::new(x, y);
-        let top = &this.top();
-        let left = &this.left();
-        let bottom = &this.bottom();
-        let right = &this.right();
-        let top_left = &this.top_left();
-        let top_right = &this.top_right();
-        let bottom_left = &this.bottom_left();
-        let bottom_right = &this.bottom_right();
-
-        // left of left
-        let left_left = &this.left().left();
-        let right_right = &this.right().right();
-
-        let connects_left = self.is_char(left, is_horizontal);
-        let connects_right = self.is_char(right, is_horizontal);
-        let connects_top = self.is_char(top, is_vertical);
-        let connects_bottom = self.is_char(bottom, is_vertical);
-        let connects_top_left = self.is_char(top_left, is_slant_left);
-        let connects_top_right = self.is_char(top_right, is_slant_right);
-        let connects_bottom_left = self.is_char(bottom_left, is_slant_right);
-        let connects_bottom_right = self.is_char(bottom_right, is_slant_left);
-        let connects_major4 = connects_left || connects_right || connects_top || connects_bottom;
-        let connects_aux4 = connects_top_left || connects_top_right || connects_bottom_left || connects_bottom_right;
-        let connects = connects_major4 || connects_aux4;
-
-        // FIXME: need more exhaustive list, for use case that makes sense matching
-        let match_list: Vec<(bool, Vec<Element>)> = 
-            vec![
-                /*
-
-                 \|/
-                 -*- (asterisk)
-                 /|\
-
-                */
-                (self.is_char(this, is_asterisk) && connects,
-                 vec![junction_circle.clone()]
-                ),
-                /*
-                  
-                \|/
-                -o-
-                /|\
-
-                */
-                (self.is_char(this, is_o) && connects,
-                 vec![open_junction.clone()]
-                ),
-                /*
-                    |
-                */
-                (self.is_char(this, is_vertical),
-                 vec![vertical.clone()]
-                ),
-                /*
-                    -
-                */
-                (self.is_char(this, is_horizontal),
-                 vec![horizontal.clone()]
-                ),
-
-                /*
-                    _
-                */
-                (self.is_char(this, is_low_horizontal),
-                 vec![low_horizontal.clone()]
-                ),
-                /*
-                   :
-                   :
-                   must have at least 1 align to it to be treated as vertical 
-                */
-                (self.is_char(this, is_vertical_dashed)
-                  && (self.is_char(top, is_vertical_dashed)
-                     || self.is_char(bottom, is_vertical_dashed)),
-                  vec![vertical_dashed.clone()]
-                ),
-
-                /*
-                   ==  at least 2 next to it
-                */
-                (self.is_char(this, is_horizontal_dashed)
-                  && ((self.is_char(left, is_horizontal_dashed)
-                     && self.is_char(right, is_horizontal_dashed)
-                     )
-                     ||
-                     (self.is_char(left, is_horizontal_dashed)
-                     && self.is_char(left_left, is_horizontal_dashed)
-                     )
-                     ||
-                     (self.is_char(right, is_horizontal_dashed)
-                      && self.is_char(right_right, is_horizontal_dashed)
-                     )
-                     ),
-                  vec![horizontal_dashed.clone()]
-                ),
-                /*
-                   ...  at least 2 next to it
-                */
-                (self.is_char(this, is_low_horizontal_dashed)
-                  && ((self.is_char(left, is_low_horizontal_dashed) //left & right
-                      && self.is_char(right, is_low_horizontal_dashed)
-                      )
-                     || 
-                      (self.is_char(left, is_low_horizontal_dashed)
-                       && self.is_char(left_left, is_low_horizontal_dashed)
-                      )
-                     || 
-                      (self.is_char(right, is_low_horizontal_dashed)
-                       && self.is_char(right_right, is_low_horizontal_dashed)
-                      )
-                     ),
-                  vec![low_horizontal_dashed.clone()]
-                ),
-                /*
-                    /
-                */
-                (self.is_char(this, is_slant_right),
-                 vec![slant_right.clone()]
-                ),
-                /*
-                    \
-                */
-                (self.is_char(this, is_slant_left),
-                 vec![slant_left.clone()]
-                ),
-                /*
-                     _ 
-                      `- 
-                   
-                */
-                (self.is_char(this, is_backtick)
-                 && self.is_char(top_left, is_low_horizontal)
-                 && self.is_char(right, is_horizontal),
-                 vec![axay_excy.clone()]
-                ),
-                /*
-                       __
-                    --'
-                   
-                */
-                (self.is_char(this, is_high_round)
-                 && self.is_char(top_right, is_low_horizontal)
-                 && self.is_char(left, is_horizontal),
-                 vec![axcy_exay.clone()]
-                ),
-                /*
-                     -._ 
-                   
-                */
-                (self.is_char(this, is_low_round)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(right, is_low_horizontal),
-                 vec![axcy_exey.clone()]
-                ),
-                /*
-                     _.-
-                   
-                */
-                (self.is_char(this, is_low_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(left, is_low_horizontal),
-                 vec![axey_excy.clone()]
-                ),
-                /*
-                    ^
-                    |
-                */
-                (self.is_char(this, is_arrow_up)
-                 && self.is_char(bottom, is_vertical),
-                 vec![arrow_up.clone()]
-                ),
-                /*
-                    ^
-                    :
-                */
-                (self.is_char(this, is_arrow_up)
-                 && self.is_char(bottom, is_vertical_dashed),
-                 vec![arrow_up_dashed.clone()]
-                ),
-                /*
-                    |
-                    V
-                */
-                (self.is_char(this, is_arrow_down)
-                 && self.is_char(top, is_vertical),
-                 vec![arrow_down.clone()]
-                ),
-
-                /*
-                    :
-                    V
-                */
-                (self.is_char(this, is_arrow_down)
-                 && self.is_char(top, is_vertical_dashed),
-                 vec![arrow_down_dashed.clone()]
-                ),
-                /*
-                    <-
-                     
-                */
-                (self.is_char(this, is_arrow_left)
-                 && self.is_char(right, is_horizontal),
-                 vec![arrow_left.clone()]
-                ),
-                /*
-                    <=
-                     
-                */
-                (self.is_char(this, is_arrow_left)
-                 && self.is_char(right, is_horizontal_dashed),
-                 vec![arrow_left_dashed.clone()]
-                ),
-                /*
-                    ->
-                     
-                */
-                (self.is_char(this, is_arrow_right)
-                 && self.is_char(left, is_horizontal),
-                 vec![arrow_right.clone()]
-                ),
-                /*
-                    =>
-                     
-                */
-                (self.is_char(this, is_arrow_right)
-                 && self.is_char(left, is_horizontal_dashed),
-                 vec![arrow_right_dashed.clone()]
-                ),
-                /*
-                    ^
-                     \
-                */
-                (self.is_char(this, is_arrow_up)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![arrow_top_left.clone()]
-                ),
-                /*
-                      ^
-                     /
-                */
-                (self.is_char(this, is_arrow_up)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![arrow_top_right.clone()]
-                ),
-                /*
-                      /
-                     V 
-                */
-                (self.is_char(this, is_arrow_down)
-                 && self.is_char(top_right, is_slant_right),
-                 vec![arrow_bottom_left.clone()]
-                ),
-                /*
-                      \
-                       V 
-                */
-                (self.is_char(this, is_arrow_down)
-                 && self.is_char(top_left, is_slant_left),
-                 vec![arrow_bottom_right.clone()]
-                ),
-                /*
-                       _  or |_
-                      |
-                */
-                (self.is_char(this, is_low_horizontal)
-                 && (self.is_char(bottom_left, is_vertical)
-                    || self.is_char(left, is_vertical)
-                    ),
-                 vec![low_horizontal_extend_left_half.clone()]
-                ),
-                /*
-                       _  or _|
-                        |
-                */
-                (self.is_char(this, is_low_horizontal)
-                 && (self.is_char(bottom_right, is_vertical)
-                    || self.is_char(right, is_vertical)
-                    ),
-                 vec![low_horizontal_extend_right_half.clone()]
-                ),
-                /*
-                      -| 
-                       
-                */
-                (self.is_char(this, is_horizontal)
-                 && self.is_char(right, is_vertical),
-                 vec![axcy_exchcy.clone()]
-                ),
-                /*
-                      |-
-                       
-                */
-                (self.is_char(this, is_horizontal)
-                 && self.is_char(left, is_vertical),
-                 vec![excy_axchcy.clone()]
-                ),
-                /*
-                       /_
-                     
-                */
-                (self.is_char(this, is_low_horizontal)
-                 && self.is_char(left, is_slant_right),
-                 vec![low_horizontal_extend_left_full.clone()]
-                ),
-                /*
-                       /_
-                     
-                */
-                (self.is_char(this, is_slant_right)
-                 && self.is_char(right, is_low_horizontal),
-                 vec![slant_right.clone(), low_horizontal_extend_right_full.clone()] 
-                ),
-                /*
-                       _\
-                     
-                */
-                (self.is_char(this, is_low_horizontal)
-                 && self.is_char(right, is_slant_left),
-                 vec![low_horizontal_extend_right_full.clone()]
-                ),
-                /*
-                       |
-                       \
-                     
-                */
-                (self.is_char(this, is_slant_left)
-                 && self.is_char(top, is_vertical),
-                 vec![cxcy_exey.clone()]
-                ),
-                /*
-                       |
-                       \
-                     
-                */
-                (self.is_char(this, is_vertical)
-                 && self.is_char(bottom, is_slant_left),
-                 vec![cxay_cxeycv.clone()]
-                ),
-                /*
-                       |
-                       /
-                     
-                */
-                (self.is_char(this, is_slant_right)
-                 && self.is_char(top, is_vertical),
-                 vec![cxcy_axey.clone()]
-                ),
-                /*
-                       |
-                       /
-                     
-                */
-                (self.is_char(this, is_vertical)
-                 && self.is_char(bottom, is_slant_right),
-                 vec![cxay_cxeycv.clone()]
-                ),
-                /*
-                      |
-                       \
-                     
-                */
-                (self.is_char(this, is_slant_left)
-                 && self.is_char(top_left, is_vertical),
-                 vec![exey_axchaycv.clone()]
-                ),
-                /*
-                      |
-                       \
-                     
-                */
-                (self.is_char(this, is_vertical)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![cxay_cxcy.clone()]
-                ),
-                /*
-                        |
-                       /
-                     
-                */
-                (self.is_char(this, is_slant_right)
-                 && self.is_char(top_right, is_vertical),
-                 vec![axey_exchaycv.clone()]
-                ),
-                /*
-                        |
-                       /
-                     
-                */
-                (self.is_char(this, is_vertical)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![cxay_cxcy.clone()]
-                ),
-                /*
-                       /
-                       |
-                     
-                */
-                (self.is_char(this, is_slant_right)
-                 && self.is_char(bottom, is_vertical),
-                 vec![cxcy_exay.clone()]
-                ),
-                /*
-                       /
-                       |
-                     
-                */
-                (self.is_char(this, is_vertical)
-                 && self.is_char(top, is_slant_right),
-                 vec![cxaycv_cxey.clone()]
-                ),
-                /*
-                       \
-                       |
-                     
-                */
-                (self.is_char(this, is_slant_left)
-                 && self.is_char(bottom, is_vertical),
-                 vec![axay_cxcy.clone()]
-                ),
-                /*
-                       \
-                       |
-                     
-                */
-                (self.is_char(this, is_vertical)
-                 && self.is_char(top, is_slant_left),
-                 vec![cxaycv_cxey.clone()]
-                ),
-                /*
-                       \
-                        |
-                     
-                */
-                (self.is_char(this, is_slant_left)
-                 && self.is_char(bottom_right, is_vertical),
-                 vec![axay_excheycv.clone()]
-                ),
-                /*
-                       \
-                        |
-                     
-                */
-                (self.is_char(this, is_vertical)
-                 && self.is_char(top_left, is_slant_left),
-                 vec![cxcy_cxey.clone()]
-                ),
-                /*
-                         /
-                        |
-                     
-                */
-                (self.is_char(this, is_slant_right)
-                 && self.is_char(bottom_left, is_vertical),
-                 vec![exay_axcheycv.clone()]
-                ),
-                /*
-                         /
-                        |
-                     
-                */
-                (self.is_char(this, is_vertical)
-                 && self.is_char(top_right, is_slant_right),
-                 vec![cxcy_cxey.clone()]
-                ),
-                /*
-                        ,     .
-                      ,'    .'
-                */
-                (self.is_char(this, is_high_round)
-                 &&((self.is_char(left, is_comma)
-                      || self.is_char(left, is_low_round)
-                    )
-                ||(self.is_char(top_right, is_comma)
-                     || self.is_char(top_right, is_low_round)
-                  )
-                ),
-                 vec![exay_axcy.clone()]
-                ),
-                /*
-                      ,'    .'
-                     '     '
-                */
-                ((self.is_char(this, is_comma)
-                    ||self.is_char(this, is_low_round)
-                 )
-                &&(self.is_char(right, is_high_round)
-                   ||self.is_char(bottom_left, is_high_round)
-                ),
-                 vec![excy_axey.clone()]
-                ),
-                /*
-                    `.
-                      `
-                */
-                (self.is_char(this, is_period)
-                 &&(self.is_char(bottom_right, is_backtick)
-                 || self.is_char(left, is_backtick)),
-                 vec![axcy_exey.clone()]
-                ),
-                /*
-                    .
-                     `.
-                       
-                */
-                (self.is_char(this, is_backtick)
-                 && (self.is_char(right, is_period)
-                 || self.is_char(top_left, is_period)),
-                 vec![axay_excy.clone()]
-                ),
-                /*
-                      ,     .
-                    /'    /'
-                    
-                */
-                (self.is_char(this, is_high_round)
-                 && (self.is_char(top_right, is_comma)
-                    || self.is_char(top_right, is_low_round)
-                    )
-                 && self.is_char(left, is_slant_right),
-                 vec![exay_axehey.clone()]
-                ),
-                /*
-                    _
-                     `.
-                */
-                (self.is_char(this, is_backtick)
-                 && self.is_char(top_left, is_low_horizontal)
-                 &&self.is_char(right, is_low_round),
-                 vec![axay_excy.clone()]
-                ),
-                /*
-                    speech bubble
-                     .
-                      `\
-                */
-                (self.is_char(this, is_backtick)
-                 && self.is_char(top_left, is_low_round)
-                 && self.is_char(right, is_slant_left),
-                 vec![axay_exehey.clone()]
-                ),
-                /*
-                    speech bubble
-                     _
-                      `\
-                */
-                (self.is_char(this, is_backtick)
-                 && self.is_char(top_left, is_low_horizontal)
-                 && self.is_char(right, is_slant_left),
-                 vec![axay_exehey.clone()]
-                ),
-                /*
-                      `.
-                        \
-                     
-                */
-                (self.is_char(this, is_low_round)
-                 && self.is_char(left, is_backtick)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![arc_exdhey_axehay.clone()]
-                ),
-                /*
-                      `.
-                        \
-                     
-                */
-                (self.is_char(this, is_backtick)
-                 && self.is_char(right, is_low_round)
-                 && self.is_char(&right.bottom_right(), is_slant_left),
-                 vec![]
-                ),
-                /*
-                      `.
-                        \
-                     
-                */
-                (self.is_char(this, is_slant_left)
-                 && self.is_char(top_left, is_low_round)
-                 && self.is_char(&top_left.left(), is_backtick),
-                 vec![arc_exchey_dxay.clone()]
-                ),
-                /*
-                    ,' 
-                   /  
-                     
-                */
-                (self.is_char(this, is_comma)
-                 && self.is_char(right, is_high_round)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![arc_exehay_axdhey.clone()]
-                ),
-                /*
-                    ,' 
-                   /  
-                     
-                */
-                (self.is_char(this, is_high_round)
-                 && self.is_char(left, is_comma)
-                 && self.is_char(&left.bottom_left(), is_slant_right),
-                 vec![]
-                ),
-                /*
-                    ,' 
-                   /  
-                     
-                */
-                (self.is_char(this, is_slant_right)
-                 && self.is_char(top_right, is_comma)
-                 && self.is_char(&top_right.right(), is_high_round),
-                 vec![arc_bxay_axchey.clone()]
-                ),
-                /*
-                   \    \
-                    `.   ',
-                     
-                */
-                ((self.is_char(this, is_high_round) 
-                 || self.is_char(this, is_backtick)
-                 )
-                 && (self.is_char(right, is_low_round)
-                    || self.is_char(right, is_comma)
-                    )
-                 && self.is_char(top_left, is_slant_left),
-                 vec![arc_axdhay_exehey.clone()]
-                ),
-                /*
-                   \    \
-                    `.   ',
-                     
-                */
-                ((self.is_char(left, is_high_round) 
-                 || self.is_char(left, is_backtick)
-                 )
-                 && (self.is_char(this, is_low_round)
-                    || self.is_char(this, is_comma)
-                    )
-                 && self.is_char(&left.top_left(), is_slant_left),
-                 vec![]
-                ),
-                /*
-                   \    \
-                    `.   ',
-                     
-                */
-                ((self.is_char(bottom_right, is_high_round) 
-                 || self.is_char(bottom_right, is_backtick)
-                 )
-                 && (self.is_char(&bottom_right.right(), is_low_round)
-                    || self.is_char(&bottom_right.right(), is_comma)
-                    )
-                 && self.is_char(this, is_slant_left),
-                 vec![arc_axchay_bxey.clone()]
-                ),
-                /*
-                       /   /
-                     .'  ,'
-                */
-                (self.is_char(this, is_high_round)
-                 && (self.is_char(left, is_low_round)
-                    || self.is_char(left, is_comma)
-                    )
-                 && self.is_char(top_right, is_slant_right),
-                 vec![arc_axehey_exdhay.clone()]
-                ),
-                /*
-                       /   /
-                     .'  ,'
-                */
-                (self.is_char(this, is_slant_right)
-                 && (self.is_char(&bottom_left.left(), is_low_round)
-                    || self.is_char(&bottom_left.left(), is_comma)
-                    )
-                 && self.is_char(bottom_left, is_high_round),
-                 vec![arc_dxey_exchay.clone()]
-                ),
-                /*
-                       /   /
-                     .'  ,'
-                */
-                (self.is_char(&right.top_right(), is_slant_right)
-                 && (self.is_char(this, is_low_round)
-                    || self.is_char(this, is_comma)
-                    )
-                 && self.is_char(right, is_high_round),
-                 vec![]
-                ),
-                /*
-                    . ,   . ,   
-                     '     `
-                       
-                */
-                ((self.is_char(this, is_high_round)
-                    ||self.is_char(this, is_backtick)
-                 )
-                 && self.is_char(top_right, is_comma)
-                 && self.is_char(top_left, is_low_round),
-                 vec![axay_cxby.clone(), cxby_exay.clone()]
-                ),
-                /*
-                 
-                  '.'  `.'
-                 
-                */
-                (self.is_char(this, is_low_round)
-                 && (self.is_char(left, is_high_round)
-                    || self.is_char(left, is_backtick)
-                    )
-                 && self.is_char(right, is_high_round),
-                 vec![axcy_cxdy.clone(), cxdy_excy.clone()]
-                ),
-                /*
-
-                    .'    or  .'
-                     `         '
-
-                 */
-                 (self.is_char(this, is_low_round)
-                  && self.is_char(right, is_high_round)
-                  && (self.is_char(bottom_right, is_high_round)
-                     ||self.is_char(bottom_right, is_backtick)),
-                  vec![cxdy_excy.clone(), cxdy_exey.clone()]
-                 ),
-                /*
-
-                     `,   or   `.  or  '.
-                     '         '       '
-
-                 */
-                 ((self.is_char(this, is_low_round)
-                    || self.is_char(this, is_comma))
-                  && (self.is_char(left, is_high_round)
-                    || self.is_char(left, is_backtick))
-                  && self.is_char(bottom_left, is_high_round),
-                  vec![cxdy_axcy.clone(), cxdy_axey.clone()]
-                 ),
-                 /*
-                      .       .  
-                     ' `  or ' '  
-                  
-                  */
-                 (self.is_char(this, is_low_round)
-                  && self.is_char(bottom_left, is_high_round)
-                  && (self.is_char(bottom_right, is_high_round)
-                     || self.is_char(bottom_right, is_backtick)),
-                  vec![cxdy_axey.clone(), cxdy_exey.clone()]
-                 ),
-
-                 /*
-                     .'.  or    ,'.
-                  
-                  */
-                 ((self.is_char(this, is_high_round)
-                    || self.is_char(this, is_backtick))
-                  && self.is_char(right, is_low_round)
-                  && (self.is_char(left, is_low_round)
-                     || self.is_char(left, is_comma)),
-                  vec![cxby_axcy.clone(), cxby_excy.clone()]
-                 ),
-
-                /*
-                      +-
-                      | 
-                */
-                (self.is_char(this, is_intersection)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(bottom, is_vertical),
-                 vec![cxcy_cxey.clone(), cxcy_excy.clone()]
-                ),
-                /*
-                     -+
-                      | 
-                */
-                (self.is_char(this, is_intersection)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(bottom, is_vertical),
-                 vec![cxcy_cxey.clone(), cxcy_axcy.clone()]
-                ),
-                /*
-                     |
-                     +-
-                */
-                (self.is_char(this, is_intersection)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top, is_vertical),
-                 vec![cxcy_cxay.clone(), cxcy_excy.clone()]
-                ),
-                /*
-                     |
-                    -+
-                */
-                (self.is_char(this, is_intersection)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top, is_vertical),
-                 vec![cxcy_cxay.clone(), cxcy_axcy.clone()]
-                ),
-                /*
-                      .-   ,-
-                      |    |
-                */
-                ((self.is_char(this, is_round)
-                 || self.is_char(this, is_comma)
-                 )
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(bottom, is_vertical),
-                 vec![cxdy_cxey.clone(), arc_excy_cxdy.clone()]
-                ),
-                /*
-                      -.
-                       | 
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(bottom, is_vertical),
-                 vec![cxdy_cxey.clone(), arc_cxdy_axcy.clone()]
-                ),
-                /*
-                     |       |
-                     '-      `-
-                */
-                ((self.is_char(this, is_round)
-                  || self.is_char(this, is_backtick)
-                  )
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top, is_vertical),
-                 vec![cxay_cxby.clone(), arc_cxby_excy.clone()]
-                ),
-                /*
-                     | 
-                    -' 
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top, is_vertical),
-                 vec![cxay_cxby.clone(), arc_axcy_cxby.clone()]
-                ),
-
-                /*
-                     | 
-                    _' 
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(left, is_low_horizontal)
-                 && self.is_char(top, is_vertical),
-                 vec![arc_axey_cxdy.clone(),cxdy_cxay.clone() ]
-                ),
-                /*
-                     | 
-                     '_
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(right, is_low_horizontal)
-                 && self.is_char(top, is_vertical),
-                 vec![cxdy_cxay.clone(),arc_cxdy_exey.clone()]
-                ),
-                /*
-                    .-  
-                   / 
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![axey_bxdy.clone(), arc_excy_bxdy.clone()]
-                ),
-                /*
-                   -.  
-                     \ 
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![exey_dxdy.clone(), arc_dxdy_axcy.clone()]
-                ),
-                /*
-                   -.  
-                   / 
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![axey_bxdy.clone(), arc_bxdy_axcy.clone()]
-                ),
-                /*
-                   .-
-                    \
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![exey_dxdy.clone(), arc_excy_dxdy.clone()]
-                ),
-                /*
-                   \  
-                    '-  
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top_left, is_slant_left),
-                 vec![axay_bxby.clone(), arc_bxby_excy.clone()]
-                ),
-                /*
-                     / 
-                    '-  
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top_right, is_slant_right),
-                 vec![dxby_exay.clone(), arc_dxby_excy.clone()]
-                ),
-                /*
-                    \
-                    -'
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top_left, is_slant_left),
-                 vec![axay_bxby.clone(), arc_axcy_bxby.clone()]
-                ),
-                /*
-                      /
-                    -'
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top_right, is_slant_right),
-                 vec![dxby_exay.clone(), arc_axcy_dxby.clone()]
-                ),
-                /*
-                    \       \
-                     .  or   )
-                    /       /
-                */
-                ((self.is_char(this, is_round) || self.is_char(this, is_close_curve))
-                 && self.is_char(top_left, is_slant_left)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![axay_bxby.clone(),axey_bxdy.clone(), arc_bxdy_bxby.clone()]
-                ),
-                /*
-                      /       /
-                     .  or   (
-                      \       \
-                */
-                ((self.is_char(this, is_round) || self.is_char(this, is_open_curve))
-                 && self.is_char(top_right, is_slant_right)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![exay_dxby.clone(),exey_dxdy.clone(), arc_dxby_dxdy.clone()]
-                ),
-                /*
-                      .      .     ,
-                     (  or  (  or (
-                      '      `     `
-                */  
-                (self.is_char(this, is_open_curve) 
-                 && (self.is_char(top_right, is_round)
-                    || self.is_char(top_right, is_comma)
-                    )
-                 && (self.is_char(bottom_right, is_high_round)
-                   || self.is_char(bottom_right, is_backtick)
-                   ),
-                 vec![arc_dxay_dxey.clone()]
-                ),
-                /*
-                      .
-                       ) 
-                      '
-                */
-                (self.is_char(this, is_close_curve) 
-                 && self.is_char(top_left, is_round)
-                 && self.is_char(bottom_left, is_round),
-                 vec![arc_bxey_bxay.clone()]
-                ),
-                /*
-                      .-          ,-
-                     (     or    (
-                */
-                ((self.is_char(this, is_low_round) 
-                  || self.is_char(this, is_comma)
-                  )
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(bottom_left, is_open_curve),
-                 vec![arc_excy_axbhey.clone()]
-                ),
-                /*
-                       -.
-                         ) 
-                */
-                (self.is_char(this, is_round) 
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(bottom_right, is_close_curve),
-                 vec![arc_exbhey_axcy.clone()]
-                ),
-                /*
-                    (    or   (
-                     '-        `-
-                */
-                ((self.is_char(this, is_high_round) 
-                  || self.is_char(this, is_backtick)
-                 )
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top_left, is_open_curve),
-                 vec![arc_axbhay_excy.clone()]
-                ),
-                /*
-                        ) 
-                      -'
-                */
-                (self.is_char(this, is_round) 
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top_right, is_close_curve),
-                 vec![arc_axcy_exbhay.clone()]
-                ),
-                /*
-                     .- 
-                     ' 
-                */
-                (self.is_char(this, is_low_round) 
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(bottom, is_high_round),
-                 vec![arc_excy_cxdy.clone(), cxdy_cxey.clone()]
-                ),
-                /*
-                     -.
-                      ' 
-                */
-                (self.is_char(this, is_low_round) 
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(bottom, is_high_round),
-                 vec![arc_cxdy_axcy.clone(),cxdy_cxey.clone()]
-                ),
-                /*
-                     . 
-                     '-
-                */
-                (self.is_char(this, is_high_round) 
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top, is_low_round),
-                 vec![arc_cxby_excy.clone(), cxay_cxby.clone()]
-                ),
-                /*
-                       . 
-                      -'
-                */
-                (self.is_char(this, is_high_round) 
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top, is_low_round),
-                 vec![arc_axcy_cxby.clone(), cxay_cxby.clone()]
-                ),
-                /*
-                      .-.  
-                         
-                */
-                (self.is_char(this, is_horizontal) 
-                 && self.is_char(left, is_low_round)
-                 && self.is_char(right, is_low_round),
-                 vec![arc_excy_axcy.clone()]
-                ),
-                /*
-                      ._.  
-                         
-                */
-                (self.is_char(this, is_low_horizontal) 
-                 && self.is_char(left, is_low_round)
-                 && self.is_char(right, is_low_round),
-                 vec![arc_axey_exey.clone()]
-                ),
-                /*
-                      .
-                      .'
-                         
-                */
-                (self.is_char(this, is_high_round) 
-                 && self.is_char(left, is_low_round)
-                 && self.is_char(top_left, is_low_round),
-                 vec![arc_axcy_axay.clone()]
-                ),
-                /*
-                      .-.  
-                     (    
-                */
-                (self.is_char(this, is_horizontal) 
-                 && self.is_char(left, is_low_round)
-                 && self.is_char(right, is_low_round)
-                 && self.is_char(&this.bottom_left().left(), is_open_curve),
-                 vec![arc_excy_axcy.clone()]
-                ),
-                /*         
-                     (     or  (
-                      '-'       `-'
-                */
-                (self.is_char(this, is_horizontal) 
-                 && (self.is_char(left, is_high_round)
-                    || self.is_char(left, is_backtick)
-                    )
-                 && self.is_char(right, is_high_round)
-                 && self.is_char(&this.top_left().left(), is_open_curve),
-                 vec![arc_axcy_excy.clone()]
-                ),
-                /*
-                      / 
-                     .  
-                     |
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(bottom, is_vertical)
-                 && self.is_char(top_right, is_slant_right),
-                 vec![exay_dxby.clone(),cxey_cxdy.clone(), arc_dxby_cxdy.clone()]
-                ),
-                /*
-                     | 
-                     .  
-                    /
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![cxay_cxby.clone(), axey_bxdy.clone(), arc_bxdy_cxby.clone()]
-                ),
-                /*
-                    \
-                     .  
-                     | 
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(bottom, is_vertical)
-                 && self.is_char(top_left, is_slant_left),
-                 vec![axay_bxby.clone(), cxdy_cxey.clone(), arc_cxdy_bxby.clone()]
-                ),
-                /*
-                     |
-                     .  
-                      \ 
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![cxay_cxby.clone(), dxdy_exey.clone(), arc_cxby_dxdy.clone()]
-                ),
-                /*
-                     .       
-                    / \       
-                */
-                (self.is_char(this, is_low_round)
-                 && self.is_char(bottom_left, is_slant_right)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![axey_cxcy.clone(), cxcy_exey.clone()]
-                ),
-                /*
-                       \ /
-                        '
-
-                */
-                (self.is_char(this, is_high_round)
-                 && self.is_char(top_left, is_slant_left)
-                 && self.is_char(top_right, is_slant_right),
-                 vec![axay_cxcy.clone(), cxcy_exay.clone()]
-                ),
-                /*
-                     |  
-                    / \
-                */
-                (self.is_char(this, is_vertical)
-                 && self.is_char(bottom_left, is_slant_right)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![axey_cxcy.clone(), cxcy_exey.clone(), cxay_cxcy.clone()]
-                ),
-
-                /*
-                    \ / 
-                     | 
-                */
-                (self.is_char(this, is_vertical)
-                 && self.is_char(top_left, is_slant_left)
-                 && self.is_char(top_right, is_slant_right),
-                 vec![axay_cxcy.clone(), cxcy_exay.clone(), cxey_cxcy.clone()]
-                ),
-                /*
-                     .  
-                     |\
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(bottom, is_vertical)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![cxcy_cxey.clone(), cxcy_exey.clone()]
-                ),
-                /*
-                      .  
-                     /|
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(bottom, is_vertical)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![cxcy_cxey.clone(), cxcy_axey.clone()]
-                ),
-                /*
-                     \|  
-                      '
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(top_left, is_slant_left),
-                 vec![axay_cxcy.clone(), cxcy_cxay.clone()]
-                ),
-                /*
-                      |/
-                      '
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(top_right, is_slant_right),
-                 vec![cxay_cxcy.clone(), cxcy_exay.clone()]
-                ),
-                /*
-                     -.
-                      (
-                      
-                */
-                (self.is_char(this, is_low_round)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(bottom, is_open_curve),
-                 vec![arc_cxey_axcy.clone()]
-                ),
-                /*
-                     
-                      (
-                     -'
-                      
-                */
-                (self.is_char(this, is_high_round)
-                 && self.is_char(top, is_open_curve)
-                 && self.is_char(left, is_horizontal),
-                 vec![arc_axcy_cxay.clone()]
-                ),
-                /*
-                     .-
-                     )
-                      
-                */
-                (self.is_char(this, is_low_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(bottom, is_close_curve),
-                 vec![arc_excy_cxey.clone()]
-                ),
-                /*
-                    )
-                    '-
-                      
-                */
-                (self.is_char(this, is_high_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top, is_close_curve),
-                 vec![arc_cxay_excy.clone()]
-                ),
-                /*
-                      
-                      (
-                       >
-                      
-                */
-                (self.is_char(this, is_open_curve)
-                 && self.is_char(bottom_right, is_arrow_right),
-                 vec![arc_cxay_exey.clone()]
-                ),
-                /*
-                      
-                       >
-                      (
-                      
-                */
-                (self.is_char(this, is_open_curve)
-                 && self.is_char(top_right, is_arrow_right),
-                 vec![arc_exay_cxey.clone()]
-                ),
-                /*
-                    expandable close bracket
-                      
-                      (
-                       >
-                      (
-                      
-                */
-                (self.is_char(this, is_arrow_right)
-                 && self.is_char(top_left, is_open_curve)
-                 && self.is_char(bottom_left, is_open_curve),
-                 vec![axay_excy.clone(), axey_excy.clone()]
-                ),
-                /*
-                      
-                      )
-                     <
-                      
-                */
-                (self.is_char(this, is_close_curve)
-                 && self.is_char(bottom_left, is_arrow_left),
-                 vec![arc_axey_cxay.clone()]
-                ),
-                /*
-                      
-                     < 
-                      )
-                      
-                */
-                (self.is_char(this, is_close_curve)
-                 && self.is_char(top_left, is_arrow_left),
-                 vec![arc_cxey_axay.clone()]
-                ),
-                /*
-                    expandable open brcket
-                      
-                      )
-                     <
-                      )
-                      
-                */
-                (self.is_char(this, is_arrow_left)
-                 && self.is_char(top_right, is_close_curve)
-                 && self.is_char(bottom_right, is_close_curve),
-                 vec![axcy_exay.clone(), axcy_exey.clone()]
-                ),
-
-                /*
-                     .- 
-                    < 
-                */
-                (self.is_char(this, is_low_round) 
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(bottom_left, is_arrow_left),
-                 vec![arc_excy_cxdy.clone(), cxdy_cxeybv.clone()]
-                ),
-                /*
-                    left speech balloon pointer  
-                      .
-                     <
-                      '
-                      
-                */
-                (self.is_char(this, is_arrow_left)
-                 && self.is_char(top_right, is_low_round)
-                 && self.is_char(bottom_right, is_high_round),
-                 vec![axcy_exchby.clone(), axcy_exchdy.clone()]
-                ),
-                /*
-                    <  
-                     '-
-                */
-                (self.is_char(this, is_high_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top_left, is_arrow_left),
-                 vec![arc_cxby_excy.clone(), cxaybv_cxby.clone()]
-                ),
-                /*
-                    right speech balloon pointer  
-                      .
-                       >
-                      '
-                      
-                */
-                (self.is_char(this, is_arrow_right)
-                 && self.is_char(top_left, is_low_round)
-                 && self.is_char(bottom_left, is_high_round),
-                 vec![axchby_excy.clone(), axchdy_excy.clone()]
-                ),
-
-                /*
-                      > 
-                    -'
-                */
-                (self.is_char(this, is_high_round)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top_right, is_arrow_right),
-                 vec![arc_axcy_cxby.clone(), cxaybv_cxby.clone()]
-                ),
-                /*
-
-                    -. 
-                      > 
-                */
-                (self.is_char(this, is_low_round) 
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(bottom_right, is_arrow_right),
-                 vec![arc_cxdy_axcy.clone(), cxdy_cxeybv.clone()]
-                ),
-                /*
-                      |_\
-                      
-                */
-                (self.is_char(this, is_low_horizontal)
-                 && self.is_char(left, is_vertical)
-                 && self.is_char(right, is_slant_left),
-                 vec![axchey_exehey.clone()]
-                ),
-                /*
-                     /
-                    .-
-                   /
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top_right, is_slant_right)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![slant_right.clone(), dxcy_excy.clone(), arc_dxcy_bxdy.clone()]
-                ),
-                /*
-                     /
-                   -.
-                   /
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top_right, is_slant_right)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![slant_right.clone(), bxcy_axcy.clone(), arc_bxcy_dxby.clone()]
-                ),
-                /*
-                    \
-                    -.
-                      \
-                   
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top_left, is_slant_left)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![slant_left.clone(),bxcy_axcy.clone(), arc_dxdy_bxcy.clone()]
-                ),
-                /*
-                    \
-                     .-
-                      \
-                   
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top_left, is_slant_left)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![slant_left.clone(),dxcy_excy.clone(), arc_bxby_dxcy.clone()]
-                ),
-                /*
-                    
-                    -.-
-                    / 
-                   
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![horizontal.clone(), axey_bxdy.clone(), arc_excy_bxdy.clone()]
-                ),
-                /*
-                    
-                    -.-
-                      \ 
-                   
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![horizontal.clone(), dxdy_exey.clone(), arc_dxdy_axcy.clone()]
-                ),
-                /*
-                      /
-                    -'-
-                   
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top_right, is_slant_right),
-                 vec![horizontal.clone(), dxby_exay.clone(), arc_axcy_dxby.clone()]
-                ),
-                /*
-                    \ 
-                    -'-
-                   
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top_left, is_slant_left),
-                 vec![horizontal.clone(), axay_bxby.clone(), arc_bxby_excy.clone()]
-                ),
-                /*
-                     |
-                    -+-
-                */
-                (self.is_char(this, is_intersection)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top, is_vertical),
-                 vec![cxcy_cxay.clone(), horizontal.clone()]
-                ),
-                /*
-                    -+-
-                     |
-                */
-                (self.is_char(this, is_intersection)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(bottom, is_vertical),
-                 vec![cxcy_cxey.clone(), horizontal.clone()]
-                ),
-                /*
-                     |
-                    -+
-                     |
-                */
-                (self.is_char(this, is_intersection)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom, is_vertical),
-                 vec![vertical.clone(), cxcy_axcy.clone()]
-                ),
-                /*
-                     |
-                     +-
-                     |
-                */
-                (self.is_char(this, is_intersection)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom, is_vertical),
-                 vec![vertical.clone(), cxcy_excy.clone()]
-                ),
-                /*
-                     | 
-                     .  
-                    /|
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(bottom, is_vertical)
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom_left, is_slant_right),
-                 vec![vertical.clone(), axey_bxdy.clone(), arc_bxdy_cxby.clone()]
-                ),
-                /*
-                     | 
-                     .  
-                     |\
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(bottom, is_vertical)
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![vertical.clone(), exey_dxdy.clone(), arc_cxby_dxdy.clone()]
-                ),
-                /*
-                     | 
-                     .  
-                    / \
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom_left, is_slant_right)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![cxay_cxcy.clone(), cxcy_exey.clone(), cxcy_axey.clone()]
-                ),
-                /*
-                     |/
-                     '  
-                     | 
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom, is_vertical)
-                 && self.is_char(top_right, is_slant_right),
-                 vec![vertical.clone(), cxcy_exay.clone()]
-                ),
-                /*
-                    \|
-                     '  
-                     | 
-                */
-                (self.is_char(this, is_round)
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom, is_vertical)
-                 && self.is_char(top_left, is_slant_left),
-                 vec![vertical.clone(), axay_cxcy.clone()]
-                ),
-                /*
-                    |  
-                   -+-
-                    | 
-                */
-                ((self.is_char(this, is_intersection) || self.is_char(this, is_round) || self.is_char(this, is_marker))
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom, is_vertical)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(right, is_horizontal),
-                 vec![vertical.clone(), horizontal.clone()]
-                ),
-                /*
-                    :  
-                   =+=
-                    : 
-                */
-                ((self.is_char(this, is_intersection) || self.is_char(this, is_round) || self.is_char(this, is_marker))
-                 && self.is_char(top, is_vertical_dashed)
-                 && self.is_char(bottom, is_vertical_dashed)
-                 && self.is_char(left, is_horizontal_dashed)
-                 && self.is_char(right, is_horizontal_dashed),
-                 vec![vertical_dashed.clone(), horizontal_dashed.clone()]
-                ),
-                /*
-                   \|/ 
-                    + 
-                   /|\
-                */
-                ((self.is_char(this, is_intersection) || self.is_char(this, is_round) || self.is_char(this, is_marker))
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom, is_vertical)
-                 && self.is_char(top_left, is_slant_left)
-                 && self.is_char(top_right, is_slant_right)
-                 && self.is_char(bottom_left, is_slant_right)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![vertical.clone(), slant_left.clone(), slant_right.clone()]
-                ),
-                /*
-                   \|/ 
-                   -+-
-                   /|\
-                */
-                ((self.is_char(this, is_intersection) || self.is_char(this, is_round) || self.is_char(this, is_marker))
-                 && self.is_char(top, is_vertical)
-                 && self.is_char(bottom, is_vertical)
-                 && self.is_char(left, is_horizontal)
-                 && self.is_char(right, is_horizontal)
-                 && self.is_char(top_left, is_slant_left)
-                 && self.is_char(top_right, is_slant_right)
-                 && self.is_char(bottom_left, is_slant_right)
-                 && self.is_char(bottom_right, is_slant_left),
-                 vec![vertical.clone(), horizontal.clone(), slant_left.clone(), slant_right.clone()]
-                ),
-            ];
-        let match_path: Option<(bool, Vec<Element>)> = match_list.into_iter()
-            .rev()
-            .find(|x| {
-                let &(cond, _) = x;
-                cond
-            });
-
-        let paths: Option<Vec<Element>> = match match_path {
-            Some((_, paths)) => Some(paths),
+        let gchar = self.get(this)?;
+        if self.is_escape_quote(this) {
+            return None;
+        }
+        if self.is_escaped(this) {
+            return Some(vec![Element::Text(this.clone(), gchar.string.clone())]);
+        }
+        if let Some(rule) = settings.rules.iter().rev().find(|rule| rule.matches(self, this)) {
+            return Some(rule.elements(&gchar.string, x, y, settings));
+        }
+        match rules.find(self, this) {
+            Some(rule) => Some(rule.elements(&gchar.string, x, y, settings)),
             None => {
-                let ch = self.get(this);
-                match ch {
-                    Some(ch) => {
-                        if !(ch.string == " ") ||
-                           (ch.string == " " && self.is_char(left, is_alphanumeric) &&
-                            self.is_char(right, is_alphanumeric)) {
-                            let s = escape_char(&ch.string);
-                            let text = Element::Text(this.clone(), s);
-                            Some(vec![text])
-                        } else {
-                            None
-                        }
-                    }
-                    None => None,
+                if gchar.string != " " || self.is_space_in_text(this, settings, rules) {
+                    Some(vec![Element::Text(this.clone(), gchar.string.clone())])
+                } else {
+                    None
                 }
             }
-        };
-
-        paths
-
+        }
     }
 
 
     fn get_all_elements(&self, settings: &Settings) -> Vec<(Loc, Vec<Element>)> {
-        fn get_line_width(line: &Vec<GChar>) -> usize{
-            let mut total_width = 0;
-            for gch in line{
-               total_width += gch.width;
-            }
-            total_width
-        }
+        let rules = RuleSet::builtin();
         let mut all_paths = vec![];
         for row in 0..self.lines.len() {
-            let line = &self.lines[row];
-            let line_width = get_line_width(line);
+            let line_width = self.line_width(row as isize);
             for column in 0..line_width {
-                let x = column as isize;
+                let x = column;
                 let y = row as isize;
-                match self.get_elements(x, y, settings) {
-                    Some(paths) => {
-                        all_paths.push((Loc::new(x, y), paths));
-                    }
-                    None => {
-                        ();
-                    }
+                if let Some(paths) = self.get_elements(x, y, settings, &rules) {
+                    all_paths.push((Loc::new(x, y), paths));
                 }
             }
         }
@@ -2588,45 +1068,125 @@ impl Grid {
 
     // each component has its relative location retain
     // use this info for optimizing svg by checking closest neigbor
-    fn get_svg_nodes(&self, settings: &Settings) -> Vec<SvgElement> {
+    fn get_svg_nodes(&self, settings: &Settings) -> (Vec<SvgElement>, Bounds) {
         let mut nodes = vec![];
-        let elements = self.get_all_elements(settings);
-        let input = if settings.optimize {
-            let optimizer = Optimizer::new(elements);
-            let optimized_elements = optimizer.optimize(settings);
-            optimized_elements
+        let mut bounds = Bounds::empty();
+        self.for_each_element(settings, |elem| {
+            bounds.add_element(&elem, settings);
+            nodes.push(elem.to_svg(settings));
+            Ok(())
+        }).unwrap();
+        (nodes, bounds)
+    }
+
+    /// the bounds of the elements drawn, without their svg
+    fn get_bounds(&self, settings: &Settings) -> Bounds {
+        let mut bounds = Bounds::empty();
+        self.for_each_element(settings, |elem| {
+            bounds.add_element(&elem, settings);
+            Ok(())
+        }).unwrap();
+        bounds
+    }
+
+    /// the left, top, width and height of the area shown,
+    /// the cropped bounds when they are not empty or else the whole grid
+    fn view_box(&self, settings: &Settings, bounds: &Bounds) -> (f32, f32, f32, f32) {
+        if settings.crop && !settings.debug && !bounds.is_empty() {
+            let margin = settings.margin;
+            (bounds.left - margin,
+             bounds.top - margin,
+             bounds.width() + 2.0 * margin,
+             bounds.height() + 2.0 * margin)
         } else {
-            elements.into_iter().flat_map(|(_, elm)| elm).collect()
-        };
-        for elem in input {
-            let element = elem.to_svg(settings);
-            nodes.push(element);
+            (0.0,
+             0.0,
+             settings.text_width * (self.columns + 4) as f32,
+             settings.text_height * (self.rows + 2) as f32)
+        }
+    }
+
+    /// call f with the elements in the order they are drawn.
+    /// Without optimization the elements are passed on as each cell is visited,
+    /// otherwise they are collected first to be reduced by the optimizer
+    fn for_each_element<F>(&self, settings: &Settings, mut f: F) -> io::Result<()>
+        where F: FnMut(Element) -> io::Result<()>
+    {
+        if settings.optimize {
+            for elem in self.optimized_elements(settings) {
+                f(elem)?;
+            }
+        } else {
+            let rules = RuleSet::builtin();
+            for row in 0..self.lines.len() as isize {
+                for column in 0..self.line_width(row) {
+                    if let Some(elements) = self.get_elements(column, row, settings, &rules) {
+                        for elem in elements {
+                            f(elem)?;
+                        }
+                    }
+                }
+            }
         }
-        nodes
+        Ok(())
+    }
+
+    fn optimized_elements(&self, settings: &Settings) -> Vec<Element> {
+        Optimizer::new(self.get_all_elements(settings)).optimize(settings)
     }
 
+    /// the elements the grid is drawn with, reduced by the optimizer
+    /// when it is enabled in the settings, the empty merged paths are left out
+    pub fn elements(&self, settings: &Settings) -> Vec<Element> {
+        let mut elements = vec![];
+        self.for_each_element(settings, |elem| {
+            match elem {
+                Element::Path(_, _, ref d, _) if d.is_empty() => (),
+                _ => elements.push(elem),
+            }
+            Ok(())
+        }).unwrap();
+        elements
+    }
+
+    /// the svg element with its attributes, without the content
+    fn get_svg_root(&self, settings: &Settings, bounds: &Bounds) -> SVG {
+        let (x, y, width, height) = self.view_box(settings, bounds);
+        let svg = SVG::new()
+            .set("font-size", settings.font_size)
+            .set("font-family", escape_str(&settings.font_family))
+            .set("viewBox", format!("{} {} {} {}", x, y, width, height));
+        if settings.responsive {
+            svg.set("width", "100%")
+        } else {
+            svg.set("width", width)
+                .set("height", height)
+        }
+    }
 
     /// get the generated svg according to the settings specified
     pub fn get_svg(&self, settings: &Settings) -> SVG {
-        let nodes = self.get_svg_nodes(settings);
-        let width = settings.text_width * (self.columns + 4) as f32;
-        let height = settings.text_height * (self.rows + 2)as f32;
-        let mut svg = SVG::new()
-            .set("font-size", 14)
-            .set("font-family",
-                "arial"
-                )
-            .set("width", width)
-            .set("height", height);
+        let (nodes, bounds) = self.get_svg_nodes(settings);
+        let mut svg = self.get_svg_root(settings, &bounds);
 
         svg.append(get_defs());
         svg.append(get_styles());
+        for css in &settings.styles {
+            svg.append(Style::new(css.as_str()));
+        }
+        if settings.debug {
+            svg.append(debug::get_debug_styles());
+            svg.append(debug::debug_overlay(self, settings));
+        }
 
         for node in nodes {
             match node {
                 SvgElement::Circle(circle) => {
                     svg.append(circle);
                 }
+                SvgElement::Polygon(polygon) => {
+                    svg.append(polygon);
+                }
                 SvgElement::Line(line) => {
                     svg.append(line);
                 }
@@ -2640,8 +1200,64 @@ impl Grid {
         }
         svg
     }
+
+    /// write the generated svg to the writer, the same content as `get_svg`
+    /// but each element is written as it is produced
+    /// instead of building the whole svg document in memory first.
+    ///
+    /// Only without optimization are the elements streamed cell by cell,
+    /// the optimizer merges the lines across the rows, so it collects the elements
+    /// of the whole diagram before the first one is written,
+    /// with the memory growing along with the diagram.
+    /// Turn it off with `Settings::set_optimize(false)` for the diagrams too large for that
+    pub fn write_svg<W: io::Write>(&self, settings: &Settings, mut writer: W) -> io::Result<()> {
+        // the optimized elements are collected anyway, they are measured and written from the same pass,
+        // the cells are visited twice without optimization to keep writing them as they are drawn
+        let optimized = if settings.optimize { Some(self.optimized_elements(settings)) } else { None };
+        let mut bounds = Bounds::empty();
+        if settings.crop && !settings.debug {
+            match optimized {
+                Some(ref elements) => {
+                    for elem in elements {
+                        bounds.add_element(elem, settings);
+                    }
+                }
+                None => bounds = self.get_bounds(settings),
+            }
+        }
+        // the root has no children yet, so it is displayed as a self closing tag
+        let root = self.get_svg_root(settings, &bounds).to_string();
+        write!(writer, "{}>", root.trim_end_matches("/>"))?;
+        write!(writer, "\n{}", get_defs())?;
+        write!(writer, "\n{}", get_styles())?;
+        for css in &settings.styles {
+            write!(writer, "\n{}", Style::new(css.as_str()))?;
+        }
+        if settings.debug {
+            write!(writer, "\n{}", debug::get_debug_styles())?;
+            write!(writer, "\n{}", debug::debug_overlay(self, settings))?;
+        }
+        match optimized {
+            Some(elements) => {
+                for elem in elements {
+                    write!(writer, "\n{}", elem.to_svg(settings))?;
+                }
+            }
+            None => {
+                self.for_each_element(settings, |elem| {
+                    write!(writer, "\n{}", elem.to_svg(settings))
+                })?;
+            }
+        }
+        write!(writer, "\n</svg>")?;
+        writer.flush()
+    }
 }
 
+/// the dashes of the heavy lines, the gaps are wider
+/// as the round line caps of the thicker stroke cover more of them
+const HEAVY_DASHES: (u32, u32) = (2, 6);
+
 fn get_defs() -> Definitions {
     let mut defs = Definitions::new();
     defs.append(arrow_marker());
@@ -2658,6 +1274,9 @@ fn get_styles() -> Style {
       stroke-linecap: round;
       stroke-linejoin: miter;
     }
+    line.heavy, path.heavy {
+      stroke-width: 4;
+    }
     circle {
       stroke: black;
       stroke-width: 2;
@@ -2673,6 +1292,15 @@ fn get_styles() -> Style {
     circle.open {
       fill:white;
     }
+    polygon {
+      stroke: black;
+      stroke-width: 2;
+      stroke-linejoin: miter;
+      fill:white;
+    }
+    polygon.solid {
+      fill:black;
+    }
     tspan.head{
         fill: none;
         stroke: none;
@@ -2699,11 +1327,11 @@ fn arrow_marker() -> Marker {
 }
 
 fn is_vertical(ch: &str) -> bool {
-    ch == "|"
+    ch == "|" || ch == "│"
 }
 
 fn is_horizontal(ch: &str) -> bool {
-    ch == "-"
+    ch == "-" || ch == "─"
 }
 
 fn is_horizontal_dashed(ch: &str) -> bool {
@@ -2770,19 +1398,59 @@ fn is_o(ch: &str) -> bool {
 }
 
 fn is_arrow_up(ch: &str) -> bool {
-    ch == "^"
+    ch == "^" || ch == "↑" || ch == "▲"
 }
 
 fn is_arrow_down(ch: &str) -> bool {
-    ch == "v" || ch == "V"
+    ch == "v" || ch == "V" || ch == "↓" || ch == "▼"
 }
 
 fn is_arrow_left(ch: &str) -> bool {
-    ch == "<"
+    ch == "<" || ch == "←" || ch == "◀"
 }
 
 fn is_arrow_right(ch: &str) -> bool {
-    ch == ">"
+    ch == ">" || ch == "→" || ch == "▶"
+}
+
+fn is_solid_bullet(ch: &str) -> bool {
+    ch == "●"
+}
+
+fn is_open_bullet(ch: &str) -> bool {
+    ch == "○"
+}
+
+fn is_solid_diamond(ch: &str) -> bool {
+    ch == "◆"
+}
+
+fn is_open_diamond(ch: &str) -> bool {
+    ch == "◇"
+}
+
+fn is_solid_square(ch: &str) -> bool {
+    ch == "■"
+}
+
+fn is_open_square(ch: &str) -> bool {
+    ch == "□"
+}
+
+fn is_triangle_right(ch: &str) -> bool {
+    ch == "▶"
+}
+
+fn is_triangle_left(ch: &str) -> bool {
+    ch == "◀"
+}
+
+fn is_triangle_up(ch: &str) -> bool {
+    ch == "▲"
+}
+
+fn is_triangle_down(ch: &str) -> bool {
+    ch == "▼"
 }
 
 fn is_open_curve(ch: &str) -> bool {
@@ -2794,6 +1462,164 @@ fn is_close_curve(ch: &str) -> bool {
 }
 
 
+/// expand the tabs to the tab stops, drop the carriage returns
+/// and replace the other control characters with a space
+/// so the characters after them stay on their columns,
+/// along with the byte offset in line of every byte of the cleaned line
+fn clean_line(line: &str, tab_width: usize) -> (String, Vec<usize>) {
+    let tab_width = tab_width.clamp(1, MAX_TAB_WIDTH);
+    let mut cleaned = String::with_capacity(line.len());
+    let mut offsets = Vec::with_capacity(line.len());
+    let mut column = 0;
+    for (offset, ch) in line.char_indices() {
+        match ch {
+            '\t' => {
+                let spaces = tab_width - column % tab_width;
+                for _ in 0..spaces {
+                    cleaned.push(' ');
+                    offsets.push(offset);
+                }
+                column += spaces;
+            }
+            '\r' => (),
+            ch if ch.is_control() => {
+                cleaned.push(' ');
+                offsets.push(offset);
+                column += 1;
+            }
+            ch => {
+                cleaned.push(ch);
+                for _ in 0..ch.len_utf8() {
+                    offsets.push(offset);
+                }
+                column += ch.width().unwrap_or(0);
+            }
+        }
+    }
+    (cleaned, offsets)
+}
+
+/// the cells of a line, each with the byte offset in the line where it starts
+fn line_gchars(line: &str, tab_width: usize) -> Vec<(usize, GChar)> {
+    let (cleaned, offsets) = clean_line(line, tab_width);
+    let mut gchars: Vec<(usize, GChar)> = Vec::with_capacity(cleaned.len());
+    for (i, grapheme) in cleaned.grapheme_indices(true) {
+        let gchar = GChar::from_str(grapheme);
+        if gchar.width == 0 {
+            // a zero width grapheme, such as a zero width space,
+            // doesn't take a cell of its own
+            if let Some((offset, last_gchar)) = gchars.pop() {
+                gchars.push((offset, last_gchar.push_str(grapheme)));
+            }
+        } else {
+            gchars.push((offsets[i], gchar));
+        }
+    }
+    gchars
+}
+
+/// the characters that mark the escaped text
+fn is_escape(ch: &str) -> bool {
+    ch == "\"" || ch == "`"
+}
+
+// a backtick next to a line is a rounded corner and not a quote
+fn is_line_end(ch: &str) -> bool {
+    is_horizontal(ch) || is_horizontal_dashed(ch) || is_low_horizontal(ch) || is_vertical(ch)
+        || is_slant_left(ch) || is_slant_right(ch) || is_round(ch) || is_intersection(ch)
+}
+
+/// find the text wrapped in double quotes or backticks on this line,
+/// a quote is only closed by the same kind of quote,
+/// and the quoted text doesn't start or end with a space or a line
+/// so the rounded corners such as `` `-' `` are not mistaken for quotes
+fn escaped_spans(line: &[GChar]) -> Vec<(isize, isize)> {
+    let mut spans = vec![];
+    let mut column = 0;
+    let mut open: Option<(isize, usize)> = None;
+    for (i, gchar) in line.iter().enumerate() {
+        if is_escape(&gchar.string) {
+            match open {
+                Some((start, start_index)) if line[start_index].string == gchar.string => {
+                    let content = &line[start_index + 1..i];
+                    let is_text = |gchar: &GChar| gchar.string != " " && !is_line_end(&gchar.string);
+                    if content.first().is_some_and(&is_text) && content.last().is_some_and(&is_text) {
+                        spans.push((start, column));
+                        open = None;
+                    } else {
+                        open = Some((column, i));
+                    }
+                }
+                Some(_) => {}
+                None => open = Some((column, i)),
+            }
+        }
+        column += gchar.width as isize;
+    }
+    spans
+}
+
+// the letters that are also drawing characters
+fn is_drawing_letter(ch: &str) -> bool {
+    is_o(ch) || is_arrow_down(ch) || ch == "x"
+}
+
+fn is_word_char(ch: &str) -> bool {
+    let mut chars = ch.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => c.is_alphanumeric() || c == '_' || c == '.',
+        _ => false,
+    }
+}
+
+/// find the words on this line, so `v`, `o`, `_` and `.` in
+/// "overview", "file_name" and "e.g." are not drawn.
+/// A word is a run of letters, digits, `_` and `.` with at least
+/// one letter or digit that is never a drawing character,
+/// the `_` and `.` at its ends are not part of it
+/// unless it is a period followed by a space.
+fn word_spans(line: &[GChar]) -> Vec<(isize, isize)> {
+    let mut spans = vec![];
+    let mut columns = Vec::with_capacity(line.len());
+    let mut column = 0;
+    for gchar in line {
+        columns.push(column);
+        column += gchar.width as isize;
+    }
+    let mut i = 0;
+    while i < line.len() {
+        if !is_word_char(&line[i].string) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < line.len() && is_word_char(&line[i].string) {
+            i += 1;
+        }
+        let (mut first, mut last) = (start, i - 1);
+        let is_joiner = |gchar: &GChar| gchar.string == "_" || gchar.string == ".";
+        while first <= last && is_joiner(&line[first]) {
+            first += 1;
+        }
+        let period = last + 1 < line.len() && line[last + 1].string == " "
+            || last + 1 == line.len();
+        while last > first && line[last].string == "_" {
+            last -= 1;
+        }
+        if !period {
+            while last > first && is_joiner(&line[last]) {
+                last -= 1;
+            }
+        }
+        let word = &line[first..last + 1];
+        let is_letter = |gchar: &GChar| !is_drawing_letter(&gchar.string) && !is_joiner(gchar);
+        if first <= last && word.iter().any(is_letter) {
+            spans.push((columns[first], columns[last]));
+        }
+    }
+    spans
+}
+
 fn escape_char(ch: &str) -> String {
     let escs = [("\"", "&quot;"), ("'", "&apos;"), ("<", "&lt;"), (">", "&gt;"), ("&", "&amp;")];
     let quote_match: Option<&(&str, &str)> = escs.iter()
@@ -2805,7 +1631,7 @@ fn escape_char(ch: &str) -> String {
         Some(&(_, quoted)) => String::from(quoted),
         None => {
             let mut s = String::new();
-            s.push_str(&ch);
+            s.push_str(ch);
             s
         }
     };
@@ -2813,9 +1639,29 @@ fn escape_char(ch: &str) -> String {
 
 }
 
+/// escape the characters of the whole text run
+/// the text is kept unescaped in the elements so their widths are correct,
+/// the characters xml doesn't allow are replaced with `\u{fffd}`
+fn escape_str(s: &str) -> String {
+    let mut escaped = String::new();
+    for ch in s.chars() {
+        if is_xml_char(ch) {
+            escaped.push_str(&escape_char(&ch.to_string()));
+        } else {
+            escaped.push('\u{fffd}');
+        }
+    }
+    escaped
+}
+
+/// the characters allowed in an xml 1.0 document
+fn is_xml_char(ch: char) -> bool {
+    matches!(ch, '\t' | '\n' | '\r' | '\u{20}'..='\u{d7ff}' | '\u{e000}'..='\u{fffd}' | '\u{10000}'..='\u{10ffff}')
+}
 
-fn is_alphanumeric(ch:&str) -> bool{
-    ch.chars().all(|c| c.is_alphanumeric())
+#[test]
+fn test_escape_non_xml_chars(){
+    assert_eq!(escape_str("a<\u{fffe}\u{ffff}\u{1}é"), "a&lt;\u{fffd}\u{fffd}\u{fffd}é");
 }
 
 #[test]
@@ -2846,7 +1692,7 @@ fn test_meme(){
     println!(r#"<meta charset="utf-8"/>"#);
     println!("char count {}", meme.chars().count());
     println!("total bytes size {}", meme.len());
-    println!("total width {}", UnicodeWidthStr::width(&*meme));
+    println!("total width {}", UnicodeWidthStr::width(meme));
     for m in meme.chars(){
         println!("{} {} width:{} alphanumeric {}",m, m as u32, m.width().unwrap(), m.is_alphanumeric());
     }
@@ -2854,7 +1700,9 @@ fn test_meme(){
     println!("{}", meme);
     let grid = Grid::from_str(meme);
     println!("{:#?}",grid);
-    assert_eq!(grid.get(&Loc::new(6,0)), Some(&GChar::from_str(" ͡°")));
+    // the combining mark is in the cell of the space before it
+    assert_eq!(grid.get(&Loc::new(6,0)), Some(&GChar::from_str(" ͡")));
+    assert_eq!(grid.get(&Loc::new(7,0)), Some(&GChar::from_str("°")));
 }
 
 #[test]
@@ -2863,7 +1711,7 @@ fn test_eye_brow(){
     println!(r#"<meta charset="utf-8"/>"#);
     println!("char count {}", meme.chars().count());
     println!("total bytes size {}", meme.len());
-    println!("total width {}", UnicodeWidthStr::width(&*meme));
+    println!("total width {}", UnicodeWidthStr::width(meme));
     for m in meme.chars(){
         println!("{} {} width:{}",m, m as u32, m.width().unwrap());
     }
@@ -2877,7 +1725,221 @@ fn test_eye_brow(){
             println!("ch: {:?}", ch as u32);
         }
     }
-    assert_eq!(ch, Some(&GChar::from_str(" ͡°")));
+    assert_eq!(ch, Some(&GChar::from_str(" ͡")));
+    assert_eq!(grid.get(&Loc::new(1,0)), Some(&GChar::from_str("°")));
 }
 
 
+
+#[test]
+fn test_font_size_scales_with_text_height(){
+    let settings = Settings::with_size(16.0, 32.0);
+    let svg = Grid::from_str("Hello").get_svg(&settings).to_string();
+    assert!(svg.contains(r#"font-size="28""#));
+    // the text spans exactly the 5 columns it occupies
+    assert!(svg.contains(r#"textLength="80""#));
+}
+
+#[test]
+fn test_monospace_font(){
+    let svg = Grid::from_str("a < b").get_svg(&Settings::monospace()).to_string();
+    assert!(svg.contains(r#"font-family="monospace""#));
+    assert!(svg.contains("&lt;"));
+}
+
+#[test]
+fn test_exact_text_position(){
+    let mut settings = Settings::default();
+    settings.set_exact_text_position(true);
+    let svg = Grid::from_str("a文b").get_svg(&settings).to_string();
+    // the CJK character occupies 2 columns
+    assert!(svg.contains(r#"x="0 8 24""#));
+    assert!(!svg.contains("textLength"));
+}
+
+#[test]
+fn test_whitespace_in_text(){
+    let svg = Grid::from_str("a  b, c").get_svg(&Settings::default()).to_string();
+    assert!(svg.contains(r#"<tspan xml:space="preserve">a  b, c</tspan>"#));
+    // spaces next to drawing characters are not text
+    let svg = Grid::from_str("a --- b").get_svg(&Settings::default()).to_string();
+    assert!(svg.contains(r#"<tspan xml:space="preserve">a</tspan>"#));
+    assert!(svg.contains(r#"<tspan xml:space="preserve">b</tspan>"#));
+}
+
+#[test]
+fn test_unicode_box(){
+    let svg = Grid::from_str("┌──┐\n│ab│\n└──┘").get_svg(&Settings::default()).to_string();
+    assert!(svg.contains(r#"<tspan xml:space="preserve">ab</tspan>"#));
+    assert!(!svg.contains("─"));
+    let svg = Grid::from_str("━━━").get_svg(&Settings::default()).to_string();
+    assert!(svg.contains(r#"class="heavy""#));
+    assert!(svg.contains(r#"class="heavy" d=" M 0 8 L 24 8" fill="none"/>"#), "{}", svg);
+    // the heavy dashes are dashed and as thick as the heavy lines
+    let svg = Grid::from_str("┅┅┅").get_svg(&Settings::default()).to_string();
+    assert!(svg.contains(r#"class="heavy" d=" M 0 8 L 24 8" fill="none" stroke-dasharray="2 6""#), "{}", svg);
+    let svg = Grid::from_str("┄┄┄").get_svg(&Settings::default()).to_string();
+    assert!(!svg.contains(r#"class="heavy""#));
+}
+
+#[test]
+fn test_unicode_arrows_and_bullets(){
+    let svg = Grid::from_str("──→").get_svg(&Settings::default()).to_string();
+    assert!(svg.contains("marker-end"));
+    assert!(!svg.contains("→"));
+    let svg = Grid::from_str("◆ ○").get_svg(&Settings::default()).to_string();
+    assert!(svg.contains(r#"<polygon class="solid""#));
+    assert!(svg.contains(r#"<circle class="open""#));
+}
+
+#[test]
+fn test_custom_rule(){
+    let mut settings = Settings::default();
+    let svg = Grid::from_str("-@-").get_svg(&settings).to_string();
+    assert!(svg.contains(r#"<tspan xml:space="preserve">@</tspan>"#));
+    settings.add_rule(|n| n.this() == "@" && n.left() == "-", &[Fragment::OpenCircle]);
+    let svg = Grid::from_str("-@-").get_svg(&settings).to_string();
+    assert!(!svg.contains("@"));
+    assert!(svg.contains(r#"<circle class="open""#));
+    // the predicate looks at the neighbors
+    let svg = Grid::from_str("@-").get_svg(&settings).to_string();
+    assert!(svg.contains(r#"<tspan xml:space="preserve">@</tspan>"#));
+    // custom rules take precedence over the built-in ones
+    let svg = Grid::from_str("-+-").get_svg(&Settings::no_optimization()).to_string();
+    assert!(!svg.contains(r#"x1="8" x2="16" y1="0" y2="16""#));
+    let mut settings = Settings::no_optimization();
+    settings.add_rule(|n| n.this() == "+", &[Fragment::Line(anchor::AXAY, anchor::EXEY)]);
+    let svg = Grid::from_str("-+-").get_svg(&settings).to_string();
+    assert!(svg.contains(r#"x1="8" x2="16" y1="0" y2="16""#));
+}
+
+#[test]
+fn test_escaped_text(){
+    let settings = &Settings::default();
+    // without the quotes, the `v` is an arrow and the `-` is a line
+    let svg = Grid::from_str("|\nv-o").get_svg(settings).to_string();
+    assert!(svg.contains("marker-end"));
+    let svg = Grid::from_str("|\n\"v-o\"").get_svg(settings).to_string();
+    assert!(!svg.contains("marker-end"));
+    assert!(svg.contains(r#"<tspan xml:space="preserve">v-o</tspan>"#));
+    assert!(!svg.contains("&quot;"));
+    let svg = Grid::from_str("`a-b` and `o`").get_svg(settings).to_string();
+    assert!(svg.contains(r#"<tspan xml:space="preserve">a-b</tspan>"#));
+    assert!(!svg.contains("<circle"));
+    // rounded corners are not quotes
+    let spans = Grid::from_str("`--'  `--'").escapes;
+    assert!(spans[0].is_empty());
+}
+
+#[test]
+fn test_tab_expansion(){
+    let grid = Grid::from_str("a\tb\n\t|");
+    assert_eq!(grid.get(&Loc::new(8, 0)).unwrap().string, "b");
+    assert_eq!(grid.get(&Loc::new(8, 1)).unwrap().string, "|");
+    let grid = Grid::from_str_with_tab_width("ab\tc", 4);
+    assert_eq!(grid.get(&Loc::new(4, 0)).unwrap().string, "c");
+    let grid = Grid::from_str_with_tab_width("a\tb", usize::MAX);
+    assert_eq!(grid.get(&Loc::new(MAX_TAB_WIDTH as isize, 0)).unwrap().string, "b");
+}
+
+#[test]
+fn test_bom_and_control_characters(){
+    let grid = Grid::from_str("\u{feff}+-+\r\n|\r |\r\n");
+    assert_eq!(grid.get(&Loc::new(0, 0)).unwrap().string, "+");
+    assert_eq!(grid.get(&Loc::new(2, 1)).unwrap().string, "|");
+    assert_eq!(grid.columns, 3);
+    let grid = Grid::from_str("a\u{c}b");
+    assert_eq!(grid.get(&Loc::new(2, 0)).unwrap().string, "b");
+}
+
+#[test]
+fn test_write_svg(){
+    let grid = Grid::from_str("+--+\n|a |\n+--+ -->\n  `o`");
+    for settings in &[Settings::compact(), Settings::no_optimization(), Settings::separate_lines()] {
+        let mut written = vec![];
+        grid.write_svg(settings, &mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), grid.get_svg(settings).to_string());
+    }
+}
+
+#[test]
+fn test_elements(){
+    let grid = Grid::from_str("--->\nhi");
+    let elements = grid.elements(&Settings::compact());
+    assert_eq!(elements.len(), 2);
+    match elements[0] {
+        Element::Line(ref s, ref e, Solid, Arrow) => {
+            assert_eq!((s, e), (&Point::new(0.0, 8.0), &Point::new(28.0, 8.0)));
+        }
+        ref elm => panic!("expecting an arrow, got {:?}", elm),
+    }
+    match elements[1] {
+        Element::Text(ref loc, ref text) => assert_eq!((loc, text.as_str()), (&Loc::new(0, 1), "hi")),
+        ref elm => panic!("expecting a text, got {:?}", elm),
+    }
+    assert!(grid.elements(&Settings::no_optimization()).len() > elements.len());
+}
+
+#[test]
+fn test_add_style(){
+    let grid = Grid::from_str("+--+");
+    let mut settings = Settings::default();
+    settings.add_style("line, path { stroke: navy; }");
+    let svg = grid.get_svg(&settings).to_string();
+    assert!(svg.contains("<style>\nline, path { stroke: navy; }\n</style>"));
+    let mut written = vec![];
+    grid.write_svg(&settings, &mut written).unwrap();
+    assert_eq!(String::from_utf8(written).unwrap(), svg);
+}
+
+#[test]
+fn test_cell_offsets(){
+    assert_eq!(cell_offsets("a\u{301}b", 8), vec![(0, 1), (3, 1)]);
+    assert_eq!(cell_offsets("中-\r", 8), vec![(0, 2), (3, 1)]);
+    assert_eq!(cell_offsets("\t|", 2), vec![(0, 1), (0, 1), (1, 1)]);
+}
+
+#[test]
+fn test_long_line(){
+    let svg = Grid::from_str(&"-".repeat(20_000)).get_svg(&Settings::default()).to_string();
+    assert!(svg.contains(r#"<path d=" M 0 8 L 160000 8" fill="none"/>"#));
+}
+
+#[test]
+fn test_crop(){
+    // the indentation and the blank lines are left out, with the margin around the line
+    let grid = Grid::from_str("\n\n        +--+\n");
+    let svg = grid.get_svg(&Settings::default()).to_string();
+    assert!(svg.contains(r#"height="32" viewBox="56 24 48 32" width="48""#), "{}", svg);
+    let mut settings = Settings::default();
+    settings.set_margin(0.0);
+    settings.set_margin(-1.0);
+    settings.set_margin(f32::NAN);
+    let svg = grid.get_svg(&settings).to_string();
+    assert!(svg.contains(r#"height="16" viewBox="64 32 32 16" width="32""#), "{}", svg);
+    settings.set_crop(false);
+    let svg = grid.get_svg(&settings).to_string();
+    assert!(svg.contains(r#"height="80" viewBox="0 0 128 80" width="128""#), "{}", svg);
+    // nothing drawn, the whole grid is shown
+    let svg = Grid::from_str("   ").get_svg(&Settings::default()).to_string();
+    assert!(svg.contains(r#"height="48" viewBox="0 0 56 48" width="56""#), "{}", svg);
+    // the same bounds when written, measured from the elements optimized or not
+    for settings in &[Settings::compact(), Settings::no_optimization()] {
+        let mut written = vec![];
+        grid.write_svg(settings, &mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), grid.get_svg(settings).to_string());
+    }
+}
+
+#[test]
+fn test_responsive(){
+    let grid = Grid::from_str("+--+");
+    let mut settings = Settings::default();
+    settings.set_responsive(true);
+    let svg = grid.get_svg(&settings).to_string();
+    assert!(svg.contains(r#"viewBox="-8 -8 48 32" width="100%""#), "{}", svg);
+    assert!(!svg.contains("height="));
+    let mut written = vec![];
+    grid.write_svg(&settings, &mut written).unwrap();
+    assert_eq!(String::from_utf8(written).unwrap(), svg);
+}
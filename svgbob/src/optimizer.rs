@@ -1,30 +1,30 @@
+use std::collections::HashMap;
+
 use super::Loc;
 use super::Element;
 use super::Stroke;
 use super::Feature;
 use super::Point;
 use super::Settings;
+use super::text_width;
 
 pub struct Optimizer {
     elements: Vec<(Loc, Vec<Element>)>,
+    /// the index in elements of each location
+    index: HashMap<Loc, usize>,
 }
 
 impl Optimizer {
     pub fn new(elements: Vec<(Loc, Vec<Element>)>) -> Optimizer {
-        Optimizer { elements: elements }
+        let mut index = HashMap::with_capacity(elements.len());
+        for (i, (loc, _)) in elements.iter().enumerate() {
+            index.entry(loc.clone()).or_insert(i);
+        }
+        Optimizer { elements, index }
     }
 
     fn get(&self, loc: &Loc) -> Option<&Vec<Element>> {
-        let found = self.elements
-            .iter()
-            .find(|x| {
-                let &(ref l, _) = *x;
-                loc == l
-            });
-        match found {
-            Some(&(_, ref elm)) => Some(elm),    
-            None => None,
-        }
+        self.index.get(loc).map(|&i| &self.elements[i].1)
     }
 
     // return the first element only
@@ -33,7 +33,7 @@ impl Optimizer {
         match self.get(loc) {
             Some(elements) => {
                 if elements.len() == 1 {
-                    elements.get(0)
+                    elements.first()
                 } else {
                     None
                 }
@@ -66,46 +66,95 @@ impl Optimizer {
             None => None,
         }
     }
-    fn trace_elements(&self, element: &Element, loc: &Loc) -> Element {
-        match self.reduce(element, &loc.right()) {
-            Some(reduced) => self.trace_elements(&reduced, &loc.right()),
-            None => {
-                match self.reduce(element, &loc.bottom()) {
-                    Some(reduced) => self.trace_elements(&reduced, &loc.bottom()),
-                    None => {
-                        match self.reduce(element, &loc.bottom_right()) {
-                            Some(reduced) => self.trace_elements(&reduced, &loc.bottom_right()),
-                            None => {
-                                match self.reduce(element, &loc.top_right()) {
-                                    Some(reduced) => {
-                                        self.trace_elements(&reduced, &loc.top_right())
-                                    }
-                                    None => {
-                                        //full width character CJK can reduce 2 cells apart
-                                        match self.reduce(element, &loc.right().right()){
-                                            Some(reduced) => self.trace_elements(&reduced, &loc.right().right()),
-                                            None => element.clone(),
-                                        }
-                                    }
-                                }
-                            }
-                        }
+    // only the edible cells are traced into, since the others are drawn on their own
+    fn trace_reduce(&self, element: &Element, loc2: &Loc) -> Option<Element> {
+        if self.is_edible(loc2) {
+            self.reduce(element, loc2)
+        } else {
+            None
+        }
+    }
+
+    // the cells the element passes through are added to trail,
+    // followed in a loop since a line can be as long as the row
+    fn trace_elements(&self, element: &Element, loc: &Loc, trail: &mut Vec<Loc>) -> Element {
+        if let Element::Text(ref start, ref text) = *element {
+            return self.trace_text(start, text, trail);
+        }
+        let mut element = element.clone();
+        let mut loc = loc.clone();
+        loop {
+            trail.push(loc.clone());
+            //full width character CJK can reduce 2 cells apart
+            let next = [loc.right(), loc.bottom(), loc.bottom_right(), loc.top_right(), loc.right().right()]
+                .iter()
+                .filter_map(|next| self.trace_reduce(&element, next).map(|reduced| (reduced, next.clone())))
+                .next();
+            match next {
+                Some((reduced, next)) => {
+                    element = reduced;
+                    loc = next;
+                }
+                None => return element,
+            }
+        }
+    }
+
+    // the text is merged with the text that follows it on the same row, as in `Element::reduce`,
+    // keeping the column where the merged text ends instead of measuring it at every cell
+    fn trace_text(&self, start: &Loc, text: &str, trail: &mut Vec<Loc>) -> Element {
+        let mut merged = text.to_string();
+        let mut end = start.x + text_width(text) as isize;
+        let mut loc = start.clone();
+        loop {
+            trail.push(loc.clone());
+            let next = [loc.right(), loc.right().right()]
+                .iter()
+                .filter(|next| self.is_edible(next))
+                .filter_map(|next| match self.first_element_only(next) {
+                    Some(Element::Text(ref loc2, ref text2)) if loc2.y == start.y && loc2.x == end => {
+                        Some((next.clone(), text2))
                     }
+                    _ => None,
+                })
+                .next();
+            match next {
+                Some((next, text2)) => {
+                    merged.push_str(text2);
+                    end += text_width(text2) as isize;
+                    loc = next;
                 }
+                None => return Element::Text(start.clone(), merged),
             }
         }
     }
 
+    /// the reduced elements that pass through the cell at loc,
+    /// and whether the cell was merged into an element that starts at another cell
+    pub fn reduced_at(&self, loc: &Loc) -> (Vec<Element>, bool) {
+        let mut reduced = vec![];
+        for (start, elem) in &self.elements {
+            if !self.is_edible(start) {
+                for e in elem {
+                    let mut trail = vec![];
+                    let traced = self.trace_elements(e, start, &mut trail);
+                    if trail.contains(loc) {
+                        reduced.push(traced);
+                    }
+                }
+            }
+        }
+        (reduced, self.is_edible(loc))
+    }
+
     // TODO: order the elements in such a way that
     // the start -> end -> start chains nicely
     pub fn optimize(&self, settings: &Settings) -> Vec<Element> {
         let mut optimized = vec![];
-        for &(ref loc, ref elem) in &self.elements {
-            if self.is_edible(&loc) {
-                ;
-            } else {
+        for (loc, elem) in &self.elements {
+            if !self.is_edible(loc) {
                 for e in elem {
-                    let traced = self.trace_elements(e, loc);
+                    let traced = self.trace_elements(e, loc, &mut vec![]);
                     optimized.push(traced);
                 }
             }
@@ -122,6 +171,8 @@ impl Optimizer {
         let mut merged = vec![];
         let mut solid_paths = vec![];
         let mut dashed_paths = vec![];
+        let mut heavy_paths = vec![];
+        let mut heavy_dashed_paths = vec![];
         let mut arrows = vec![];
         let mut text = vec![];
         let mut circles = vec![];
@@ -130,6 +181,9 @@ impl Optimizer {
                 Element::Circle(_,_,_) => {
                     circles.push(elm.clone());
                 },
+                Element::Polygon(_, _) => {
+                    circles.push(elm.clone());
+                },
                 Element::Line(_, _, ref stroke, ref feature) => {
                     match *feature {
                         Feature::Arrow => {
@@ -146,6 +200,12 @@ impl Optimizer {
                                 Stroke::Dashed => {
                                     dashed_paths.push(elm.clone());
                                 }
+                                Stroke::Heavy => {
+                                    heavy_paths.push(elm.clone());
+                                }
+                                Stroke::HeavyDashed => {
+                                    heavy_dashed_paths.push(elm.clone());
+                                }
                             }
                         }
                     }
@@ -159,6 +219,12 @@ impl Optimizer {
         }
         merged.push(unify(solid_paths, Stroke::Solid));
         merged.push(unify(dashed_paths, Stroke::Dashed));
+        if !heavy_paths.is_empty() {
+            merged.push(unify(heavy_paths, Stroke::Heavy));
+        }
+        if !heavy_dashed_paths.is_empty() {
+            merged.push(unify(heavy_dashed_paths, Stroke::HeavyDashed));
+        }
         merged.extend(arrows);
         merged.extend(text);
         merged.extend(circles);
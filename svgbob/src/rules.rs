@@ -0,0 +1,2566 @@
+//! The rules that decide what a character in the grid is drawn as.
+//!
+//! Each rule is a template over the character and its neighbors: a
+//! character class for the center cell, a character class for each of the
+//! neighbors it cares about, and the fragments to draw in the cell when
+//! they all match.
+//! Rules are listed from the lowest to the highest priority,
+//! when more than one rule matches a cell, the one further down the list wins.
+//!
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use super::Element;
+use super::Grid;
+use super::Loc;
+use super::Point;
+use super::Settings;
+use super::Stroke;
+use super::Feature;
+use box_drawing::box_elements;
+use box_drawing::is_box_drawing;
+use self::Fragment::*;
+use self::anchor::*;
+
+use super::{is_vertical, is_horizontal, is_horizontal_dashed, is_vertical_dashed,
+            is_low_horizontal, is_low_horizontal_dashed, is_slant_left, is_slant_right,
+            is_low_round, is_period, is_comma, is_high_round, is_backtick, is_round,
+            is_intersection, is_marker, is_asterisk, is_o, is_arrow_up, is_arrow_down,
+            is_arrow_left, is_arrow_right, is_solid_bullet, is_open_bullet,
+            is_solid_diamond, is_open_diamond, is_solid_square, is_open_square,
+            is_triangle_right, is_triangle_left, is_triangle_up, is_triangle_down,
+            is_open_curve, is_close_curve};
+
+/// a character class
+pub type Predicate = fn(&str) -> bool;
+
+/// A point in the cell, counted in quarters of the text width and text height
+/// from the top left corner of the cell.
+/// `Anchor(2.0, 2.0)` is the center of the cell, values outside 0..4
+/// reach into the neighboring cells.
+#[derive(Debug, Clone, Copy)]
+pub struct Anchor(pub f32, pub f32);
+
+/// what gets drawn on the cell when a rule matches
+#[derive(Debug, Clone, Copy)]
+pub enum Fragment {
+    Line(Anchor, Anchor),
+    DashedLine(Anchor, Anchor),
+    Arrow(Anchor, Anchor),
+    DashedArrow(Anchor, Anchor),
+    /// an arc with the radius in multiples of half the text width
+    Arc(Anchor, Anchor, f32),
+    SolidCircle,
+    OpenCircle,
+    /// a polygon around the center of the cell,
+    /// the vertices are counted in quarters of the text width
+    /// so the marker keeps its shape regardless of the text height
+    SolidPolygon(&'static [(f32, f32)]),
+    OpenPolygon(&'static [(f32, f32)]),
+    /// unicode box drawing characters, see `box_drawing`
+    BoxDrawing,
+}
+
+pub struct Rule {
+    /// the character in the cell matches any of these
+    pub this: &'static [Predicate],
+    /// each of the neighbors at (dx, dy) from the cell matches any of its predicates
+    pub neighbors: &'static [(isize, isize, &'static [Predicate])],
+    pub fragments: &'static [Fragment],
+}
+
+impl Rule {
+
+    pub fn matches(&self, grid: &Grid, loc: &Loc) -> bool {
+        grid.is_char(loc, |ch| self.this.iter().any(|f| f(ch)))
+            && self.neighbors.iter().all(|&(dx, dy, predicates)| {
+                let neighbor = Loc::new(loc.x + dx, loc.y + dy);
+                grid.is_char(&neighbor, |ch| predicates.iter().any(|f| f(ch)))
+            })
+    }
+
+    /// the elements of this rule drawn on the cell at x,y containing ch
+    pub fn elements(&self, ch: &str, x: isize, y: isize, settings: &Settings) -> Vec<Element> {
+        fragment_elements(self.fragments, ch, x, y, settings)
+    }
+
+    /// the rule in words, each class is shown as the characters it accepts
+    /// ie: "`+` with `-─` at left, `-─` at right"
+    pub fn describe(&self) -> String {
+        let mut description = describe_class(self.this);
+        let neighbors: Vec<String> = self.neighbors.iter()
+            .map(|&(dx, dy, predicates)| format!("{} at {}", describe_class(predicates), direction(dx, dy)))
+            .collect();
+        if !neighbors.is_empty() {
+            description.push_str(" with ");
+            description.push_str(&neighbors.join(", "));
+        }
+        description
+    }
+}
+
+/// the characters that are tried against the predicates when describing a rule
+fn probe_chars() -> Vec<String> {
+    let ascii = (0x21u8..0x7f).map(|b| b as char);
+    let symbols = "←↑→↓■□▲▶▼◀◆◇○●".chars();
+    let box_drawing = (0x2500u32..0x2580).filter_map(::std::char::from_u32);
+    ascii.chain(symbols).chain(box_drawing).map(|ch| ch.to_string()).collect()
+}
+
+fn describe_class(predicates: &[Predicate]) -> String {
+    let accepted: Vec<String> = probe_chars().into_iter()
+        .filter(|ch| predicates.iter().any(|f| f(ch)))
+        .collect();
+    if accepted.len() > 16 {
+        format!("`{}…`", accepted[..16].concat())
+    } else {
+        format!("`{}`", accepted.concat())
+    }
+}
+
+fn direction(dx: isize, dy: isize) -> String {
+    match (dx, dy) {
+        (0, -1) => "top".into(),
+        (0, 1) => "bottom".into(),
+        (-1, 0) => "left".into(),
+        (1, 0) => "right".into(),
+        (-1, -1) => "top_left".into(),
+        (1, -1) => "top_right".into(),
+        (-1, 1) => "bottom_left".into(),
+        (1, 1) => "bottom_right".into(),
+        _ => format!("({},{})", dx, dy),
+    }
+}
+
+/// The characters around a cell, as seen by a user defined rule
+pub struct Neighborhood<'a> {
+    grid: &'a Grid,
+    loc: &'a Loc,
+}
+
+impl<'a> Neighborhood<'a> {
+
+    /// the character at dx,dy from the cell,
+    /// empty when it is outside of the grid
+    pub fn at(&self, dx: isize, dy: isize) -> &str {
+        match self.grid.get(&Loc::new(self.loc.x + dx, self.loc.y + dy)) {
+            Some(gchar) => &gchar.string,
+            None => "",
+        }
+    }
+
+    pub fn this(&self) -> &str {
+        self.at(0, 0)
+    }
+
+    pub fn top(&self) -> &str {
+        self.at(0, -1)
+    }
+
+    pub fn bottom(&self) -> &str {
+        self.at(0, 1)
+    }
+
+    pub fn left(&self) -> &str {
+        self.at(-1, 0)
+    }
+
+    pub fn right(&self) -> &str {
+        self.at(1, 0)
+    }
+
+    pub fn top_left(&self) -> &str {
+        self.at(-1, -1)
+    }
+
+    pub fn top_right(&self) -> &str {
+        self.at(1, -1)
+    }
+
+    pub fn bottom_left(&self) -> &str {
+        self.at(-1, 1)
+    }
+
+    pub fn bottom_right(&self) -> &str {
+        self.at(1, 1)
+    }
+}
+
+/// A rule added with `Settings::add_rule`
+pub struct CustomRule {
+    predicate: Box<dyn Fn(&Neighborhood) -> bool>,
+    fragments: Vec<Fragment>,
+}
+
+impl CustomRule {
+
+    pub fn new<F>(predicate: F, fragments: &[Fragment]) -> CustomRule
+        where F: Fn(&Neighborhood) -> bool + 'static
+    {
+        CustomRule {
+            predicate: Box::new(predicate),
+            fragments: fragments.to_vec(),
+        }
+    }
+
+    pub fn matches(&self, grid: &Grid, loc: &Loc) -> bool {
+        grid.get(loc).is_some() && (self.predicate)(&Neighborhood { grid, loc })
+    }
+
+    pub fn elements(&self, ch: &str, x: isize, y: isize, settings: &Settings) -> Vec<Element> {
+        fragment_elements(&self.fragments, ch, x, y, settings)
+    }
+}
+
+fn fragment_elements(fragments: &[Fragment], ch: &str, x: isize, y: isize, settings: &Settings) -> Vec<Element> {
+    let tw = settings.text_width;
+    let th = settings.text_height;
+    let measurex = x as f32 * tw;
+    let measurey = y as f32 * th;
+    let cx = measurex + tw / 2.0;
+    let cy = measurey + th / 2.0;
+    let point = |a: &Anchor| Point::new(measurex + a.0 * tw / 4.0, measurey + a.1 * th / 4.0);
+    let polygon = |vertices: &[(f32, f32)]| -> Vec<Point> {
+        vertices.iter()
+            .map(|&(vx, vy)| Point::new(cx + vx * tw / 4.0, cy + vy * tw / 4.0))
+            .collect()
+    };
+    let line = |s: &Anchor, e: &Anchor, stroke, feature| {
+        Element::line(&point(s), &point(e), stroke, feature)
+    };
+    let mut elements = vec![];
+    for fragment in fragments {
+        match *fragment {
+            Line(ref s, ref e) => elements.push(Element::solid_line(&point(s), &point(e))),
+            DashedLine(ref s, ref e) => elements.push(line(s, e, Stroke::Dashed, Feature::Nothing)),
+            Arrow(ref s, ref e) => elements.push(line(s, e, Stroke::Solid, Feature::Arrow)),
+            DashedArrow(ref s, ref e) => elements.push(line(s, e, Stroke::Dashed, Feature::Arrow)),
+            Arc(ref s, ref e, radius) => {
+                elements.push(Element::arc(&point(s), &point(e), radius * tw / 2.0, false))
+            }
+            SolidCircle => elements.push(Element::solid_circle(&Point::new(cx, cy), tw / 2.0)),
+            OpenCircle => elements.push(Element::open_circle(&Point::new(cx, cy), tw / 2.0)),
+            SolidPolygon(vertices) => elements.push(Element::solid_polygon(&polygon(vertices))),
+            OpenPolygon(vertices) => elements.push(Element::open_polygon(&polygon(vertices))),
+            BoxDrawing => elements.extend(box_elements(ch, x, y, settings)),
+        }
+    }
+    elements
+}
+
+/// The rules compiled for lookup.
+/// Only the rules whose center character class accepts the character
+/// are tried on a cell, the candidates are worked out once per distinct character.
+pub struct RuleSet {
+    rules: &'static [Rule],
+    /// index of the candidate rules for each character, highest priority first
+    candidates: RefCell<HashMap<String, Vec<usize>>>,
+}
+
+impl RuleSet {
+
+    pub fn builtin() -> RuleSet {
+        RuleSet {
+            rules: RULES,
+            candidates: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// the highest priority rule that matches the cell at loc
+    pub fn find(&self, grid: &Grid, loc: &Loc) -> Option<&'static Rule> {
+        let gchar = grid.get(loc)?;
+        let mut candidates = self.candidates.borrow_mut();
+        let rules = self.rules;
+        let indexes = candidates.entry(gchar.string.clone()).or_insert_with(|| {
+            (0..rules.len())
+                .rev()
+                .filter(|&i| rules[i].this.iter().any(|f| f(&gchar.string)))
+                .collect()
+        });
+        indexes.iter()
+            .map(|&i| &rules[i])
+            .find(|rule| rule.matches(grid, loc))
+    }
+
+    /// every rule that matches the cell at loc with its index in the list,
+    /// lowest priority first, the last one is what `find` returns
+    pub fn matching(&self, grid: &Grid, loc: &Loc) -> Vec<(usize, &'static Rule)> {
+        let rules = self.rules;
+        (0..rules.len())
+            .filter(|&i| rules[i].matches(grid, loc))
+            .map(|i| (i, &rules[i]))
+            .collect()
+    }
+}
+
+/// Points in the cell, named by their location in the 5x5 grid of the cell.
+/// a,b,c,d,e is start, quarter, center, 3 quarters, end respectively,
+/// combining [a,b,c,d,e] * [x] and [a,b,c,d,e] * [y] gives the point,
+/// `CXCY` is the center of the cell.
+/// The extended points reach into the neighboring cells
+/// by adding [b,c,d,e] * [h] horizontal or [v] vertical quarters,
+/// `EXCHCY` is at the center of the cell on the right.
+pub mod anchor {
+    use super::Anchor;
+
+    pub const AXAY: Anchor = Anchor(0.0, 0.0);
+    pub const BXAY: Anchor = Anchor(1.0, 0.0);
+    pub const CXAY: Anchor = Anchor(2.0, 0.0);
+    pub const DXAY: Anchor = Anchor(3.0, 0.0);
+    pub const EXAY: Anchor = Anchor(4.0, 0.0);
+    pub const AXBY: Anchor = Anchor(0.0, 1.0);
+    pub const BXBY: Anchor = Anchor(1.0, 1.0);
+    pub const CXBY: Anchor = Anchor(2.0, 1.0);
+    pub const DXBY: Anchor = Anchor(3.0, 1.0);
+    pub const EXBY: Anchor = Anchor(4.0, 1.0);
+    pub const AXCY: Anchor = Anchor(0.0, 2.0);
+    pub const BXCY: Anchor = Anchor(1.0, 2.0);
+    pub const CXCY: Anchor = Anchor(2.0, 2.0);
+    pub const DXCY: Anchor = Anchor(3.0, 2.0);
+    pub const EXCY: Anchor = Anchor(4.0, 2.0);
+    pub const AXDY: Anchor = Anchor(0.0, 3.0);
+    pub const BXDY: Anchor = Anchor(1.0, 3.0);
+    pub const CXDY: Anchor = Anchor(2.0, 3.0);
+    pub const DXDY: Anchor = Anchor(3.0, 3.0);
+    pub const EXDY: Anchor = Anchor(4.0, 3.0);
+    pub const AXEY: Anchor = Anchor(0.0, 4.0);
+    pub const BXEY: Anchor = Anchor(1.0, 4.0);
+    pub const CXEY: Anchor = Anchor(2.0, 4.0);
+    pub const DXEY: Anchor = Anchor(3.0, 4.0);
+    pub const EXEY: Anchor = Anchor(4.0, 4.0);
+
+    pub const AXCHAYCV: Anchor = Anchor(-2.0, -2.0);
+    pub const CXAYCV: Anchor = Anchor(2.0, -2.0);
+    pub const EXCHAYCV: Anchor = Anchor(6.0, -2.0);
+    pub const CXAYBV: Anchor = Anchor(2.0, -1.0);
+    pub const AXEHAY: Anchor = Anchor(-4.0, 0.0);
+    pub const AXDHAY: Anchor = Anchor(-3.0, 0.0);
+    pub const AXCHAY: Anchor = Anchor(-2.0, 0.0);
+    pub const AXBHAY: Anchor = Anchor(-1.0, 0.0);
+    pub const EXBHAY: Anchor = Anchor(5.0, 0.0);
+    pub const EXCHAY: Anchor = Anchor(6.0, 0.0);
+    pub const EXDHAY: Anchor = Anchor(7.0, 0.0);
+    pub const EXEHAY: Anchor = Anchor(8.0, 0.0);
+    pub const AXCHBY: Anchor = Anchor(-2.0, 1.0);
+    pub const EXCHBY: Anchor = Anchor(6.0, 1.0);
+    pub const AXCHCY: Anchor = Anchor(-2.0, 2.0);
+    pub const EXCHCY: Anchor = Anchor(6.0, 2.0);
+    pub const AXCHDY: Anchor = Anchor(-2.0, 3.0);
+    pub const EXCHDY: Anchor = Anchor(6.0, 3.0);
+    pub const AXEHEY: Anchor = Anchor(-4.0, 4.0);
+    pub const AXDHEY: Anchor = Anchor(-3.0, 4.0);
+    pub const AXCHEY: Anchor = Anchor(-2.0, 4.0);
+    pub const AXBHEY: Anchor = Anchor(-1.0, 4.0);
+    pub const EXBHEY: Anchor = Anchor(5.0, 4.0);
+    pub const EXCHEY: Anchor = Anchor(6.0, 4.0);
+    pub const EXDHEY: Anchor = Anchor(7.0, 4.0);
+    pub const EXEHEY: Anchor = Anchor(8.0, 4.0);
+    pub const CXEYBV: Anchor = Anchor(2.0, 5.0);
+    pub const AXCHEYCV: Anchor = Anchor(-2.0, 6.0);
+    pub const CXEYCV: Anchor = Anchor(2.0, 6.0);
+    pub const EXCHEYCV: Anchor = Anchor(6.0, 6.0);
+}
+
+const AXAY_BXBY: Fragment = Line(AXAY, BXBY);
+const CXCY_AXCY: Fragment = Line(CXCY, AXCY);
+const CXCY_CXAY: Fragment = Line(CXCY, CXAY);
+const CXCY_CXEY: Fragment = Line(CXCY, CXEY);
+const CXCY_EXCY: Fragment = Line(CXCY, EXCY);
+const CXDY_CXEY: Fragment = Line(CXDY, CXEY);
+const CXAY_CXBY: Fragment = Line(CXAY, CXBY);
+const DXBY_EXAY: Fragment = Line(DXBY, EXAY);
+const AXEY_BXDY: Fragment = Line(AXEY, BXDY);
+const EXEY_DXDY: Fragment = Line(EXEY, DXDY);
+const DXCY_EXCY: Fragment = Line(DXCY, EXCY);
+const BXCY_AXCY: Fragment = Line(BXCY, AXCY);
+const EXAY_DXBY: Fragment = Line(EXAY, DXBY);
+const CXEY_CXDY: Fragment = Line(CXEY, CXDY);
+const DXDY_EXEY: Fragment = Line(DXDY, EXEY);
+const CXCY_EXEY: Fragment = Line(CXCY, EXEY);
+const CXCY_AXEY: Fragment = Line(CXCY, AXEY);
+const AXAY_CXCY: Fragment = Line(AXAY, CXCY);
+const CXCY_EXAY: Fragment = Line(CXCY, EXAY);
+const CXAY_CXCY: Fragment = Line(CXAY, CXCY);
+const AXAY_EXCY: Fragment = Line(AXAY, EXCY);
+const AXCY_EXEY: Fragment = Line(AXCY, EXEY);
+const AXCY_EXAY: Fragment = Line(AXCY, EXAY);
+const AXEY_EXCY: Fragment = Line(AXEY, EXCY);
+const EXAY_AXCY: Fragment = Line(EXAY, AXCY);
+const EXCY_AXEY: Fragment = Line(EXCY, AXEY);
+const AXEY_CXCY: Fragment = Line(AXEY, CXCY);
+const CXEY_CXCY: Fragment = Line(CXEY, CXCY);
+const AXAY_CXBY: Fragment = Line(AXAY, CXBY);
+const CXBY_EXAY: Fragment = Line(CXBY, EXAY);
+const AXCY_CXDY: Fragment = Line(AXCY, CXDY);
+const CXDY_EXCY: Fragment = Line(CXDY, EXCY);
+const CXDY_EXEY: Fragment = Line(CXDY, EXEY);
+const CXDY_AXCY: Fragment = Line(CXDY, AXCY);
+const CXDY_AXEY: Fragment = Line(CXDY, AXEY);
+const CXBY_AXCY: Fragment = Line(CXBY, AXCY);
+const CXBY_EXCY: Fragment = Line(CXBY, EXCY);
+const AXCY_EXCHBY: Fragment = Line(AXCY, EXCHBY);
+const CXDY_CXEYBV: Fragment = Line(CXDY, CXEYBV);
+const CXAYBV_CXBY: Fragment = Line(CXAYBV, CXBY);
+const EXAY_AXEHEY: Fragment = Line(EXAY, AXEHEY);
+const AXAY_EXEHEY: Fragment = Line(AXAY, EXEHEY);
+const AXCY_EXCHCY: Fragment = Line(AXCY, EXCHCY);
+const AXCHEY_EXEHEY: Fragment = Line(AXCHEY, EXEHEY);
+const EXCY_AXCHCY: Fragment = Line(EXCY, AXCHCY);
+const CXDY_CXAY: Fragment = Line(CXDY, CXAY);
+const AXCY_EXCHDY: Fragment = Line(AXCY, EXCHDY);
+const AXCHBY_EXCY: Fragment = Line(AXCHBY, EXCY);
+const AXCHDY_EXCY: Fragment = Line(AXCHDY, EXCY);
+const CXAY_CXEYCV: Fragment = Line(CXAY, CXEYCV);
+const CXAYCV_CXEY: Fragment = Line(CXAYCV, CXEY);
+const AXAY_EXCHEYCV: Fragment = Line(AXAY, EXCHEYCV);
+const EXAY_AXCHEYCV: Fragment = Line(EXAY, AXCHEYCV);
+const EXEY_AXCHAYCV: Fragment = Line(EXEY, AXCHAYCV);
+const AXEY_EXCHAYCV: Fragment = Line(AXEY, EXCHAYCV);
+const ARC_AXCY_DXBY: Fragment = Arc(AXCY, DXBY, 2.0);
+const ARC_BXBY_EXCY: Fragment = Arc(BXBY, EXCY, 2.0);
+const ARC_AXCY_BXBY: Fragment = Arc(AXCY, BXBY, 1.0);
+const ARC_CXDY_AXCY: Fragment = Arc(CXDY, AXCY, 1.0);
+const ARC_CXBY_EXCY: Fragment = Arc(CXBY, EXCY, 1.0);
+const ARC_DXDY_AXCY: Fragment = Arc(DXDY, AXCY, 2.0);
+const ARC_EXCY_CXDY: Fragment = Arc(EXCY, CXDY, 1.0);
+const ARC_EXCY_BXDY: Fragment = Arc(EXCY, BXDY, 2.0);
+const ARC_DXBY_EXCY: Fragment = Arc(DXBY, EXCY, 1.0);
+const ARC_BXDY_AXCY: Fragment = Arc(BXDY, AXCY, 1.0);
+const ARC_EXCY_DXDY: Fragment = Arc(EXCY, DXDY, 1.0);
+const ARC_DXCY_BXDY: Fragment = Arc(DXCY, BXDY, 2.0);
+const ARC_DXDY_BXCY: Fragment = Arc(DXDY, BXCY, 2.0);
+const ARC_BXBY_DXCY: Fragment = Arc(BXBY, DXCY, 2.0);
+const ARC_BXDY_BXBY: Fragment = Arc(BXDY, BXBY, 2.0);
+const ARC_DXBY_DXDY: Fragment = Arc(DXBY, DXDY, 2.0);
+const ARC_DXBY_CXDY: Fragment = Arc(DXBY, CXDY, 4.0);
+const ARC_BXDY_CXBY: Fragment = Arc(BXDY, CXBY, 4.0);
+const ARC_CXBY_DXDY: Fragment = Arc(CXBY, DXDY, 4.0);
+const ARC_CXDY_BXBY: Fragment = Arc(CXDY, BXBY, 4.0);
+const ARC_DXAY_DXEY: Fragment = Arc(DXAY, DXEY, 4.0);
+const ARC_BXEY_BXAY: Fragment = Arc(BXEY, BXAY, 4.0);
+const ARC_EXCY_AXCY: Fragment = Arc(EXCY, AXCY, 4.0);
+const ARC_AXCY_EXCY: Fragment = Arc(AXCY, EXCY, 4.0);
+const ARC_BXCY_DXBY: Fragment = Arc(BXCY, DXBY, 4.0);
+const ARC_AXCY_AXAY: Fragment = Arc(AXCY, AXAY, 4.0);
+const ARC_AXEY_EXEY: Fragment = Arc(AXEY, EXEY, 4.0);
+const ARC_CXAY_EXEY: Fragment = Arc(CXAY, EXEY, 8.0);
+const ARC_EXAY_CXEY: Fragment = Arc(EXAY, CXEY, 8.0);
+const ARC_CXEY_AXAY: Fragment = Arc(CXEY, AXAY, 8.0);
+const ARC_AXEY_CXAY: Fragment = Arc(AXEY, CXAY, 8.0);
+const ARC_CXEY_AXCY: Fragment = Arc(CXEY, AXCY, 2.0);
+const ARC_AXCY_CXAY: Fragment = Arc(AXCY, CXAY, 2.0);
+const ARC_EXCY_CXEY: Fragment = Arc(EXCY, CXEY, 2.0);
+const ARC_CXAY_EXCY: Fragment = Arc(CXAY, EXCY, 2.0);
+const ARC_EXDHEY_AXEHAY: Fragment = Arc(EXDHEY, AXEHAY, 10.0);
+const ARC_EXCHEY_DXAY: Fragment = Arc(EXCHEY, DXAY, 10.0);
+const ARC_EXEHAY_AXDHEY: Fragment = Arc(EXEHAY, AXDHEY, 10.0);
+const ARC_BXAY_AXCHEY: Fragment = Arc(BXAY, AXCHEY, 10.0);
+const ARC_AXDHAY_EXEHEY: Fragment = Arc(AXDHAY, EXEHEY, 10.0);
+const ARC_AXCHAY_BXEY: Fragment = Arc(AXCHAY, BXEY, 10.0);
+const ARC_AXEHEY_EXDHAY: Fragment = Arc(AXEHEY, EXDHAY, 10.0);
+const ARC_DXEY_EXCHAY: Fragment = Arc(DXEY, EXCHAY, 10.0);
+const ARC_AXEY_CXDY: Fragment = Arc(AXEY, CXDY, 1.0);
+const ARC_CXDY_EXEY: Fragment = Arc(CXDY, EXEY, 1.0);
+const ARC_EXCY_AXBHEY: Fragment = Arc(EXCY, AXBHEY, 4.0);
+const ARC_EXBHEY_AXCY: Fragment = Arc(EXBHEY, AXCY, 4.0);
+const ARC_AXBHAY_EXCY: Fragment = Arc(AXBHAY, EXCY, 4.0);
+const ARC_AXCY_EXBHAY: Fragment = Arc(AXCY, EXBHAY, 4.0);
+const ARC_AXCY_CXBY: Fragment = Arc(AXCY, CXBY, 1.0);
+const VERTICAL: Fragment = Line(CXAY, CXEY);
+const HORIZONTAL: Fragment = Line(AXCY, EXCY);
+const SLANT_LEFT: Fragment = Line(AXAY, EXEY);
+const SLANT_RIGHT: Fragment = Line(AXEY, EXAY);
+const LOW_HORIZONTAL: Fragment = Line(AXEY, EXEY);
+const LOW_HORIZONTAL_EXTEND_LEFT_HALF: Fragment = Line(EXEY, AXCHEY);
+const LOW_HORIZONTAL_EXTEND_RIGHT_HALF: Fragment = Line(AXEY, EXCHEY);
+const LOW_HORIZONTAL_EXTEND_LEFT_FULL: Fragment = Line(EXEY, AXEHEY);
+const LOW_HORIZONTAL_EXTEND_RIGHT_FULL: Fragment = Line(AXEY, EXEHEY);
+const VERTICAL_DASHED: Fragment = DashedLine(CXAY, CXEY);
+const HORIZONTAL_DASHED: Fragment = DashedLine(AXCY, EXCY);
+const LOW_HORIZONTAL_DASHED: Fragment = DashedLine(AXEY, EXEY);
+const ARROW_DOWN: Fragment = Arrow(CXAY, CXEY);
+const ARROW_DOWN_DASHED: Fragment = DashedArrow(CXAY, CXEY);
+const ARROW_UP: Fragment = Arrow(CXEY, CXAY);
+const ARROW_UP_DASHED: Fragment = DashedArrow(CXEY, CXAY);
+const ARROW_LEFT: Fragment = Arrow(EXCY, CXCY);
+const ARROW_LEFT_DASHED: Fragment = DashedArrow(EXCY, CXCY);
+const ARROW_RIGHT: Fragment = Arrow(AXCY, CXCY);
+const ARROW_RIGHT_DASHED: Fragment = DashedArrow(AXCY, CXCY);
+const ARROW_BOTTOM_LEFT: Fragment = Arrow(EXAY, CXCY);
+const ARROW_BOTTOM_RIGHT: Fragment = Arrow(AXAY, CXCY);
+const ARROW_TOP_LEFT: Fragment = Arrow(EXEY, CXCY);
+const ARROW_TOP_RIGHT: Fragment = Arrow(AXEY, CXCY);
+
+const DIAMOND: &[(f32, f32)] = &[(0.0, -2.0), (2.0, 0.0), (0.0, 2.0), (-2.0, 0.0)];
+const SQUARE: &[(f32, f32)] = &[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+const TRIANGLE_RIGHT: &[(f32, f32)] = &[(-2.0, -2.0), (2.0, 0.0), (-2.0, 2.0)];
+const TRIANGLE_LEFT: &[(f32, f32)] = &[(2.0, -2.0), (-2.0, 0.0), (2.0, 2.0)];
+const TRIANGLE_UP: &[(f32, f32)] = &[(-2.0, 2.0), (0.0, -2.0), (2.0, 2.0)];
+const TRIANGLE_DOWN: &[(f32, f32)] = &[(-2.0, -2.0), (0.0, 2.0), (2.0, -2.0)];
+
+/// the built-in rules, lowest priority first
+static RULES: &[Rule] = &[
+    /*
+       \|/
+       -*- (asterisk)
+       /|\
+    */
+    Rule {
+        this: &[is_asterisk],
+        neighbors: &[(-1, 0, &[is_horizontal])],
+        fragments: &[SolidCircle],
+    },
+    Rule {
+        this: &[is_asterisk],
+        neighbors: &[(1, 0, &[is_horizontal])],
+        fragments: &[SolidCircle],
+    },
+    Rule {
+        this: &[is_asterisk],
+        neighbors: &[(0, -1, &[is_vertical])],
+        fragments: &[SolidCircle],
+    },
+    Rule {
+        this: &[is_asterisk],
+        neighbors: &[(0, 1, &[is_vertical])],
+        fragments: &[SolidCircle],
+    },
+    Rule {
+        this: &[is_asterisk],
+        neighbors: &[(-1, -1, &[is_slant_left])],
+        fragments: &[SolidCircle],
+    },
+    Rule {
+        this: &[is_asterisk],
+        neighbors: &[(1, -1, &[is_slant_right])],
+        fragments: &[SolidCircle],
+    },
+    Rule {
+        this: &[is_asterisk],
+        neighbors: &[(-1, 1, &[is_slant_right])],
+        fragments: &[SolidCircle],
+    },
+    Rule {
+        this: &[is_asterisk],
+        neighbors: &[(1, 1, &[is_slant_left])],
+        fragments: &[SolidCircle],
+    },
+    /*
+       \|/
+       -o-
+       /|\
+    */
+    Rule {
+        this: &[is_o],
+        neighbors: &[(-1, 0, &[is_horizontal])],
+        fragments: &[OpenCircle],
+    },
+    Rule {
+        this: &[is_o],
+        neighbors: &[(1, 0, &[is_horizontal])],
+        fragments: &[OpenCircle],
+    },
+    Rule {
+        this: &[is_o],
+        neighbors: &[(0, -1, &[is_vertical])],
+        fragments: &[OpenCircle],
+    },
+    Rule {
+        this: &[is_o],
+        neighbors: &[(0, 1, &[is_vertical])],
+        fragments: &[OpenCircle],
+    },
+    Rule {
+        this: &[is_o],
+        neighbors: &[(-1, -1, &[is_slant_left])],
+        fragments: &[OpenCircle],
+    },
+    Rule {
+        this: &[is_o],
+        neighbors: &[(1, -1, &[is_slant_right])],
+        fragments: &[OpenCircle],
+    },
+    Rule {
+        this: &[is_o],
+        neighbors: &[(-1, 1, &[is_slant_right])],
+        fragments: &[OpenCircle],
+    },
+    Rule {
+        this: &[is_o],
+        neighbors: &[(1, 1, &[is_slant_left])],
+        fragments: &[OpenCircle],
+    },
+    /*
+       ●  ○  ◆  ◇  ■  □
+       unicode bullets are markers whether isolated or on a junction
+    */
+    Rule {
+        this: &[is_solid_bullet],
+        neighbors: &[],
+        fragments: &[SolidCircle],
+    },
+    Rule {
+        this: &[is_open_bullet],
+        neighbors: &[],
+        fragments: &[OpenCircle],
+    },
+    Rule {
+        this: &[is_solid_diamond],
+        neighbors: &[],
+        fragments: &[SolidPolygon(DIAMOND)],
+    },
+    Rule {
+        this: &[is_open_diamond],
+        neighbors: &[],
+        fragments: &[OpenPolygon(DIAMOND)],
+    },
+    Rule {
+        this: &[is_solid_square],
+        neighbors: &[],
+        fragments: &[SolidPolygon(SQUARE)],
+    },
+    Rule {
+        this: &[is_open_square],
+        neighbors: &[],
+        fragments: &[OpenPolygon(SQUARE)],
+    },
+    /*
+       ▶  ◀  ▲  ▼
+       isolated triangles, the arrow rules take over
+       when they are at the end of a line
+    */
+    Rule {
+        this: &[is_triangle_right],
+        neighbors: &[],
+        fragments: &[SolidPolygon(TRIANGLE_RIGHT)],
+    },
+    Rule {
+        this: &[is_triangle_left],
+        neighbors: &[],
+        fragments: &[SolidPolygon(TRIANGLE_LEFT)],
+    },
+    Rule {
+        this: &[is_triangle_up],
+        neighbors: &[],
+        fragments: &[SolidPolygon(TRIANGLE_UP)],
+    },
+    Rule {
+        this: &[is_triangle_down],
+        neighbors: &[],
+        fragments: &[SolidPolygon(TRIANGLE_DOWN)],
+    },
+    /*
+       |
+    */
+    Rule {
+        this: &[is_vertical],
+        neighbors: &[],
+        fragments: &[VERTICAL],
+    },
+    /*
+       -
+    */
+    Rule {
+        this: &[is_horizontal],
+        neighbors: &[],
+        fragments: &[HORIZONTAL],
+    },
+    /*
+       _
+    */
+    Rule {
+        this: &[is_low_horizontal],
+        neighbors: &[],
+        fragments: &[LOW_HORIZONTAL],
+    },
+    /*
+       :
+       :
+       must have at least 1 align to it to be treated as vertical
+    */
+    Rule {
+        this: &[is_vertical_dashed],
+        neighbors: &[(0, -1, &[is_vertical_dashed])],
+        fragments: &[VERTICAL_DASHED],
+    },
+    Rule {
+        this: &[is_vertical_dashed],
+        neighbors: &[(0, 1, &[is_vertical_dashed])],
+        fragments: &[VERTICAL_DASHED],
+    },
+    /*
+       ==  at least 2 next to it
+    */
+    Rule {
+        this: &[is_horizontal_dashed],
+        neighbors: &[
+            (-1, 0, &[is_horizontal_dashed]),
+            (1, 0, &[is_horizontal_dashed]),
+        ],
+        fragments: &[HORIZONTAL_DASHED],
+    },
+    Rule {
+        this: &[is_horizontal_dashed],
+        neighbors: &[
+            (-1, 0, &[is_horizontal_dashed]),
+            (-2, 0, &[is_horizontal_dashed]),
+        ],
+        fragments: &[HORIZONTAL_DASHED],
+    },
+    Rule {
+        this: &[is_horizontal_dashed],
+        neighbors: &[
+            (1, 0, &[is_horizontal_dashed]),
+            (2, 0, &[is_horizontal_dashed]),
+        ],
+        fragments: &[HORIZONTAL_DASHED],
+    },
+    /*
+       ...  at least 2 next to it
+    */
+    Rule {
+        this: &[is_low_horizontal_dashed],
+        neighbors: &[
+            (-1, 0, &[is_low_horizontal_dashed]),
+            (1, 0, &[is_low_horizontal_dashed]),
+        ],
+        fragments: &[LOW_HORIZONTAL_DASHED],
+    },
+    Rule {
+        this: &[is_low_horizontal_dashed],
+        neighbors: &[
+            (-1, 0, &[is_low_horizontal_dashed]),
+            (-2, 0, &[is_low_horizontal_dashed]),
+        ],
+        fragments: &[LOW_HORIZONTAL_DASHED],
+    },
+    Rule {
+        this: &[is_low_horizontal_dashed],
+        neighbors: &[
+            (1, 0, &[is_low_horizontal_dashed]),
+            (2, 0, &[is_low_horizontal_dashed]),
+        ],
+        fragments: &[LOW_HORIZONTAL_DASHED],
+    },
+    /*
+       /
+    */
+    Rule {
+        this: &[is_slant_right],
+        neighbors: &[],
+        fragments: &[SLANT_RIGHT],
+    },
+    /*
+       \
+    */
+    Rule {
+        this: &[is_slant_left],
+        neighbors: &[],
+        fragments: &[SLANT_LEFT],
+    },
+    /*
+       _
+        `-
+    */
+    Rule {
+        this: &[is_backtick],
+        neighbors: &[
+            (-1, -1, &[is_low_horizontal]),
+            (1, 0, &[is_horizontal]),
+        ],
+        fragments: &[AXAY_EXCY],
+    },
+    /*
+          __
+       --'
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (1, -1, &[is_low_horizontal]),
+            (-1, 0, &[is_horizontal]),
+        ],
+        fragments: &[AXCY_EXAY],
+    },
+    /*
+       -._
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (1, 0, &[is_low_horizontal]),
+        ],
+        fragments: &[AXCY_EXEY],
+    },
+    /*
+       _.-
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, 0, &[is_low_horizontal]),
+        ],
+        fragments: &[AXEY_EXCY],
+    },
+    /*
+       ^
+       |
+    */
+    Rule {
+        this: &[is_arrow_up],
+        neighbors: &[(0, 1, &[is_vertical])],
+        fragments: &[ARROW_UP],
+    },
+    /*
+       ^
+       :
+    */
+    Rule {
+        this: &[is_arrow_up],
+        neighbors: &[(0, 1, &[is_vertical_dashed])],
+        fragments: &[ARROW_UP_DASHED],
+    },
+    /*
+       |
+       V
+    */
+    Rule {
+        this: &[is_arrow_down],
+        neighbors: &[(0, -1, &[is_vertical])],
+        fragments: &[ARROW_DOWN],
+    },
+    /*
+       :
+       V
+    */
+    Rule {
+        this: &[is_arrow_down],
+        neighbors: &[(0, -1, &[is_vertical_dashed])],
+        fragments: &[ARROW_DOWN_DASHED],
+    },
+    /*
+       <-
+    */
+    Rule {
+        this: &[is_arrow_left],
+        neighbors: &[(1, 0, &[is_horizontal])],
+        fragments: &[ARROW_LEFT],
+    },
+    /*
+       <=
+    */
+    Rule {
+        this: &[is_arrow_left],
+        neighbors: &[(1, 0, &[is_horizontal_dashed])],
+        fragments: &[ARROW_LEFT_DASHED],
+    },
+    /*
+       ->
+    */
+    Rule {
+        this: &[is_arrow_right],
+        neighbors: &[(-1, 0, &[is_horizontal])],
+        fragments: &[ARROW_RIGHT],
+    },
+    /*
+       =>
+    */
+    Rule {
+        this: &[is_arrow_right],
+        neighbors: &[(-1, 0, &[is_horizontal_dashed])],
+        fragments: &[ARROW_RIGHT_DASHED],
+    },
+    /*
+       ^
+        \
+    */
+    Rule {
+        this: &[is_arrow_up],
+        neighbors: &[(1, 1, &[is_slant_left])],
+        fragments: &[ARROW_TOP_LEFT],
+    },
+    /*
+        ^
+       /
+    */
+    Rule {
+        this: &[is_arrow_up],
+        neighbors: &[(-1, 1, &[is_slant_right])],
+        fragments: &[ARROW_TOP_RIGHT],
+    },
+    /*
+        /
+       V
+    */
+    Rule {
+        this: &[is_arrow_down],
+        neighbors: &[(1, -1, &[is_slant_right])],
+        fragments: &[ARROW_BOTTOM_LEFT],
+    },
+    /*
+       \
+        V
+    */
+    Rule {
+        this: &[is_arrow_down],
+        neighbors: &[(-1, -1, &[is_slant_left])],
+        fragments: &[ARROW_BOTTOM_RIGHT],
+    },
+    /*
+        _  or |_
+       |
+    */
+    Rule {
+        this: &[is_low_horizontal],
+        neighbors: &[(-1, 1, &[is_vertical])],
+        fragments: &[LOW_HORIZONTAL_EXTEND_LEFT_HALF],
+    },
+    Rule {
+        this: &[is_low_horizontal],
+        neighbors: &[(-1, 0, &[is_vertical])],
+        fragments: &[LOW_HORIZONTAL_EXTEND_LEFT_HALF],
+    },
+    /*
+       _  or _|
+        |
+    */
+    Rule {
+        this: &[is_low_horizontal],
+        neighbors: &[(1, 1, &[is_vertical])],
+        fragments: &[LOW_HORIZONTAL_EXTEND_RIGHT_HALF],
+    },
+    Rule {
+        this: &[is_low_horizontal],
+        neighbors: &[(1, 0, &[is_vertical])],
+        fragments: &[LOW_HORIZONTAL_EXTEND_RIGHT_HALF],
+    },
+    /*
+       -|
+    */
+    Rule {
+        this: &[is_horizontal],
+        neighbors: &[(1, 0, &[is_vertical])],
+        fragments: &[AXCY_EXCHCY],
+    },
+    /*
+       |-
+    */
+    Rule {
+        this: &[is_horizontal],
+        neighbors: &[(-1, 0, &[is_vertical])],
+        fragments: &[EXCY_AXCHCY],
+    },
+    /*
+       /_
+    */
+    Rule {
+        this: &[is_low_horizontal],
+        neighbors: &[(-1, 0, &[is_slant_right])],
+        fragments: &[LOW_HORIZONTAL_EXTEND_LEFT_FULL],
+    },
+    /*
+       /_
+    */
+    Rule {
+        this: &[is_slant_right],
+        neighbors: &[(1, 0, &[is_low_horizontal])],
+        fragments: &[SLANT_RIGHT, LOW_HORIZONTAL_EXTEND_RIGHT_FULL],
+    },
+    /*
+       _\
+    */
+    Rule {
+        this: &[is_low_horizontal],
+        neighbors: &[(1, 0, &[is_slant_left])],
+        fragments: &[LOW_HORIZONTAL_EXTEND_RIGHT_FULL],
+    },
+    /*
+       |
+       \
+    */
+    Rule {
+        this: &[is_slant_left],
+        neighbors: &[(0, -1, &[is_vertical])],
+        fragments: &[CXCY_EXEY],
+    },
+    /*
+       |
+       \
+    */
+    Rule {
+        this: &[is_vertical],
+        neighbors: &[(0, 1, &[is_slant_left])],
+        fragments: &[CXAY_CXEYCV],
+    },
+    /*
+       |
+       /
+    */
+    Rule {
+        this: &[is_slant_right],
+        neighbors: &[(0, -1, &[is_vertical])],
+        fragments: &[CXCY_AXEY],
+    },
+    /*
+       |
+       /
+    */
+    Rule {
+        this: &[is_vertical],
+        neighbors: &[(0, 1, &[is_slant_right])],
+        fragments: &[CXAY_CXEYCV],
+    },
+    /*
+       |
+        \
+    */
+    Rule {
+        this: &[is_slant_left],
+        neighbors: &[(-1, -1, &[is_vertical])],
+        fragments: &[EXEY_AXCHAYCV],
+    },
+    /*
+       |
+        \
+    */
+    Rule {
+        this: &[is_vertical],
+        neighbors: &[(1, 1, &[is_slant_left])],
+        fragments: &[CXAY_CXCY],
+    },
+    /*
+        |
+       /
+    */
+    Rule {
+        this: &[is_slant_right],
+        neighbors: &[(1, -1, &[is_vertical])],
+        fragments: &[AXEY_EXCHAYCV],
+    },
+    /*
+        |
+       /
+    */
+    Rule {
+        this: &[is_vertical],
+        neighbors: &[(-1, 1, &[is_slant_right])],
+        fragments: &[CXAY_CXCY],
+    },
+    /*
+       /
+       |
+    */
+    Rule {
+        this: &[is_slant_right],
+        neighbors: &[(0, 1, &[is_vertical])],
+        fragments: &[CXCY_EXAY],
+    },
+    /*
+       /
+       |
+    */
+    Rule {
+        this: &[is_vertical],
+        neighbors: &[(0, -1, &[is_slant_right])],
+        fragments: &[CXAYCV_CXEY],
+    },
+    /*
+       \
+       |
+    */
+    Rule {
+        this: &[is_slant_left],
+        neighbors: &[(0, 1, &[is_vertical])],
+        fragments: &[AXAY_CXCY],
+    },
+    /*
+       \
+       |
+    */
+    Rule {
+        this: &[is_vertical],
+        neighbors: &[(0, -1, &[is_slant_left])],
+        fragments: &[CXAYCV_CXEY],
+    },
+    /*
+       \
+        |
+    */
+    Rule {
+        this: &[is_slant_left],
+        neighbors: &[(1, 1, &[is_vertical])],
+        fragments: &[AXAY_EXCHEYCV],
+    },
+    /*
+       \
+        |
+    */
+    Rule {
+        this: &[is_vertical],
+        neighbors: &[(-1, -1, &[is_slant_left])],
+        fragments: &[CXCY_CXEY],
+    },
+    /*
+        /
+       |
+    */
+    Rule {
+        this: &[is_slant_right],
+        neighbors: &[(-1, 1, &[is_vertical])],
+        fragments: &[EXAY_AXCHEYCV],
+    },
+    /*
+        /
+       |
+    */
+    Rule {
+        this: &[is_vertical],
+        neighbors: &[(1, -1, &[is_slant_right])],
+        fragments: &[CXCY_CXEY],
+    },
+    /*
+         ,     .
+       ,'    .'
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[(-1, 0, &[is_comma, is_low_round])],
+        fragments: &[EXAY_AXCY],
+    },
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[(1, -1, &[is_comma, is_low_round])],
+        fragments: &[EXAY_AXCY],
+    },
+    /*
+        ,'    .'
+       '     '
+    */
+    Rule {
+        this: &[is_comma, is_low_round],
+        neighbors: &[(1, 0, &[is_high_round])],
+        fragments: &[EXCY_AXEY],
+    },
+    Rule {
+        this: &[is_comma, is_low_round],
+        neighbors: &[(-1, 1, &[is_high_round])],
+        fragments: &[EXCY_AXEY],
+    },
+    /*
+       `.
+         `
+    */
+    Rule {
+        this: &[is_period],
+        neighbors: &[(1, 1, &[is_backtick])],
+        fragments: &[AXCY_EXEY],
+    },
+    Rule {
+        this: &[is_period],
+        neighbors: &[(-1, 0, &[is_backtick])],
+        fragments: &[AXCY_EXEY],
+    },
+    /*
+       .
+        `.
+    */
+    Rule {
+        this: &[is_backtick],
+        neighbors: &[(1, 0, &[is_period])],
+        fragments: &[AXAY_EXCY],
+    },
+    Rule {
+        this: &[is_backtick],
+        neighbors: &[(-1, -1, &[is_period])],
+        fragments: &[AXAY_EXCY],
+    },
+    /*
+         ,     .
+       /'    /'
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (1, -1, &[is_comma, is_low_round]),
+            (-1, 0, &[is_slant_right]),
+        ],
+        fragments: &[EXAY_AXEHEY],
+    },
+    /*
+       _
+        `.
+    */
+    Rule {
+        this: &[is_backtick],
+        neighbors: &[
+            (-1, -1, &[is_low_horizontal]),
+            (1, 0, &[is_low_round]),
+        ],
+        fragments: &[AXAY_EXCY],
+    },
+    /*
+       speech bubble
+        .
+         `\
+    */
+    Rule {
+        this: &[is_backtick],
+        neighbors: &[
+            (-1, -1, &[is_low_round]),
+            (1, 0, &[is_slant_left]),
+        ],
+        fragments: &[AXAY_EXEHEY],
+    },
+    /*
+       speech bubble
+        _
+         `\
+    */
+    Rule {
+        this: &[is_backtick],
+        neighbors: &[
+            (-1, -1, &[is_low_horizontal]),
+            (1, 0, &[is_slant_left]),
+        ],
+        fragments: &[AXAY_EXEHEY],
+    },
+    /*
+       `.
+         \
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (-1, 0, &[is_backtick]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[ARC_EXDHEY_AXEHAY],
+    },
+    /*
+       `.
+         \
+    */
+    Rule {
+        this: &[is_backtick],
+        neighbors: &[
+            (1, 0, &[is_low_round]),
+            (2, 1, &[is_slant_left]),
+        ],
+        fragments: &[],
+    },
+    /*
+       `.
+         \
+    */
+    Rule {
+        this: &[is_slant_left],
+        neighbors: &[
+            (-1, -1, &[is_low_round]),
+            (-2, -1, &[is_backtick]),
+        ],
+        fragments: &[ARC_EXCHEY_DXAY],
+    },
+    /*
+        ,'
+       /
+    */
+    Rule {
+        this: &[is_comma],
+        neighbors: &[
+            (1, 0, &[is_high_round]),
+            (-1, 1, &[is_slant_right]),
+        ],
+        fragments: &[ARC_EXEHAY_AXDHEY],
+    },
+    /*
+        ,'
+       /
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (-1, 0, &[is_comma]),
+            (-2, 1, &[is_slant_right]),
+        ],
+        fragments: &[],
+    },
+    /*
+        ,'
+       /
+    */
+    Rule {
+        this: &[is_slant_right],
+        neighbors: &[
+            (1, -1, &[is_comma]),
+            (2, -1, &[is_high_round]),
+        ],
+        fragments: &[ARC_BXAY_AXCHEY],
+    },
+    /*
+       \    \
+        `.   ',
+    */
+    Rule {
+        this: &[is_high_round, is_backtick],
+        neighbors: &[
+            (1, 0, &[is_low_round, is_comma]),
+            (-1, -1, &[is_slant_left]),
+        ],
+        fragments: &[ARC_AXDHAY_EXEHEY],
+    },
+    /*
+       \    \
+        `.   ',
+    */
+    Rule {
+        this: &[is_low_round, is_comma],
+        neighbors: &[
+            (-1, 0, &[is_high_round, is_backtick]),
+            (-2, -1, &[is_slant_left]),
+        ],
+        fragments: &[],
+    },
+    /*
+       \    \
+        `.   ',
+    */
+    Rule {
+        this: &[is_slant_left],
+        neighbors: &[
+            (1, 1, &[is_high_round, is_backtick]),
+            (2, 1, &[is_low_round, is_comma]),
+        ],
+        fragments: &[ARC_AXCHAY_BXEY],
+    },
+    /*
+         /   /
+       .'  ,'
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (-1, 0, &[is_low_round, is_comma]),
+            (1, -1, &[is_slant_right]),
+        ],
+        fragments: &[ARC_AXEHEY_EXDHAY],
+    },
+    /*
+         /   /
+       .'  ,'
+    */
+    Rule {
+        this: &[is_slant_right],
+        neighbors: &[
+            (-2, 1, &[is_low_round, is_comma]),
+            (-1, 1, &[is_high_round]),
+        ],
+        fragments: &[ARC_DXEY_EXCHAY],
+    },
+    /*
+         /   /
+       .'  ,'
+    */
+    Rule {
+        this: &[is_low_round, is_comma],
+        neighbors: &[
+            (2, -1, &[is_slant_right]),
+            (1, 0, &[is_high_round]),
+        ],
+        fragments: &[],
+    },
+    /*
+       . ,   . ,
+        '     `
+    */
+    Rule {
+        this: &[is_high_round, is_backtick],
+        neighbors: &[
+            (1, -1, &[is_comma]),
+            (-1, -1, &[is_low_round]),
+        ],
+        fragments: &[AXAY_CXBY, CXBY_EXAY],
+    },
+    /*
+       '.'  `.'
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (-1, 0, &[is_high_round, is_backtick]),
+            (1, 0, &[is_high_round]),
+        ],
+        fragments: &[AXCY_CXDY, CXDY_EXCY],
+    },
+    /*
+       .'    or  .'
+        `         '
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (1, 0, &[is_high_round]),
+            (1, 1, &[is_high_round, is_backtick]),
+        ],
+        fragments: &[CXDY_EXCY, CXDY_EXEY],
+    },
+    /*
+       `,   or   `.  or  '.
+       '         '       '
+    */
+    Rule {
+        this: &[is_low_round, is_comma],
+        neighbors: &[
+            (-1, 0, &[is_high_round, is_backtick]),
+            (-1, 1, &[is_high_round]),
+        ],
+        fragments: &[CXDY_AXCY, CXDY_AXEY],
+    },
+    /*
+        .       .
+       ' `  or ' '
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (-1, 1, &[is_high_round]),
+            (1, 1, &[is_high_round, is_backtick]),
+        ],
+        fragments: &[CXDY_AXEY, CXDY_EXEY],
+    },
+    /*
+       .'.  or    ,'.
+    */
+    Rule {
+        this: &[is_high_round, is_backtick],
+        neighbors: &[
+            (1, 0, &[is_low_round]),
+            (-1, 0, &[is_low_round, is_comma]),
+        ],
+        fragments: &[CXBY_AXCY, CXBY_EXCY],
+    },
+    /*
+       +-
+       |
+    */
+    Rule {
+        this: &[is_intersection],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (0, 1, &[is_vertical]),
+        ],
+        fragments: &[CXCY_CXEY, CXCY_EXCY],
+    },
+    /*
+       -+
+        |
+    */
+    Rule {
+        this: &[is_intersection],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (0, 1, &[is_vertical]),
+        ],
+        fragments: &[CXCY_CXEY, CXCY_AXCY],
+    },
+    /*
+       |
+       +-
+    */
+    Rule {
+        this: &[is_intersection],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (0, -1, &[is_vertical]),
+        ],
+        fragments: &[CXCY_CXAY, CXCY_EXCY],
+    },
+    /*
+        |
+       -+
+    */
+    Rule {
+        this: &[is_intersection],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (0, -1, &[is_vertical]),
+        ],
+        fragments: &[CXCY_CXAY, CXCY_AXCY],
+    },
+    /*
+       .-   ,-
+       |    |
+    */
+    Rule {
+        this: &[is_round, is_comma],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (0, 1, &[is_vertical]),
+        ],
+        fragments: &[CXDY_CXEY, ARC_EXCY_CXDY],
+    },
+    /*
+       -.
+        |
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (0, 1, &[is_vertical]),
+        ],
+        fragments: &[CXDY_CXEY, ARC_CXDY_AXCY],
+    },
+    /*
+       |       |
+       '-      `-
+    */
+    Rule {
+        this: &[is_round, is_backtick],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (0, -1, &[is_vertical]),
+        ],
+        fragments: &[CXAY_CXBY, ARC_CXBY_EXCY],
+    },
+    /*
+        |
+       -'
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (0, -1, &[is_vertical]),
+        ],
+        fragments: &[CXAY_CXBY, ARC_AXCY_CXBY],
+    },
+    /*
+        |
+       _'
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (-1, 0, &[is_low_horizontal]),
+            (0, -1, &[is_vertical]),
+        ],
+        fragments: &[ARC_AXEY_CXDY, CXDY_CXAY],
+    },
+    /*
+       |
+       '_
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (1, 0, &[is_low_horizontal]),
+            (0, -1, &[is_vertical]),
+        ],
+        fragments: &[CXDY_CXAY, ARC_CXDY_EXEY],
+    },
+    /*
+        .-
+       /
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, 1, &[is_slant_right]),
+        ],
+        fragments: &[AXEY_BXDY, ARC_EXCY_BXDY],
+    },
+    /*
+       -.
+         \
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[EXEY_DXDY, ARC_DXDY_AXCY],
+    },
+    /*
+       -.
+       /
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (-1, 1, &[is_slant_right]),
+        ],
+        fragments: &[AXEY_BXDY, ARC_BXDY_AXCY],
+    },
+    /*
+       .-
+        \
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[EXEY_DXDY, ARC_EXCY_DXDY],
+    },
+    /*
+       \
+        '-
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, -1, &[is_slant_left]),
+        ],
+        fragments: &[AXAY_BXBY, ARC_BXBY_EXCY],
+    },
+    /*
+        /
+       '-
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (1, -1, &[is_slant_right]),
+        ],
+        fragments: &[DXBY_EXAY, ARC_DXBY_EXCY],
+    },
+    /*
+       \
+       -'
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (-1, -1, &[is_slant_left]),
+        ],
+        fragments: &[AXAY_BXBY, ARC_AXCY_BXBY],
+    },
+    /*
+         /
+       -'
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (1, -1, &[is_slant_right]),
+        ],
+        fragments: &[DXBY_EXAY, ARC_AXCY_DXBY],
+    },
+    /*
+       \       \
+        .  or   )
+       /       /
+    */
+    Rule {
+        this: &[is_round, is_close_curve],
+        neighbors: &[
+            (-1, -1, &[is_slant_left]),
+            (-1, 1, &[is_slant_right]),
+        ],
+        fragments: &[AXAY_BXBY, AXEY_BXDY, ARC_BXDY_BXBY],
+    },
+    /*
+        /       /
+       .  or   (
+        \       \
+    */
+    Rule {
+        this: &[is_round, is_open_curve],
+        neighbors: &[
+            (1, -1, &[is_slant_right]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[EXAY_DXBY, EXEY_DXDY, ARC_DXBY_DXDY],
+    },
+    /*
+        .      .     ,
+       (  or  (  or (
+        '      `     `
+    */
+    Rule {
+        this: &[is_open_curve],
+        neighbors: &[
+            (1, -1, &[is_round, is_comma]),
+            (1, 1, &[is_high_round, is_backtick]),
+        ],
+        fragments: &[ARC_DXAY_DXEY],
+    },
+    /*
+       .
+        )
+       '
+    */
+    Rule {
+        this: &[is_close_curve],
+        neighbors: &[
+            (-1, -1, &[is_round]),
+            (-1, 1, &[is_round]),
+        ],
+        fragments: &[ARC_BXEY_BXAY],
+    },
+    /*
+        .-          ,-
+       (     or    (
+    */
+    Rule {
+        this: &[is_low_round, is_comma],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, 1, &[is_open_curve]),
+        ],
+        fragments: &[ARC_EXCY_AXBHEY],
+    },
+    /*
+       -.
+         )
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (1, 1, &[is_close_curve]),
+        ],
+        fragments: &[ARC_EXBHEY_AXCY],
+    },
+    /*
+       (    or   (
+        '-        `-
+    */
+    Rule {
+        this: &[is_high_round, is_backtick],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, -1, &[is_open_curve]),
+        ],
+        fragments: &[ARC_AXBHAY_EXCY],
+    },
+    /*
+         )
+       -'
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (1, -1, &[is_close_curve]),
+        ],
+        fragments: &[ARC_AXCY_EXBHAY],
+    },
+    /*
+       .-
+       '
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (0, 1, &[is_high_round]),
+        ],
+        fragments: &[ARC_EXCY_CXDY, CXDY_CXEY],
+    },
+    /*
+       -.
+        '
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (0, 1, &[is_high_round]),
+        ],
+        fragments: &[ARC_CXDY_AXCY, CXDY_CXEY],
+    },
+    /*
+       .
+       '-
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (0, -1, &[is_low_round]),
+        ],
+        fragments: &[ARC_CXBY_EXCY, CXAY_CXBY],
+    },
+    /*
+        .
+       -'
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (0, -1, &[is_low_round]),
+        ],
+        fragments: &[ARC_AXCY_CXBY, CXAY_CXBY],
+    },
+    /*
+       .-.
+    */
+    Rule {
+        this: &[is_horizontal],
+        neighbors: &[
+            (-1, 0, &[is_low_round]),
+            (1, 0, &[is_low_round]),
+        ],
+        fragments: &[ARC_EXCY_AXCY],
+    },
+    /*
+       ._.
+    */
+    Rule {
+        this: &[is_low_horizontal],
+        neighbors: &[
+            (-1, 0, &[is_low_round]),
+            (1, 0, &[is_low_round]),
+        ],
+        fragments: &[ARC_AXEY_EXEY],
+    },
+    /*
+       .
+       .'
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (-1, 0, &[is_low_round]),
+            (-1, -1, &[is_low_round]),
+        ],
+        fragments: &[ARC_AXCY_AXAY],
+    },
+    /*
+        .-.
+       (
+    */
+    Rule {
+        this: &[is_horizontal],
+        neighbors: &[
+            (-1, 0, &[is_low_round]),
+            (1, 0, &[is_low_round]),
+            (-2, 1, &[is_open_curve]),
+        ],
+        fragments: &[ARC_EXCY_AXCY],
+    },
+    /*
+       (     or  (
+        '-'       `-'
+    */
+    Rule {
+        this: &[is_horizontal],
+        neighbors: &[
+            (-1, 0, &[is_high_round, is_backtick]),
+            (1, 0, &[is_high_round]),
+            (-2, -1, &[is_open_curve]),
+        ],
+        fragments: &[ARC_AXCY_EXCY],
+    },
+    /*
+        /
+       .
+       |
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, 1, &[is_vertical]),
+            (1, -1, &[is_slant_right]),
+        ],
+        fragments: &[EXAY_DXBY, CXEY_CXDY, ARC_DXBY_CXDY],
+    },
+    /*
+        |
+        .
+       /
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, -1, &[is_vertical]),
+            (-1, 1, &[is_slant_right]),
+        ],
+        fragments: &[CXAY_CXBY, AXEY_BXDY, ARC_BXDY_CXBY],
+    },
+    /*
+       \
+        .
+        |
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, 1, &[is_vertical]),
+            (-1, -1, &[is_slant_left]),
+        ],
+        fragments: &[AXAY_BXBY, CXDY_CXEY, ARC_CXDY_BXBY],
+    },
+    /*
+       |
+       .
+        \
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, -1, &[is_vertical]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[CXAY_CXBY, DXDY_EXEY, ARC_CXBY_DXDY],
+    },
+    /*
+        .
+       / \
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (-1, 1, &[is_slant_right]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[AXEY_CXCY, CXCY_EXEY],
+    },
+    /*
+       \ /
+        '
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (-1, -1, &[is_slant_left]),
+            (1, -1, &[is_slant_right]),
+        ],
+        fragments: &[AXAY_CXCY, CXCY_EXAY],
+    },
+    /*
+        |
+       / \
+    */
+    Rule {
+        this: &[is_vertical],
+        neighbors: &[
+            (-1, 1, &[is_slant_right]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[AXEY_CXCY, CXCY_EXEY, CXAY_CXCY],
+    },
+    /*
+       \ /
+        |
+    */
+    Rule {
+        this: &[is_vertical],
+        neighbors: &[
+            (-1, -1, &[is_slant_left]),
+            (1, -1, &[is_slant_right]),
+        ],
+        fragments: &[AXAY_CXCY, CXCY_EXAY, CXEY_CXCY],
+    },
+    /*
+       .
+       |\
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, 1, &[is_vertical]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[CXCY_CXEY, CXCY_EXEY],
+    },
+    /*
+        .
+       /|
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, 1, &[is_vertical]),
+            (-1, 1, &[is_slant_right]),
+        ],
+        fragments: &[CXCY_CXEY, CXCY_AXEY],
+    },
+    /*
+       \|
+        '
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, -1, &[is_vertical]),
+            (-1, -1, &[is_slant_left]),
+        ],
+        fragments: &[AXAY_CXCY, CXCY_CXAY],
+    },
+    /*
+       |/
+       '
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, -1, &[is_vertical]),
+            (1, -1, &[is_slant_right]),
+        ],
+        fragments: &[CXAY_CXCY, CXCY_EXAY],
+    },
+    /*
+       -.
+        (
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (0, 1, &[is_open_curve]),
+        ],
+        fragments: &[ARC_CXEY_AXCY],
+    },
+    /*
+        (
+       -'
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (0, -1, &[is_open_curve]),
+            (-1, 0, &[is_horizontal]),
+        ],
+        fragments: &[ARC_AXCY_CXAY],
+    },
+    /*
+       .-
+       )
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (0, 1, &[is_close_curve]),
+        ],
+        fragments: &[ARC_EXCY_CXEY],
+    },
+    /*
+       )
+       '-
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (0, -1, &[is_close_curve]),
+        ],
+        fragments: &[ARC_CXAY_EXCY],
+    },
+    /*
+       (
+        >
+    */
+    Rule {
+        this: &[is_open_curve],
+        neighbors: &[(1, 1, &[is_arrow_right])],
+        fragments: &[ARC_CXAY_EXEY],
+    },
+    /*
+        >
+       (
+    */
+    Rule {
+        this: &[is_open_curve],
+        neighbors: &[(1, -1, &[is_arrow_right])],
+        fragments: &[ARC_EXAY_CXEY],
+    },
+    /*
+       expandable close bracket
+
+         (
+          >
+         (
+    */
+    Rule {
+        this: &[is_arrow_right],
+        neighbors: &[
+            (-1, -1, &[is_open_curve]),
+            (-1, 1, &[is_open_curve]),
+        ],
+        fragments: &[AXAY_EXCY, AXEY_EXCY],
+    },
+    /*
+        )
+       <
+    */
+    Rule {
+        this: &[is_close_curve],
+        neighbors: &[(-1, 1, &[is_arrow_left])],
+        fragments: &[ARC_AXEY_CXAY],
+    },
+    /*
+       <
+        )
+    */
+    Rule {
+        this: &[is_close_curve],
+        neighbors: &[(-1, -1, &[is_arrow_left])],
+        fragments: &[ARC_CXEY_AXAY],
+    },
+    /*
+       expandable open brcket
+
+         )
+        <
+         )
+    */
+    Rule {
+        this: &[is_arrow_left],
+        neighbors: &[
+            (1, -1, &[is_close_curve]),
+            (1, 1, &[is_close_curve]),
+        ],
+        fragments: &[AXCY_EXAY, AXCY_EXEY],
+    },
+    /*
+        .-
+       <
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, 1, &[is_arrow_left]),
+        ],
+        fragments: &[ARC_EXCY_CXDY, CXDY_CXEYBV],
+    },
+    /*
+       left speech balloon pointer
+         .
+        <
+         '
+    */
+    Rule {
+        this: &[is_arrow_left],
+        neighbors: &[
+            (1, -1, &[is_low_round]),
+            (1, 1, &[is_high_round]),
+        ],
+        fragments: &[AXCY_EXCHBY, AXCY_EXCHDY],
+    },
+    /*
+       <
+        '-
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, -1, &[is_arrow_left]),
+        ],
+        fragments: &[ARC_CXBY_EXCY, CXAYBV_CXBY],
+    },
+    /*
+       right speech balloon pointer
+         .
+          >
+         '
+    */
+    Rule {
+        this: &[is_arrow_right],
+        neighbors: &[
+            (-1, -1, &[is_low_round]),
+            (-1, 1, &[is_high_round]),
+        ],
+        fragments: &[AXCHBY_EXCY, AXCHDY_EXCY],
+    },
+    /*
+         >
+       -'
+    */
+    Rule {
+        this: &[is_high_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (1, -1, &[is_arrow_right]),
+        ],
+        fragments: &[ARC_AXCY_CXBY, CXAYBV_CXBY],
+    },
+    /*
+       -.
+         >
+    */
+    Rule {
+        this: &[is_low_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (1, 1, &[is_arrow_right]),
+        ],
+        fragments: &[ARC_CXDY_AXCY, CXDY_CXEYBV],
+    },
+    /*
+       |_\
+    */
+    Rule {
+        this: &[is_low_horizontal],
+        neighbors: &[
+            (-1, 0, &[is_vertical]),
+            (1, 0, &[is_slant_left]),
+        ],
+        fragments: &[AXCHEY_EXEHEY],
+    },
+    /*
+         /
+        .-
+       /
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (1, -1, &[is_slant_right]),
+            (-1, 1, &[is_slant_right]),
+        ],
+        fragments: &[SLANT_RIGHT, DXCY_EXCY, ARC_DXCY_BXDY],
+    },
+    /*
+         /
+       -.
+       /
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (1, -1, &[is_slant_right]),
+            (-1, 1, &[is_slant_right]),
+        ],
+        fragments: &[SLANT_RIGHT, BXCY_AXCY, ARC_BXCY_DXBY],
+    },
+    /*
+       \
+       -.
+         \
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (-1, -1, &[is_slant_left]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[SLANT_LEFT, BXCY_AXCY, ARC_DXDY_BXCY],
+    },
+    /*
+       \
+        .-
+         \
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, -1, &[is_slant_left]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[SLANT_LEFT, DXCY_EXCY, ARC_BXBY_DXCY],
+    },
+    /*
+       -.-
+       /
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, 0, &[is_horizontal]),
+            (-1, 1, &[is_slant_right]),
+        ],
+        fragments: &[HORIZONTAL, AXEY_BXDY, ARC_EXCY_BXDY],
+    },
+    /*
+       -.-
+         \
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, 0, &[is_horizontal]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[HORIZONTAL, DXDY_EXEY, ARC_DXDY_AXCY],
+    },
+    /*
+         /
+       -'-
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, 0, &[is_horizontal]),
+            (1, -1, &[is_slant_right]),
+        ],
+        fragments: &[HORIZONTAL, DXBY_EXAY, ARC_AXCY_DXBY],
+    },
+    /*
+       \
+       -'-
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (-1, 0, &[is_horizontal]),
+            (-1, -1, &[is_slant_left]),
+        ],
+        fragments: &[HORIZONTAL, AXAY_BXBY, ARC_BXBY_EXCY],
+    },
+    /*
+        |
+       -+-
+    */
+    Rule {
+        this: &[is_intersection],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (1, 0, &[is_horizontal]),
+            (0, -1, &[is_vertical]),
+        ],
+        fragments: &[CXCY_CXAY, HORIZONTAL],
+    },
+    /*
+       -+-
+        |
+    */
+    Rule {
+        this: &[is_intersection],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (1, 0, &[is_horizontal]),
+            (0, 1, &[is_vertical]),
+        ],
+        fragments: &[CXCY_CXEY, HORIZONTAL],
+    },
+    /*
+        |
+       -+
+        |
+    */
+    Rule {
+        this: &[is_intersection],
+        neighbors: &[
+            (-1, 0, &[is_horizontal]),
+            (0, -1, &[is_vertical]),
+            (0, 1, &[is_vertical]),
+        ],
+        fragments: &[VERTICAL, CXCY_AXCY],
+    },
+    /*
+       |
+       +-
+       |
+    */
+    Rule {
+        this: &[is_intersection],
+        neighbors: &[
+            (1, 0, &[is_horizontal]),
+            (0, -1, &[is_vertical]),
+            (0, 1, &[is_vertical]),
+        ],
+        fragments: &[VERTICAL, CXCY_EXCY],
+    },
+    /*
+        |
+        .
+       /|
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, 1, &[is_vertical]),
+            (0, -1, &[is_vertical]),
+            (-1, 1, &[is_slant_right]),
+        ],
+        fragments: &[VERTICAL, AXEY_BXDY, ARC_BXDY_CXBY],
+    },
+    /*
+       |
+       .
+       |\
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, 1, &[is_vertical]),
+            (0, -1, &[is_vertical]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[VERTICAL, EXEY_DXDY, ARC_CXBY_DXDY],
+    },
+    /*
+        |
+        .
+       / \
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, -1, &[is_vertical]),
+            (-1, 1, &[is_slant_right]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[CXAY_CXCY, CXCY_EXEY, CXCY_AXEY],
+    },
+    /*
+       |/
+       '
+       |
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, -1, &[is_vertical]),
+            (0, 1, &[is_vertical]),
+            (1, -1, &[is_slant_right]),
+        ],
+        fragments: &[VERTICAL, CXCY_EXAY],
+    },
+    /*
+       \|
+        '
+        |
+    */
+    Rule {
+        this: &[is_round],
+        neighbors: &[
+            (0, -1, &[is_vertical]),
+            (0, 1, &[is_vertical]),
+            (-1, -1, &[is_slant_left]),
+        ],
+        fragments: &[VERTICAL, AXAY_CXCY],
+    },
+    /*
+        |
+       -+-
+        |
+    */
+    Rule {
+        this: &[is_intersection, is_round, is_marker],
+        neighbors: &[
+            (0, -1, &[is_vertical]),
+            (0, 1, &[is_vertical]),
+            (-1, 0, &[is_horizontal]),
+            (1, 0, &[is_horizontal]),
+        ],
+        fragments: &[VERTICAL, HORIZONTAL],
+    },
+    /*
+        :
+       =+=
+        :
+    */
+    Rule {
+        this: &[is_intersection, is_round, is_marker],
+        neighbors: &[
+            (0, -1, &[is_vertical_dashed]),
+            (0, 1, &[is_vertical_dashed]),
+            (-1, 0, &[is_horizontal_dashed]),
+            (1, 0, &[is_horizontal_dashed]),
+        ],
+        fragments: &[VERTICAL_DASHED, HORIZONTAL_DASHED],
+    },
+    /*
+       \|/
+        +
+       /|\
+    */
+    Rule {
+        this: &[is_intersection, is_round, is_marker],
+        neighbors: &[
+            (0, -1, &[is_vertical]),
+            (0, 1, &[is_vertical]),
+            (-1, -1, &[is_slant_left]),
+            (1, -1, &[is_slant_right]),
+            (-1, 1, &[is_slant_right]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[VERTICAL, SLANT_LEFT, SLANT_RIGHT],
+    },
+    /*
+       \|/
+       -+-
+       /|\
+    */
+    Rule {
+        this: &[is_intersection, is_round, is_marker],
+        neighbors: &[
+            (0, -1, &[is_vertical]),
+            (0, 1, &[is_vertical]),
+            (-1, 0, &[is_horizontal]),
+            (1, 0, &[is_horizontal]),
+            (-1, -1, &[is_slant_left]),
+            (1, -1, &[is_slant_right]),
+            (-1, 1, &[is_slant_right]),
+            (1, 1, &[is_slant_left]),
+        ],
+        fragments: &[VERTICAL, HORIZONTAL, SLANT_LEFT, SLANT_RIGHT],
+    },
+    /*
+       ╭─┬─╮
+       │ ║ │  unicode box drawing characters are unambiguous
+       ╰─┴─╯  and don't need to look at the neighbors
+    */
+    Rule {
+        this: &[is_box_drawing],
+        neighbors: &[],
+        fragments: &[BoxDrawing],
+    },
+];
+
+#[test]
+fn test_rule_matches(){
+    // -+-  the horizontal neighbors of the plus
+    let rule = Rule {
+        this: &[is_intersection],
+        neighbors: &[(-1, 0, &[is_horizontal]), (1, 0, &[is_horizontal])],
+        fragments: &[HORIZONTAL],
+    };
+    let grid = Grid::from_str("-+-\n-+");
+    assert!(rule.matches(&grid, &Loc::new(1, 0)));
+    assert!(!rule.matches(&grid, &Loc::new(1, 1)));
+    assert!(!rule.matches(&grid, &Loc::new(0, 0)));
+    let elements = rule.elements("+", 1, 0, &Settings::default());
+    assert_eq!(elements.len(), 1);
+}
+
+#[test]
+fn test_later_rule_wins(){
+    let rules = RuleSet::builtin();
+    // a lone period is not matched by any rule, it is text
+    let grid = Grid::from_str(".");
+    assert!(rules.find(&grid, &Loc::new(0, 0)).is_none());
+    // a rounded corner
+    let grid = Grid::from_str(".-\n|");
+    let rule = rules.find(&grid, &Loc::new(0, 0)).unwrap();
+    assert!(rule.this.iter().any(|f| f(".")));
+    assert!(!rule.neighbors.is_empty());
+    // the vertical line still matches the lower priority `|` rule
+    let rule = rules.find(&grid, &Loc::new(0, 1)).unwrap();
+    assert!(rule.neighbors.is_empty());
+}
+
+#[test]
+fn test_describe_rule(){
+    let rule = Rule {
+        this: &[is_intersection],
+        neighbors: &[(-1, 0, &[is_horizontal]), (0, 1, &[is_vertical])],
+        fragments: &[HORIZONTAL],
+    };
+    assert_eq!(rule.describe(), "`+` with `-─` at left, `|│` at bottom");
+}
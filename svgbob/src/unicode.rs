@@ -0,0 +1,336 @@
+//! Draw the elements back as text, with the unicode box drawing characters:
+//! the lines as `─ │ ╱ ╲`, the rounded corners as `╭ ╮ ╰ ╯`,
+//! the junctions as `┼ ├ ┬`.. and the arrow heads as `→ ↑ ← ↓`.
+//!
+//! The elements are placed in the cells of the grid
+//! by dividing each cell in 4 by 4, the same points used in `get_elements`.
+//!
+use unicode_segmentation::UnicodeSegmentation;
+
+use box_drawing::{find_box_char, Arm};
+use super::{grapheme_width, Element, Feature, Point, Settings, Stroke};
+
+const TOP: usize = 0;
+const RIGHT: usize = 1;
+const BOTTOM: usize = 2;
+const LEFT: usize = 3;
+
+#[derive(Clone)]
+struct Cell {
+    /// the lines going out of the center of the cell to each side:
+    /// top, right, bottom, left
+    arms: [Arm; 4],
+    /// `╱` and `╲` lines crossing the cell
+    slash: bool,
+    backslash: bool,
+    /// a line along the bottom edge of the cell
+    low: bool,
+    /// a character that replaces the lines: arrow heads, circles
+    mark: Option<&'static str>,
+    /// the text, followed by empty cells for the wide characters
+    text: Option<String>,
+}
+
+impl Cell {
+    fn new() -> Cell {
+        Cell {
+            arms: [Arm::Empty; 4],
+            slash: false,
+            backslash: false,
+            low: false,
+            mark: None,
+            text: None,
+        }
+    }
+
+    fn add_arm(&mut self, side: usize, arm: Arm) {
+        if rank(arm) > rank(self.arms[side]) {
+            self.arms[side] = arm;
+        }
+    }
+
+    fn to_char(&self) -> Option<String> {
+        if let Some(ref text) = self.text {
+            return Some(text.clone());
+        }
+        if let Some(mark) = self.mark {
+            return Some(mark.into());
+        }
+        if self.arms.iter().any(|&arm| arm != Arm::Empty) {
+            return Some(arms_char(self.arms).into());
+        }
+        match (self.slash, self.backslash) {
+            (true, true) => Some("╳".into()),
+            (true, false) => Some("╱".into()),
+            (false, true) => Some("╲".into()),
+            (false, false) if self.low => Some("_".into()),
+            (false, false) => Some(" ".into()),
+        }
+    }
+}
+
+/// the arm kept when 2 lines come out of the same side:
+/// the rounded corners over the straight lines over the dashes
+fn rank(arm: Arm) -> u8 {
+    match arm {
+        Arm::Empty => 0,
+        Arm::Dashed => 1,
+        Arm::HeavyDashed => 2,
+        Arm::Light => 3,
+        Arm::Double => 4,
+        Arm::Heavy => 5,
+        Arm::Round => 6,
+    }
+}
+
+/// the box drawing character with these arms, otherwise the closest one
+/// with the dashes and the rounded corners drawn as light lines, the heavy dashes as heavy lines,
+/// and then with every line drawn light
+fn arms_char(arms: [Arm; 4]) -> &'static str {
+    let light = |arm: Arm| match arm {
+        Arm::Dashed | Arm::Round => Arm::Light,
+        Arm::HeavyDashed => Arm::Heavy,
+        arm => arm,
+    };
+    let all_light = |arm: Arm| if arm == Arm::Empty { Arm::Empty } else { Arm::Light };
+    find_box_char(arms)
+        .or_else(|| find_box_char([light(arms[0]), light(arms[1]), light(arms[2]), light(arms[3])]))
+        .or_else(|| find_box_char([all_light(arms[0]), all_light(arms[1]), all_light(arms[2]), all_light(arms[3])]))
+        .unwrap_or("┼")
+}
+
+fn arm(stroke: &Stroke) -> Arm {
+    match *stroke {
+        Stroke::Solid => Arm::Light,
+        Stroke::Dashed => Arm::Dashed,
+        Stroke::Heavy => Arm::Heavy,
+        Stroke::HeavyDashed => Arm::HeavyDashed,
+    }
+}
+
+/// the arrow pointing to the direction of x, y, with y going down
+fn arrow(x: f32, y: f32) -> &'static str {
+    let arrows = ["→", "↘", "↓", "↙", "←", "↖", "↑", "↗"];
+    let octant = (y.atan2(x) / std::f32::consts::FRAC_PI_4).round() as isize;
+    arrows[octant.rem_euclid(8) as usize]
+}
+
+struct Canvas {
+    rows: Vec<Vec<Cell>>,
+    /// the size of a quarter of the cell
+    quarter_width: f32,
+    quarter_height: f32,
+}
+
+impl Canvas {
+    /// the cell at column x and row y, extending the canvas as needed
+    fn cell(&mut self, x: isize, y: isize) -> Option<&mut Cell> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        let (x, y) = (x as usize, y as usize);
+        while self.rows.len() <= y {
+            self.rows.push(vec![]);
+        }
+        let row = &mut self.rows[y];
+        while row.len() <= x {
+            row.push(Cell::new());
+        }
+        Some(&mut row[x])
+    }
+
+    /// the point in quarters of a cell
+    fn quarters(&self, p: &Point) -> (f32, f32) {
+        (p.x / self.quarter_width, p.y / self.quarter_height)
+    }
+
+    fn line(&mut self, s: &Point, e: &Point, stroke: &Stroke, feature: &Feature) {
+        let (sx, sy) = self.quarters(s);
+        let (ex, ey) = self.quarters(e);
+        let (dx, dy) = (ex - sx, ey - sy);
+        let steps = (dx.abs().max(dy.abs()) * 2.0).ceil().max(1.0) as usize;
+        // the line drawn on the top or bottom edge of the cells is an underline
+        let is_low = dy == 0.0 && sy > 0.0 && sy.round() == sy && sy as isize % 4 == 0;
+        for i in 0..steps {
+            let t = (i as f32 + 0.5) / steps as f32;
+            let (x, y) = (sx + dx * t, sy + dy * t);
+            let (column, row) = ((x / 4.0).floor() as isize, (y / 4.0).floor() as isize);
+            if is_low {
+                if let Some(cell) = self.cell(column, row - 1) {
+                    cell.low = true;
+                }
+                continue;
+            }
+            let Some(cell) = self.cell(column, row) else { continue };
+            if dx.abs() > dy.abs() * 2.0 {
+                cell.add_arm(if x - column as f32 * 4.0 < 2.0 { LEFT } else { RIGHT }, arm(stroke));
+            } else if dy.abs() > dx.abs() * 2.0 {
+                cell.add_arm(if y - row as f32 * 4.0 < 2.0 { TOP } else { BOTTOM }, arm(stroke));
+            } else if (dx < 0.0) == (dy < 0.0) {
+                cell.backslash = true;
+            } else {
+                cell.slash = true;
+            }
+        }
+        // the marks are placed in the cell just inside the end of the line
+        let nudge = 0.5 / dx.abs().max(dy.abs()).max(0.5);
+        match *feature {
+            Feature::Arrow => {
+                let (x, y) = (ex - dx * nudge, ey - dy * nudge);
+                if let Some(cell) = self.cell((x / 4.0).floor() as isize, (y / 4.0).floor() as isize) {
+                    cell.mark = Some(arrow(e.x - s.x, e.y - s.y));
+                }
+            }
+            Feature::Circle => {
+                let (x, y) = (sx + dx * nudge, sy + dy * nudge);
+                if let Some(cell) = self.cell((x / 4.0).floor() as isize, (y / 4.0).floor() as isize) {
+                    cell.mark = Some("○");
+                }
+            }
+            Feature::Nothing => (),
+        }
+    }
+
+    /// the small arcs are rounded corners, the arcs with the ends
+    /// straight across the cell are drawn as parenthesis
+    fn arc(&mut self, s: &Point, e: &Point, radius: f32, sweep: bool) {
+        let (dx, dy) = (e.x - s.x, e.y - s.y);
+        let chord = (dx * dx + dy * dy).sqrt();
+        if chord == 0.0 {
+            return;
+        }
+        // the arc bulges out to the left of the chord when drawn counter clockwise
+        let (nx, ny) = if sweep { (dy / chord, -dx / chord) } else { (-dy / chord, dx / chord) };
+        let half = chord / 2.0;
+        let sagitta = radius - (radius * radius - half * half).max(0.0).sqrt();
+        let middle = Point::new((s.x + e.x) / 2.0 + nx * sagitta, (s.y + e.y) / 2.0 + ny * sagitta);
+        let (mx, my) = self.quarters(&middle);
+        let (column, row) = ((mx / 4.0).floor() as isize, (my / 4.0).floor() as isize);
+        let center = (column as f32 * 4.0 + 2.0, row as f32 * 4.0 + 2.0);
+        let side = |(x, y): (f32, f32)| {
+            let (x, y) = (x - center.0, y - center.1);
+            if x.abs() > y.abs() {
+                if x > 0.0 { RIGHT } else { LEFT }
+            } else if y > 0.0 {
+                BOTTOM
+            } else {
+                TOP
+            }
+        };
+        let (start, end) = (side(self.quarters(s)), side(self.quarters(e)));
+        let Some(cell) = self.cell(column, row) else { return };
+        if start == end {
+            return;
+        }
+        // the arc joining a slanted line to a straight one is drawn straight
+        let is_vertical = start % 2 == 0;
+        let is_parallel = if is_vertical { dx == 0.0 } else { dy == 0.0 };
+        if (start + 2) % 4 == end && !is_parallel {
+            cell.add_arm(start, Arm::Light);
+            cell.add_arm(end, Arm::Light);
+        } else if (start + 2) % 4 == end {
+            cell.mark = Some(match (is_vertical, nx < 0.0, ny < 0.0) {
+                (true, true, _) => "(",
+                (true, false, _) => ")",
+                (false, _, true) => "⌒",
+                (false, _, false) => "‿",
+            });
+        } else {
+            cell.add_arm(start, Arm::Round);
+            cell.add_arm(end, Arm::Round);
+        }
+    }
+
+    fn mark(&mut self, p: &Point, mark: &'static str) {
+        let (x, y) = self.quarters(p);
+        if let Some(cell) = self.cell((x / 4.0).floor() as isize, (y / 4.0).floor() as isize) {
+            cell.mark = Some(mark);
+        }
+    }
+
+    fn text(&mut self, x: isize, y: isize, text: &str) {
+        let mut column = x;
+        for grapheme in text.graphemes(true) {
+            let width = grapheme_width(grapheme) as isize;
+            if let Some(cell) = self.cell(column, y) {
+                cell.text = Some(grapheme.into());
+            }
+            // the columns taken by the right side of a wide character
+            for covered in column + 1..column + width {
+                if let Some(cell) = self.cell(covered, y) {
+                    cell.text = Some("".into());
+                }
+            }
+            column += width.max(1);
+        }
+    }
+
+    fn draw(&mut self, element: &Element) {
+        match *element {
+            Element::Line(ref s, ref e, ref stroke, ref feature) => self.line(s, e, stroke, feature),
+            Element::Arc(ref s, ref e, radius, sweep) => self.arc(s, e, radius, sweep),
+            Element::Circle(ref c, _, ref class) => self.mark(c, if class == "solid" { "●" } else { "○" }),
+            Element::Polygon(ref points, ref class) => {
+                if !points.is_empty() {
+                    let n = points.len() as f32;
+                    let x = points.iter().map(|p| p.x).sum::<f32>() / n;
+                    let y = points.iter().map(|p| p.y).sum::<f32>() / n;
+                    self.mark(&Point::new(x, y), if class == "solid" { "■" } else { "□" });
+                }
+            }
+            Element::Text(ref loc, ref text) => self.text(loc.x, loc.y, text),
+            // the merged paths are only kept as svg path data
+            Element::Path(_, _, _, _) => (),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut lines: Vec<String> = self.rows.iter()
+            .map(|row| {
+                let line: String = row.iter().filter_map(|cell| cell.to_char()).collect();
+                line.trim_end().to_string()
+            })
+            .collect();
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        let mut text = lines.join("\n");
+        text.push('\n');
+        text
+    }
+}
+
+/// draw the elements in the cells of the size in the settings
+pub fn draw(elements: &[Element], settings: &Settings) -> String {
+    let mut canvas = Canvas {
+        rows: vec![],
+        quarter_width: settings.text_width / 4.0,
+        quarter_height: settings.text_height / 4.0,
+    };
+    for element in elements {
+        canvas.draw(element);
+    }
+    canvas.to_text()
+}
+
+#[cfg(test)]
+use super::Grid;
+
+#[test]
+fn test_unicode_box(){
+    let bob = ".--+--.\n|  |  |\n'--+--'\n";
+    assert_eq!(Grid::from_str(bob).get_unicode(&Settings::default()),
+               "╭──┬──╮\n│  │  │\n╰──┴──╯\n");
+    let bob = "+-----+\n| box |\n+-----+\n";
+    assert_eq!(Grid::from_str(bob).get_unicode(&Settings::default()),
+               "┌─────┐\n│ box │\n└─────┘\n");
+    // the heavy dashes stay heavy
+    assert_eq!(Grid::from_str("┅┅\n┇\n┄┄\n").get_unicode(&Settings::default()), "┅┅\n┇\n┄┄\n");
+}
+
+#[test]
+fn test_unicode_arrows(){
+    let bob = "--->  <---\n  ^\n  |\n  v\n";
+    assert_eq!(Grid::from_str(bob).get_unicode(&Settings::default()),
+               "───→  ←───\n  ↑\n  │\n  ↓\n");
+}
@@ -4,21 +4,14 @@ extern crate test;
 extern crate svgbob;
 use test::Bencher;
 
-#[cfg(test)]
-mod tests {
-    #![feature(test)]
-    extern crate test;
-    use super::*;
-
-    #[bench]
-    fn how_fast(b: &mut Bencher) {
-        b.iter(|| svgbob::to_svg(get_arg()));
-    }
+#[bench]
+fn how_fast(b: &mut Bencher) {
+    b.iter(|| svgbob::to_svg(get_arg()));
 }
 
 fn get_arg() -> &'static str{
 
-let arg = r#"
+r#"
 +------+   +-----+   +-----+   +-----+
 |      |   |     |   |     |   |     |
 | Foo  +-->| Bar +---+ Baz |<--+ Moo |
@@ -115,7 +108,5 @@ let arg = r#"
          More::Stuff  '            \
                      /              '--- Last::One
        More::Stuff  V 
-"#;
-
-arg
+"#
 }
@@ -0,0 +1,153 @@
+//! Properties of the svg of any text, with diagrams made of
+//! the drawing characters, text, wide and combining characters,
+//! box drawing characters, control characters and random unicode.
+//!
+//! The same rendering is fuzzed in `fuzz/`.
+extern crate proptest;
+extern crate roxmltree;
+extern crate svgbob;
+
+use proptest::prelude::*;
+use svgbob::Element;
+use svgbob::Grid;
+use svgbob::Settings;
+
+/// the characters diagrams are drawn with, and the ones that trip them
+const PALETTE: &[&str] = &[
+    "-", "|", "+", ".", "'", "`", ",", "/", "\\", "_", "*", "o", "O", "<", ">", "^", "v", "V",
+    "(", ")", "[", "]", "=", ":", "~", "#", " ", " ", " ", "a", "b", "x", "\"",
+    "─", "│", "┌", "┐", "└", "┘", "┼", "╭", "╯", "━", "║", "╱", "╳",
+    "中", "💖", "é", "e\u{301}", "\u{301}", "\u{200b}", "\u{feff}", "\u{fffe}", "\u{ffff}",
+    "\t", "\r", "\u{0}", "\u{1b}", "\u{7f}", "\u{85}", "&", "<!--", "]]>",
+];
+
+fn diagram() -> impl Strategy<Value = String> {
+    let cell = prop_oneof![
+        8 => proptest::sample::select(PALETTE).prop_map(String::from),
+        1 => any::<char>().prop_map(String::from),
+    ];
+    let line = proptest::collection::vec(cell, 0..24).prop_map(|cells| cells.concat());
+    proptest::collection::vec(line, 0..8).prop_map(|lines| lines.join("\n"))
+}
+
+/// the numbers in the path data, the coordinates of the end points
+/// of each command: 2 for the lines and 7 for the arcs
+fn path_points(d: &str) -> Vec<(f32, f32)> {
+    let mut points = vec![];
+    let mut tokens = d.split_whitespace();
+    while let Some(command) = tokens.next() {
+        let count = match command {
+            "M" | "L" => 2,
+            "A" => 7,
+            _ => panic!("unexpected path command `{}` in `{}`", command, d),
+        };
+        let numbers: Vec<f32> = (0..count)
+            .map(|_| tokens.next().unwrap_or_else(|| panic!("missing number in `{}`", d)).parse().unwrap())
+            .collect();
+        points.push((numbers[count - 2], numbers[count - 1]));
+    }
+    points
+}
+
+/// every coordinate drawn, outside of the markers
+fn coordinates(svg: &roxmltree::Document) -> Vec<(f32, f32)> {
+    let number = |node: &roxmltree::Node, name: &str| -> Option<f32> {
+        node.attribute(name).map(|value| value.parse().unwrap_or(f32::NAN))
+    };
+    let mut coordinates = vec![];
+    for node in svg.descendants().filter(|node| node.is_element()) {
+        if node.ancestors().any(|ancestor| ancestor.has_tag_name("defs")) {
+            continue;
+        }
+        for &(x, y) in &[("x", "y"), ("x1", "y1"), ("x2", "y2"), ("cx", "cy")] {
+            if let (Some(x), Some(y)) = (number(&node, x), number(&node, y)) {
+                coordinates.push((x, y));
+            }
+        }
+        if let Some(points) = node.attribute("points") {
+            for point in points.split_whitespace() {
+                let mut xy = point.split(',').map(|n| n.parse().unwrap_or(f32::NAN));
+                coordinates.push((xy.next().unwrap_or(f32::NAN), xy.next().unwrap_or(f32::NAN)));
+            }
+        }
+        if let Some(d) = node.attribute("d") {
+            coordinates.extend(path_points(d));
+        }
+    }
+    coordinates
+}
+
+/// the total length of the lines and arcs
+fn stroked_length(elements: &[Element]) -> f32 {
+    elements.iter()
+        .map(|element| match *element {
+            Element::Line(ref s, ref e, _, _) => (e.x() - s.x()).hypot(e.y() - s.y()),
+            Element::Arc(ref s, ref e, radius, _) => {
+                let chord = (e.x() - s.x()).hypot(e.y() - s.y());
+                2.0 * radius * (chord / (2.0 * radius)).min(1.0).asin()
+            }
+            _ => 0.0,
+        })
+        .sum()
+}
+
+#[test]
+fn font_family_with_markup(){
+    let mut settings = Settings::default();
+    settings.set_font_family(r#"A&B<C, 'Times' "New""#);
+    let grid = Grid::from_str("a");
+    let mut written = vec![];
+    grid.write_svg(&settings, &mut written).unwrap();
+    for svg in &[grid.get_svg(&settings).to_string(), String::from_utf8(written).unwrap()] {
+        let document = roxmltree::Document::parse(svg).unwrap();
+        assert_eq!(document.root_element().attribute("font-family"), Some(r#"A&B<C, 'Times' "New""#));
+    }
+}
+
+proptest! {
+    #[test]
+    fn svg_is_well_formed(bob in diagram()) {
+        let svg = Grid::from_str(&bob).get_svg(&Settings::default()).to_string();
+        if let Err(e) = roxmltree::Document::parse(&svg) {
+            panic!("{}\n{}", e, svg);
+        }
+    }
+
+    #[test]
+    fn font_family_is_escaped(font_family in any::<String>()) {
+        let mut settings = Settings::default();
+        settings.set_font_family(&font_family);
+        let svg = Grid::from_str("a").get_svg(&settings).to_string();
+        if let Err(e) = roxmltree::Document::parse(&svg) {
+            panic!("{}\n{}", e, svg);
+        }
+    }
+
+    #[test]
+    fn coordinates_are_inside(bob in diagram(), tab_width in prop_oneof![1usize..9, any::<usize>()]) {
+        let svg = Grid::from_str_with_tab_width(&bob, tab_width).get_svg(&Settings::separate_lines()).to_string();
+        let document = roxmltree::Document::parse(&svg).unwrap();
+        let root = document.root_element();
+        let view_box: Vec<f32> = root.attribute("viewBox").unwrap()
+            .split_whitespace()
+            .map(|n| n.parse().unwrap())
+            .collect();
+        let (left, top, width, height) = (view_box[0], view_box[1], view_box[2], view_box[3]);
+        prop_assert_eq!(root.attribute("width").map(|w| w.parse::<f32>().unwrap()), Some(width));
+        prop_assert_eq!(root.attribute("height").map(|h| h.parse::<f32>().unwrap()), Some(height));
+        for (x, y) in coordinates(&document) {
+            prop_assert!(x.is_finite() && y.is_finite(), "{}, {} in\n{}", x, y, svg);
+            prop_assert!(x >= left && x <= left + width && y >= top && y <= top + height,
+                         "{}, {} outside of {:?} in\n{}", x, y, view_box, svg);
+        }
+    }
+
+    #[test]
+    fn optimizer_keeps_the_length(bob in diagram()) {
+        let grid = Grid::from_str(&bob);
+        let optimized = stroked_length(&grid.elements(&Settings::separate_lines()));
+        let unoptimized = stroked_length(&grid.elements(&Settings::no_optimization()));
+        prop_assert!((optimized - unoptimized).abs() <= 0.01 * unoptimized.max(1.0),
+                     "{} optimized, {} without optimization for\n{}", optimized, unoptimized, bob);
+    }
+}
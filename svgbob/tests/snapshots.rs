@@ -0,0 +1,212 @@
+//! Render every diagram of `svgbob_cli/examples` and `tests/corpus`
+//! and compare it with the svg in `tests/snapshots`.
+//!
+//! The svg are compared tag by tag, with the attributes sorted
+//! and the whitespace in between the tags left out.
+//! After an intended change, the snapshots are written again with
+//!
+//! ```console
+//! $ SVGBOB_BLESS=1 cargo test --test snapshots
+//! ```
+//!
+//! which also removes the snapshots of the diagrams that are gone.
+//! The examples are in the svgbob_cli crate, they are skipped
+//! when the crate is built on its own, as from its package.
+extern crate svgbob;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use svgbob::Grid;
+use svgbob::Settings;
+
+const BLESS: &str = "SVGBOB_BLESS";
+
+fn examples_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../svgbob_cli/examples")
+}
+
+fn corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus")
+}
+
+fn snapshots_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots")
+}
+
+/// the .bob files in the directory, sorted by name
+fn diagrams(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("can not read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "bob"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// the tags with their attributes sorted, and the text in between them,
+/// the text of the elements with `xml:space="preserve"` is kept as is
+fn normalize(svg: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut preserve = false;
+    let mut rest = svg;
+    while !rest.is_empty() {
+        let end = if rest.starts_with('<') {
+            rest.find('>').map_or(rest.len(), |i| i + 1)
+        } else {
+            rest.find('<').unwrap_or(rest.len())
+        };
+        let (item, next) = rest.split_at(end);
+        rest = next;
+        if item.starts_with('<') {
+            preserve = item.contains(r#"xml:space="preserve""#);
+            items.push(normalize_tag(item));
+        } else if preserve {
+            items.push(item.to_string());
+        } else {
+            let text = item.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !text.is_empty() {
+                items.push(text);
+            }
+        }
+    }
+    items
+}
+
+fn normalize_tag(tag: &str) -> String {
+    let closing = if tag.ends_with("/>") { "/>" } else { ">" };
+    let inner = tag[1..tag.len() - closing.len()].trim();
+    let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+    let (name, mut attributes) = inner.split_at(name_end);
+    let mut sorted = vec![];
+    loop {
+        attributes = attributes.trim_start();
+        let (key, value) = match attributes.find('=') {
+            Some(i) => (attributes[..i].trim(), attributes[i + 1..].trim_start()),
+            None => break,
+        };
+        let quote = value.chars().next().unwrap_or('"');
+        let value = &value[quote.len_utf8()..];
+        let value_end = value.find(quote).unwrap_or(value.len());
+        let normalized = value[..value_end].split_whitespace().collect::<Vec<_>>().join(" ");
+        sorted.push(format!(r#"{}="{}""#, key, normalized));
+        attributes = &value[(value_end + quote.len_utf8()).min(value.len())..];
+    }
+    sorted.sort();
+    let mut normalized = format!("<{}", name);
+    for attribute in sorted {
+        normalized.push(' ');
+        normalized.push_str(&attribute);
+    }
+    normalized.push_str(closing);
+    normalized
+}
+
+/// the part of the item around the byte at, the path data is too long to be read whole
+fn excerpt(item: &str, at: usize) -> String {
+    let start = (0..=at.saturating_sub(40)).rev().find(|&i| item.is_char_boundary(i)).unwrap_or(0);
+    let end = (at + 40..item.len()).find(|&i| item.is_char_boundary(i)).unwrap_or(item.len());
+    format!("{}{}{}",
+            if start > 0 { "..." } else { "" },
+            &item[start..end],
+            if end < item.len() { "..." } else { "" })
+}
+
+/// the first difference between the expected and the rendered svg
+fn difference(expected: &str, actual: &str) -> Option<String> {
+    let expected = normalize(expected);
+    let actual = normalize(actual);
+    let missing = String::new();
+    (0..expected.len().max(actual.len()))
+        .find(|&i| expected.get(i) != actual.get(i))
+        .map(|i| {
+            let expected = expected.get(i).unwrap_or(&missing);
+            let actual = actual.get(i).unwrap_or(&missing);
+            let at = expected.bytes().zip(actual.bytes()).take_while(|(a, b)| a == b).count();
+            format!("at item {}\n  expected: {}\n  rendered: {}", i, excerpt(expected, at), excerpt(actual, at))
+        })
+}
+
+fn snapshot_name(diagram: &Path) -> String {
+    format!("{}.svg", diagram.file_stem().unwrap().to_string_lossy())
+}
+
+/// remove the snapshots without a diagram in the examples or in the corpus,
+/// only when both are there to tell
+fn remove_stale_snapshots() {
+    if !examples_dir().is_dir() {
+        return;
+    }
+    let names: Vec<String> = diagrams(&examples_dir()).iter()
+        .chain(diagrams(&corpus_dir()).iter())
+        .map(|diagram| snapshot_name(diagram))
+        .collect();
+    for entry in fs::read_dir(snapshots_dir()).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if name.ends_with(".svg") && !names.contains(&name) {
+            fs::remove_file(&path).unwrap();
+        }
+    }
+}
+
+/// check the diagrams in the directory against their snapshots,
+/// or write the snapshots when blessing
+fn check(dir: &Path) {
+    let snapshots = snapshots_dir();
+    let bless = env::var_os(BLESS).is_some();
+    let mut failures = vec![];
+    for file in diagrams(dir) {
+        let bob = fs::read_to_string(&file).unwrap();
+        let svg = Grid::from_str(&bob).get_svg(&Settings::default()).to_string();
+        let snapshot = snapshots.join(snapshot_name(&file));
+        if bless {
+            fs::create_dir_all(&snapshots).unwrap();
+            fs::write(&snapshot, svg + "\n").unwrap();
+            continue;
+        }
+        match fs::read_to_string(&snapshot) {
+            Ok(expected) => {
+                if let Some(difference) = difference(&expected, &svg) {
+                    failures.push(format!("{}: {}", file.display(), difference));
+                }
+            }
+            Err(_) => failures.push(format!("{}: there is no snapshot {}", file.display(), snapshot.display())),
+        }
+    }
+    if bless {
+        remove_stale_snapshots();
+    }
+    assert!(failures.is_empty(),
+            "{}\n\nrun with {}=1 to accept the rendered svg as the new snapshots",
+            failures.join("\n"),
+            BLESS);
+}
+
+#[test]
+fn examples(){
+    let examples = examples_dir();
+    if !examples.is_dir() {
+        eprintln!("skipped, there is no {}", examples.display());
+        return;
+    }
+    check(&examples);
+}
+
+#[test]
+fn corpus(){
+    check(&corpus_dir());
+}
+
+#[test]
+fn normalized_attributes(){
+    assert_eq!(normalize(r#"<svg  width="8" height="16">
+  <path fill="none" d=" M 0 0  L 8 8"/>
+  <text x="0"><tspan xml:space="preserve"> a  b </tspan></text>
+</svg>"#),
+               normalize(r#"<svg height="16" width="8"><path d="M 0 0 L 8 8" fill="none"/><text x="0"><tspan xml:space="preserve"> a  b </tspan></text></svg>"#));
+    assert!(difference(r#"<text><tspan xml:space="preserve">a b</tspan></text>"#,
+                       r#"<text><tspan xml:space="preserve">a  b</tspan></text>"#).is_some());
+}
@@ -1,3 +1,9 @@
+extern crate svgbob;
+
+use svgbob::Element;
+use svgbob::Grid;
+use svgbob::Loc;
+use svgbob::Settings;
 
 #[test]
 fn char_len(){
@@ -6,3 +12,52 @@ fn char_len(){
     println!("s: {} {} {}", s, s.len(), s.chars().count());
     println!("ch: {} {}", ch, ch.len_utf8());
 }
+
+/// the text in the cell at column x of the first line,
+/// None when no character starts at that column
+fn cell(grid: &Grid, x: isize) -> Option<String> {
+    let explanation = grid.explain(&Loc::new(x, 0), &Settings::default())?;
+    match explanation.elements.first() {
+        Some(Element::Text(_, text)) => Some(text.clone()),
+        _ => Some(String::new()),
+    }
+}
+
+#[test]
+fn combining_accents(){
+    // e followed by a combining acute accent
+    let grid = Grid::from_str("e\u{301}b");
+    assert_eq!(cell(&grid, 0), Some("e\u{301}".to_string()));
+    assert_eq!(cell(&grid, 1), Some("b".to_string()));
+}
+
+#[test]
+fn flags(){
+    let grid = Grid::from_str("🇯🇵b");
+    assert_eq!(cell(&grid, 0), Some("🇯🇵".to_string()));
+    assert_eq!(cell(&grid, 1), None);
+    assert_eq!(cell(&grid, 2), Some("b".to_string()));
+}
+
+#[test]
+fn skin_tone_modifiers(){
+    let grid = Grid::from_str("👍🏽b");
+    assert_eq!(cell(&grid, 0), Some("👍🏽".to_string()));
+    assert_eq!(cell(&grid, 2), Some("b".to_string()));
+}
+
+#[test]
+fn zwj_emoji(){
+    // man, woman, girl joined with zero width joiners
+    let family = "👨\u{200d}👩\u{200d}👧";
+    let grid = Grid::from_str(&format!("{}b", family));
+    assert_eq!(cell(&grid, 0), Some(family.to_string()));
+    assert_eq!(cell(&grid, 2), Some("b".to_string()));
+}
+
+#[test]
+fn zero_width_space(){
+    // the zero width space doesn't take a column
+    let grid = Grid::from_str("a\u{200b}b");
+    assert_eq!(cell(&grid, 1), Some("b".to_string()));
+}
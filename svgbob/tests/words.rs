@@ -0,0 +1,48 @@
+extern crate svgbob;
+
+use svgbob::Grid;
+use svgbob::Settings;
+
+fn render(bob: &str) -> String {
+    Grid::from_str(bob).get_svg(&Settings::default()).to_string()
+}
+
+fn has_text(svg: &str, text: &str) -> bool {
+    svg.contains(&format!(r#"<tspan xml:space="preserve">{}</tspan>"#, text))
+}
+
+#[test]
+fn words_next_to_lines(){
+    let svg = render(include_str!("../../svgbob_cli/examples/schematic.bob"));
+    // the `o` was drawn as an open junction, the `_` and `.` as lines
+    assert!(has_text(&svg, "1 Mohm"));
+    assert!(has_text(&svg, "Diode"));
+    assert!(has_text(&svg, "ground"));
+}
+
+#[test]
+fn words_with_underscore(){
+    let svg = render(include_str!("../../svgbob_cli/examples/long.bob"));
+    assert!(has_text(&svg, "Base::Class::Derived_A"));
+    let svg = render(include_str!("../../svgbob_cli/examples/memes.bob"));
+    assert!(has_text(&svg, "twitch_memes"));
+}
+
+#[test]
+fn words_with_period(){
+    let svg = render(include_str!("../../svgbob_cli/examples/nice_to_have.bob"));
+    assert!(has_text(&svg, "Add or Edit Signatures..."));
+    let svg = render("e.g. file_name\n|");
+    assert!(has_text(&svg, "e.g. file_name"));
+}
+
+#[test]
+fn drawing_letters_alone(){
+    // `o` and `v` that are not part of a word are still drawn
+    let svg = render("--o--\n  |\n  v");
+    assert!(svg.contains("<circle"));
+    assert!(svg.contains("marker-end"));
+    // the `_` before a label is still a line
+    let svg = render("___0,047R");
+    assert!(has_text(&svg, "0,047R"));
+}
@@ -14,7 +14,7 @@ fn main() {
     let html_file = "ascii_art.html";
     let bob_str = include_str!("ascii_art.bob");
     let svg = svgbob::to_svg(bob_str);
-    if let Ok(_) = svg::save(svg_file, &svg){
+    if svg::save(svg_file, &svg).is_ok(){
         println!("Saved to {}",svg_file);
     }else{
         println!("Error saving to file {}", svg_file);
@@ -27,9 +27,9 @@ fn main() {
     let context = Context::wraps(&m);
 
 
-    let mut source_template = File::open(&"web/index.hbs").unwrap();
+    let mut source_template = File::open("web/index.hbs").unwrap();
     let mut output_file = File::create(html_file).unwrap();
-    if let Ok(_) = handlebars.template_renderw2(&mut source_template, &context, &mut output_file) {
+    if handlebars.template_renderw2(&mut source_template, &context, &mut output_file).is_ok() {
         println!("Rendered to {}", html_file);
     } else {
        println!("Error"); 
@@ -15,7 +15,7 @@ fn main() {
 
 fn get_arg() -> &'static str{
 
-let arg = r#"
+r#"
 
 
 +------+   +-----+   +-----+   +-----+
@@ -125,7 +125,5 @@ let arg = r#"
        More::Stuff  V 
 
 
-"#;
-
-arg
+"#
 }
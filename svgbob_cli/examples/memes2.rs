@@ -24,9 +24,9 @@ fn main() {
     let context = Context::wraps(&m);
 
 
-    let mut source_template = File::open(&"web/index.hbs").unwrap();
+    let mut source_template = File::open("web/index.hbs").unwrap();
     let mut output_file = File::create(html_file).unwrap();
-    if let Ok(_) = handlebars.template_renderw2(&mut source_template, &context, &mut output_file) {
+    if handlebars.template_renderw2(&mut source_template, &context, &mut output_file).is_ok() {
         println!("Rendered to {}", html_file);
     } else {
        println!("Error"); 
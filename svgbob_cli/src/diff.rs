@@ -0,0 +1,143 @@
+//! A unified diff of 2 texts, line by line
+//!
+use std::fmt::Write;
+
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+// the edits turning old into new, comparing the lines pairwise:
+// formatting keeps the lines one to one, and only drops the trailing ones.
+// A run of changed lines is removed before it is added back, as in the other diffs
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let mut edits = Vec::with_capacity(old.len().max(new.len()));
+    let mut changed = vec![];
+    for (i, (o, n)) in old.iter().zip(new).enumerate() {
+        if o == n {
+            push_changed(&mut edits, &mut changed);
+            edits.push(Edit::Same(i, i));
+        } else {
+            changed.push(i);
+        }
+    }
+    push_changed(&mut edits, &mut changed);
+    let paired = old.len().min(new.len());
+    edits.extend((paired..old.len()).map(Edit::Removed));
+    edits.extend((paired..new.len()).map(Edit::Added));
+    edits
+}
+
+fn push_changed(edits: &mut Vec<Edit>, changed: &mut Vec<usize>) {
+    edits.extend(changed.iter().map(|&i| Edit::Removed(i)));
+    edits.extend(changed.drain(..).map(Edit::Added));
+}
+
+/// the unified diff from old to new with 3 lines of context, empty when they are the same
+pub fn unified_diff(old_name: &str, new_name: &str, old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let edits = edits(&old, &new);
+    let changed: Vec<usize> = edits.iter()
+        .enumerate()
+        .filter(|&(_, edit)| !matches!(*edit, Edit::Same(_, _)))
+        .map(|(i, _)| i)
+        .collect();
+    let mut diff = String::new();
+    if changed.is_empty() {
+        return diff;
+    }
+    writeln!(diff, "--- {}", old_name).unwrap();
+    writeln!(diff, "+++ {}", new_name).unwrap();
+
+    // the changes closer than twice the context are in the same hunk
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for &i in &changed {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    for (start, end) in hunks {
+        let hunk = &edits[start..end];
+        // the line where the hunk starts in old and in new
+        let (mut old_start, mut new_start) = (old.len(), new.len());
+        for edit in &edits[start..] {
+            match *edit {
+                Edit::Same(i, j) => {
+                    old_start = old_start.min(i);
+                    new_start = new_start.min(j);
+                    break;
+                }
+                Edit::Removed(i) => old_start = old_start.min(i),
+                Edit::Added(j) => new_start = new_start.min(j),
+            }
+        }
+        let old_count = hunk.iter().filter(|edit| !matches!(**edit, Edit::Added(_))).count();
+        let new_count = hunk.iter().filter(|edit| !matches!(**edit, Edit::Removed(_))).count();
+        // an empty range is numbered from the line before it
+        let old_line = if old_count == 0 { old_start } else { old_start + 1 };
+        let new_line = if new_count == 0 { new_start } else { new_start + 1 };
+        writeln!(diff, "@@ -{},{} +{},{} @@", old_line, old_count, new_line, new_count).unwrap();
+        for edit in hunk {
+            match *edit {
+                Edit::Same(i, _) => writeln!(diff, " {}", old[i]).unwrap(),
+                Edit::Removed(i) => writeln!(diff, "-{}", old[i]).unwrap(),
+                Edit::Added(j) => writeln!(diff, "+{}", new[j]).unwrap(),
+            }
+        }
+    }
+    diff
+}
+
+#[test]
+fn test_unified_diff(){
+    assert_eq!(unified_diff("a", "b", "x\ny\n", "x\ny\n"), "");
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+    let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\n11\n12\n13\n";
+    assert_eq!(unified_diff("a.bob", "a.bob (formatted)", old, new), "\
+--- a.bob
++++ a.bob (formatted)
+@@ -2,7 +2,7 @@
+ 2
+ 3
+ 4
+-5
++five
+ 6
+ 7
+ 8
+@@ -10,3 +10,4 @@
+ 10
+ 11
+ 12
++13
+");
+    assert_eq!(unified_diff("a", "b", "", "x\n"), "--- a\n+++ b\n@@ -0,0 +1,1 @@\n+x\n");
+    assert_eq!(unified_diff("a", "b", "x\ny\nz\n\n\n", "X\nY\nz\n"), "\
+--- a
++++ b
+@@ -1,5 +1,3 @@
+-x
+-y
++X
++Y
+ z
+-
+-
+");
+}
+
+#[test]
+fn test_long_diff(){
+    // the space taken is linear in the number of lines
+    let old = "|\n".repeat(200_000);
+    let new = old.replacen("|", "+", 1);
+    assert_eq!(unified_diff("a", "b", &old, &new).lines().count(), 2 + 1 + 2 + 3);
+}
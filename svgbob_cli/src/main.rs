@@ -3,17 +3,25 @@
 extern crate clap;
 
 extern crate svgbob;
-extern crate svg;
 
+mod diff;
+mod serve;
+
+use svgbob::CornerStyle;
 use svgbob::Grid;
 use svgbob::Settings;
+use svgbob::Diagnostic;
+use svgbob::Severity;
+use svgbob::Loc;
+use svgbob::MAX_TAB_WIDTH;
 
 use clap::ArgMatches;
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::error::Error;
-use std::io::Read;
+use std::io::{BufWriter, Read};
 use std::process::exit;
+use std::time::Duration;
 
 fn main() {
     use clap::{Arg, App, SubCommand};
@@ -27,6 +35,32 @@ fn main() {
             .long("output")
             .takes_value(true)
             .help("where to write svg output [default: STDOUT]"))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&["svg", "unicode"])
+            .help("svg, or the diagram drawn again with the unicode box drawing characters [default: svg]"))
+        .arg(Arg::with_name("debug")
+            .long("debug")
+            .help("overlay the cell grid, the line and column numbers and the anchor points"))
+        .arg(Arg::with_name("no-optimize")
+            .long("no-optimize")
+            .help("keep the fragments of every cell apart, the svg is then written as it is drawn"))
+        .arg(Arg::with_name("no-crop")
+            .long("no-crop")
+            .help("size the svg to the whole text instead of the drawing"))
+        .arg(Arg::with_name("margin")
+            .long("margin")
+            .takes_value(true)
+            .help("the space around the drawing, in pixels [default: 8]"))
+        .arg(Arg::with_name("responsive")
+            .long("responsive")
+            .help("set the width to 100% and leave out the height, to scale to the page"))
+        .arg(Arg::with_name("tab-width")
+            .long("tab-width")
+            .takes_value(true)
+            .global(true)
+            .help("the columns between the tab stops, from 1 to 64 [default: 8]"))
         .subcommand(SubCommand::with_name("build")
             .about("Batch convert files to svg.")
             .version("0.0.1")
@@ -40,16 +74,117 @@ fn main() {
                 .long("outdir")
                 .takes_value(true)
                 .help("set dir of svg files")))
+        .subcommand(SubCommand::with_name("lint")
+            .about("Report the places in the diagram that are likely not drawn as intended.")
+            .arg(Arg::with_name("input").index(1).help("svgbob text file to check [default: STDIN]"))
+            .arg(Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .help("output the diagnostics as text or json")))
+        .subcommand(SubCommand::with_name("explain")
+            .about("Show how a character is drawn: the rules it matches and the resulting elements.")
+            .arg(Arg::with_name("input").index(1).help("svgbob text file [default: STDIN]"))
+            .arg(Arg::with_name("at")
+                .long("at")
+                .takes_value(true)
+                .required(true)
+                .value_name("LINE:COLUMN")
+                .help("the character to explain, line and column start at 1")))
+        .subcommand(SubCommand::with_name("fmt")
+            .about("Rewrite the diagram with consistent corners, aligned vertical lines and no trailing whitespace.")
+            .arg(Arg::with_name("input").index(1).help("svgbob text file to format in place [default: STDIN to STDOUT]"))
+            .arg(Arg::with_name("corners")
+                .long("corners")
+                .takes_value(true)
+                .possible_values(&["plus", "rounded", "unicode"])
+                .default_value("plus")
+                .help("write the corners as `+`, as rounded `.` and `'` or as unicode box drawing"))
+            .arg(Arg::with_name("check")
+                .long("check")
+                .help("show the changes as a diff instead of writing them, exit with 1 when there are changes")))
+        .subcommand(SubCommand::with_name("serve")
+            .about("Serve POST /render over http, the query parameters are the settings.")
+            .arg(Arg::with_name("listen")
+                .long("listen")
+                .takes_value(true)
+                .default_value("127.0.0.1:8000")
+                .help("the address to listen on"))
+            .arg(Arg::with_name("max-size")
+                .long("max-size")
+                .takes_value(true)
+                .help("the largest diagram accepted, in bytes [default: 65536]"))
+            .arg(Arg::with_name("timeout")
+                .long("timeout")
+                .takes_value(true)
+                .help("the seconds allowed to read and to render a request [default: 5]"))
+            .arg(Arg::with_name("cache-size")
+                .long("cache-size")
+                .takes_value(true)
+                .help("the number of rendered diagrams kept [default: 256]"))
+            .arg(Arg::with_name("max-connections")
+                .long("max-connections")
+                .takes_value(true)
+                .help("the connections handled at once, the others are answered with 503 [default: 64]"))
+            .arg(Arg::with_name("max-renders")
+                .long("max-renders")
+                .takes_value(true)
+                .help("the diagrams rendered at once, the others are answered with 503 [default: the number of cpus]")))
         .get_matches();
 
+    let default_tab_width = tab_width(&args, 8);
+
     if let Some(sub_build) = args.subcommand_matches("build") {
-        match build(sub_build) {
+        match build(sub_build, tab_width(sub_build, default_tab_width)) {
             Ok(_) => {}
             Err(e) => println!("{}", e),
         };
         exit(1);
     }
 
+    if let Some(sub_lint) = args.subcommand_matches("lint") {
+        match lint(sub_lint, tab_width(sub_lint, default_tab_width)) {
+            Ok(true) => exit(0),
+            Ok(false) => exit(1),
+            Err(e) => {
+                use std::io::Write;
+                writeln!(&mut std::io::stderr(), "{}", e).unwrap();
+                exit(2);
+            }
+        }
+    }
+
+    if let Some(sub_explain) = args.subcommand_matches("explain") {
+        if let Err(e) = explain(sub_explain, tab_width(sub_explain, default_tab_width)) {
+            use std::io::Write;
+            writeln!(&mut std::io::stderr(), "{}", e).unwrap();
+            exit(2);
+        }
+        exit(0);
+    }
+
+    if let Some(sub_fmt) = args.subcommand_matches("fmt") {
+        match fmt(sub_fmt, tab_width(sub_fmt, default_tab_width)) {
+            Ok(true) => exit(0),
+            Ok(false) => exit(1),
+            Err(e) => {
+                use std::io::Write;
+                writeln!(&mut std::io::stderr(), "{}", e).unwrap();
+                exit(2);
+            }
+        }
+    }
+
+    if let Some(sub_serve) = args.subcommand_matches("serve") {
+        if let Err(e) = serve(sub_serve) {
+            use std::io::Write;
+            writeln!(&mut std::io::stderr(), "{}", e).unwrap();
+            exit(2);
+        }
+        exit(0);
+    }
+
     let mut bob = String::new();
     if let Some(file) = args.value_of("input") {
         match File::open(file) {
@@ -73,11 +208,44 @@ fn main() {
         io::stdin().read_to_string(&mut bob).unwrap();
     }
 
-    let g = Grid::from_str(&*bob);
-    let svg = g.get_svg(&Settings::compact());
+    let g = Grid::from_str_with_tab_width(&bob, default_tab_width);
+    let mut settings = Settings::compact();
+    settings.set_debug(args.is_present("debug"));
+    if args.is_present("no-optimize") {
+        settings.set_optimize(false);
+        settings.set_compact_path(false);
+    }
+    settings.set_crop(!args.is_present("no-crop"));
+    settings.set_responsive(args.is_present("responsive"));
+    if let Some(margin) = args.value_of("margin") {
+        match margin.parse::<f32>() {
+            Ok(margin) if margin.is_finite() && margin >= 0.0 => settings.set_margin(margin),
+            _ => {
+                use std::io::Write;
+                writeln!(&mut std::io::stderr(), "--margin should be a number of pixels, not {}", margin).unwrap();
+                exit(2);
+            }
+        }
+    }
+
+    if args.value_of("format") == Some("unicode") {
+        let text = g.get_unicode(&settings);
+        match args.value_of("output") {
+            Some(file) => {
+                if let Err(e) = fs::write(file, text) {
+                    use std::io::Write;
+                    writeln!(&mut std::io::stderr(), "Failed to write to output file {}: {}", file, e).unwrap();
+                    exit(2);
+                }
+            }
+            None => print!("{}", text),
+        }
+        return;
+    }
 
     if let Some(file) = args.value_of("output") {
-        if let Err(e) = svg::save(file, &svg) {
+        let written = File::create(file).and_then(|f| g.write_svg(&settings, BufWriter::new(f)));
+        if let Err(e) = written {
             use std::io::Write;
             use std::process::exit;
 
@@ -89,21 +257,45 @@ fn main() {
             exit(2);
         }
     } else {
-        println!("{}", svg);
+        use std::io::Write;
+        let stdout = std::io::stdout();
+        let mut stdout = BufWriter::new(stdout.lock());
+        g.write_svg(&settings, &mut stdout)
+            .and_then(|_| writeln!(stdout))
+            .unwrap();
+    }
+}
+
+// the --tab-width option, given before or after the subcommand
+fn tab_width(args: &ArgMatches, default: usize) -> usize {
+    match args.value_of("tab-width") {
+        None => default,
+        Some(width) => match width.parse::<usize>() {
+            Ok(width) if (1..=MAX_TAB_WIDTH).contains(&width) => width,
+            _ => {
+                use std::io::Write;
+                writeln!(&mut std::io::stderr(),
+                         "--tab-width should be a number of columns from 1 to {}, not {}",
+                         MAX_TAB_WIDTH,
+                         width)
+                    .unwrap();
+                exit(2);
+            }
+        },
     }
 }
 
 // Batch convert files to svg
 // use svgbob build -i inputdir/*.bob -o outdir/
-fn build(args: &ArgMatches) -> Result<(), Box<Error>> {
+fn build(args: &ArgMatches, tab_width: usize) -> Result<(), Box<dyn Error>> {
 
     let files_pattern = args.value_of("input").unwrap_or("*.bob");
     let outdir = args.value_of("outdir").unwrap_or("");
     let input_path = Path::new(files_pattern);
-    let ext = input_path.extension().unwrap_or(&"bob".as_ref()).to_str().unwrap();
+    let ext = input_path.extension().unwrap_or("bob".as_ref()).to_str().unwrap();
 
     let input_dir = if input_path.is_dir() {
-        input_path.clone()
+        input_path
     } else {
         input_path.parent().unwrap()
     };
@@ -114,27 +306,27 @@ fn build(args: &ArgMatches) -> Result<(), Box<Error>> {
     }
 
     let mut out_path = PathBuf::new();
-    if outdir == "" {
+    if outdir.is_empty() {
         out_path = input_dir.to_path_buf();
     } else {
         out_path.push(outdir)
     }
 
     if !out_path.is_dir() {
-        try!(fs::create_dir_all(out_path.clone()));
+        fs::create_dir_all(out_path.clone())?;
     }
 
     let paths = fs::read_dir(input_dir).unwrap();
     for path in paths {
         let tmp_path = path.unwrap().path();
         if tmp_path.is_file() {
-            let tmp_ext = tmp_path.extension().unwrap_or(&"".as_ref()).to_str().unwrap();
+            let tmp_ext = tmp_path.extension().unwrap_or("".as_ref()).to_str().unwrap();
             if tmp_ext == ext {
                 let name = tmp_path.file_stem().unwrap().to_str().unwrap();
                 let mut tmp = out_path.clone();
                 tmp.push(format!("{}.svg", name));
                 println!("{} => {}", tmp_path.display(), tmp.display());
-                match convert_file(tmp_path.clone(), tmp) {
+                match convert_file(tmp_path.clone(), tmp, tab_width) {
                     Ok(_) => {}
                     Err(e) => {
                         println!("{}", e);
@@ -147,12 +339,171 @@ fn build(args: &ArgMatches) -> Result<(), Box<Error>> {
     Ok(())
 }
 
-fn convert_file(input: PathBuf, output: PathBuf) -> Result<(), Box<Error>> {
+fn convert_file(input: PathBuf, output: PathBuf, tab_width: usize) -> Result<(), Box<dyn Error>> {
     let mut bob = String::new();
-    let mut f = try!(File::open(&input));
+    let mut f = File::open(&input)?;
     f.read_to_string(&mut bob).unwrap();
-    let g = Grid::from_str(&*bob);
-    let svg = g.get_svg(&Settings::compact());
-    try!(svg::save(&output, &svg));
+    let g = Grid::from_str_with_tab_width(&bob, tab_width);
+    g.write_svg(&Settings::compact(), BufWriter::new(File::create(&output)?))?;
     Ok(())
 }
+
+// Check the diagram for mistakes
+// use svgbob lint file.bob [--format json]
+// returns false when there are errors
+fn lint(args: &ArgMatches, tab_width: usize) -> Result<bool, Box<dyn Error>> {
+    let mut bob = String::new();
+    let name = match args.value_of("input") {
+        Some(file) => {
+            File::open(file)?.read_to_string(&mut bob)?;
+            file
+        }
+        None => {
+            std::io::stdin().read_to_string(&mut bob)?;
+            "<stdin>"
+        }
+    };
+    let diagnostics = Grid::from_str_with_tab_width(&bob, tab_width).diagnose();
+    if args.value_of("format") == Some("json") {
+        println!("{}", diagnostics_to_json(name, &diagnostics));
+    } else {
+        for diagnostic in &diagnostics {
+            println!("{}:{}", name, diagnostic);
+        }
+    }
+    Ok(!diagnostics.iter().any(|d| d.severity == Severity::Error))
+}
+
+// Show the rules that decide how a character is drawn
+// use svgbob explain file.bob --at 12:7
+fn explain(args: &ArgMatches, tab_width: usize) -> Result<(), Box<dyn Error>> {
+    let mut bob = String::new();
+    match args.value_of("input") {
+        Some(file) => File::open(file)?.read_to_string(&mut bob)?,
+        None => std::io::stdin().read_to_string(&mut bob)?,
+    };
+    let at = args.value_of("at").unwrap_or("");
+    let (line, column) = parse_position(at)
+        .ok_or_else(|| format!("invalid position `{}`, expecting LINE:COLUMN", at))?;
+    let loc = Loc::new(column as isize - 1, line as isize - 1);
+    match Grid::from_str_with_tab_width(&bob, tab_width).explain(&loc, &Settings::compact()) {
+        Some(explanation) => print!("{}", explanation),
+        None => return Err(Box::from(format!("there is no character at {}", at))),
+    }
+    Ok(())
+}
+
+// Rewrite the diagram consistently
+// use svgbob fmt file.bob [--corners rounded] [--check]
+// returns false when --check finds changes
+fn fmt(args: &ArgMatches, tab_width: usize) -> Result<bool, Box<dyn Error>> {
+    let style = match args.value_of("corners") {
+        Some("rounded") => CornerStyle::Rounded,
+        Some("unicode") => CornerStyle::BoxDrawing,
+        _ => CornerStyle::Plus,
+    };
+    let mut bob = String::new();
+    let name = match args.value_of("input") {
+        Some(file) => {
+            File::open(file)?.read_to_string(&mut bob)?;
+            file
+        }
+        None => {
+            std::io::stdin().read_to_string(&mut bob)?;
+            "<stdin>"
+        }
+    };
+    let formatted = svgbob::format_source(&bob, tab_width, style);
+    if args.is_present("check") {
+        let diff = diff::unified_diff(name, &format!("{} (formatted)", name), &bob, &formatted);
+        if diff.is_empty() && formatted != bob {
+            println!("{}: only the line endings change", name);
+        }
+        print!("{}", diff);
+        return Ok(formatted == bob);
+    }
+    match args.value_of("input") {
+        Some(file) => {
+            if formatted != bob {
+                fs::write(file, formatted)?;
+            }
+        }
+        None => print!("{}", formatted),
+    }
+    Ok(true)
+}
+
+// Render the diagrams posted to /render
+// use svgbob serve --listen 127.0.0.1:8000
+fn serve(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let mut limits = serve::Limits::default();
+    if let Some(max_size) = args.value_of("max-size") {
+        limits.max_body = max_size.parse()
+            .map_err(|_| format!("invalid --max-size `{}`", max_size))?;
+    }
+    if let Some(timeout) = args.value_of("timeout") {
+        let seconds: f64 = timeout.parse().ok()
+            .filter(|s: &f64| s.is_finite() && *s > 0.0)
+            .ok_or_else(|| format!("invalid --timeout `{}`", timeout))?;
+        limits.timeout = Duration::from_millis((seconds * 1000.0).ceil() as u64);
+    }
+    if let Some(cache_size) = args.value_of("cache-size") {
+        limits.cache_size = cache_size.parse()
+            .map_err(|_| format!("invalid --cache-size `{}`", cache_size))?;
+    }
+    if let Some(max_connections) = args.value_of("max-connections") {
+        limits.max_connections = max_connections.parse()
+            .map_err(|_| format!("invalid --max-connections `{}`", max_connections))?;
+    }
+    if let Some(max_renders) = args.value_of("max-renders") {
+        limits.max_renders = max_renders.parse()
+            .map_err(|_| format!("invalid --max-renders `{}`", max_renders))?;
+    }
+    let listen = args.value_of("listen").unwrap_or("127.0.0.1:8000");
+    let server = serve::Server::bind(listen, limits)?;
+    println!("listening on http://{}/render", server.local_addr()?);
+    server.run()?;
+    Ok(())
+}
+
+fn parse_position(at: &str) -> Option<(usize, usize)> {
+    let mut parts = at.splitn(2, ':');
+    let line = parts.next()?.trim().parse().ok()?;
+    let column = parts.next()?.trim().parse().ok()?;
+    if line > 0 && column > 0 {
+        Some((line, column))
+    } else {
+        None
+    }
+}
+
+fn diagnostics_to_json(file: &str, diagnostics: &[Diagnostic]) -> String {
+    let items: Vec<String> = diagnostics.iter()
+        .map(|d| {
+            format!(r#"{{"file":{},"line":{},"column":{},"severity":"{}","message":{}}}"#,
+                    json_string(file),
+                    d.line,
+                    d.column,
+                    d.severity,
+                    json_string(&d.message))
+        })
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for ch in s.chars() {
+        match ch {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
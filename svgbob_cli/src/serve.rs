@@ -0,0 +1,726 @@
+//! `svgbob serve`: render the diagrams posted over http
+//!
+//! ```console
+//! $ svgbob serve --listen 127.0.0.1:8000
+//! $ curl --data-binary @diagram.bob 'http://127.0.0.1:8000/render?theme=dark&text_height=20'
+//! ```
+//!
+//! The query parameters are the fields of the settings:
+//! `format`, `theme`, `text_width`, `text_height`, `font_size`, `font_family`,
+//! `exact_text_position`, `optimize`, `compact_path`, `debug`, `crop`, `margin`,
+//! `responsive` and `tab_width` (1 to 64).
+//! The `font_family` is a list of font names as in css,
+//! without `&`, `<`, `>` or control characters.
+//! With `format=unicode` the diagram is drawn again as text
+//! with the unicode box drawing characters.
+//! The rendered diagram is cached by the hash of the body and the parameters.
+//!
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use svgbob::Grid;
+use svgbob::Settings;
+use svgbob::MAX_TAB_WIDTH;
+
+/// the limits put on every request
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// the largest body accepted, in bytes
+    pub max_body: usize,
+    /// how long reading the whole request, rendering it
+    /// and writing the response may take each
+    pub timeout: Duration,
+    /// the number of rendered svg kept in the cache
+    pub cache_size: usize,
+    /// the connections handled at once, the others are answered with 503
+    pub max_connections: usize,
+    /// the diagrams rendered at once, counting the renders still running
+    /// after their request timed out, the others are answered with 503
+    pub max_renders: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_body: 64 * 1024,
+            timeout: Duration::from_secs(5),
+            cache_size: 256,
+            max_connections: 64,
+            max_renders: thread::available_parallelism().map_or(4, |n| n.get()),
+        }
+    }
+}
+
+// the longest request line and header accepted
+const MAX_HEADER_LINE: usize = 8 * 1024;
+
+const DARK_THEME: &str = r#"
+    svg {
+      background-color: black;
+    }
+    line, path, circle, polygon {
+      stroke: white;
+    }
+    circle, circle.open, polygon {
+      fill: black;
+    }
+    circle.solid, polygon.solid, text {
+      fill: white;
+    }
+    "#;
+
+/// the threads at work out of a maximum,
+/// a slot is taken before starting one and given back when it is dropped
+struct Slots {
+    used: Mutex<usize>,
+    max: usize,
+}
+
+struct Slot(Arc<Slots>);
+
+impl Slots {
+    fn new(max: usize) -> Arc<Slots> {
+        Arc::new(Slots { used: Mutex::new(0), max })
+    }
+
+    fn take(self: &Arc<Self>) -> Option<Slot> {
+        let mut used = self.used.lock().unwrap();
+        if *used < self.max {
+            *used += 1;
+            Some(Slot(self.clone()))
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        *self.0.used.lock().unwrap() -= 1;
+    }
+}
+
+pub struct Server {
+    listener: TcpListener,
+    limits: Limits,
+    cache: Arc<Mutex<Cache>>,
+    connections: Arc<Slots>,
+    renders: Arc<Slots>,
+}
+
+impl Server {
+    pub fn bind(addr: &str, limits: Limits) -> io::Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            cache: Arc::new(Mutex::new(Cache::new(limits.cache_size))),
+            connections: Slots::new(limits.max_connections),
+            renders: Slots::new(limits.max_renders),
+            limits,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// handle the connections, each in its own thread, until the listener fails,
+    /// the connections over `max_connections` are turned away
+    pub fn run(&self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let mut stream = stream?;
+            let slot = match self.connections.take() {
+                Some(slot) => slot,
+                None => {
+                    // the response is small enough to not block, the request is not read
+                    let _ = stream.set_write_timeout(Some(Duration::from_secs(1)))
+                        .and_then(|_| Response::error(503, "too many connections, try again later").write_to(&mut stream));
+                    continue;
+                }
+            };
+            let limits = self.limits.clone();
+            let cache = self.cache.clone();
+            let renders = self.renders.clone();
+            thread::spawn(move || {
+                let _slot = slot;
+                // the client went away, there is no one to report to
+                let _ = handle(stream, &limits, &cache, &renders);
+            });
+        }
+        Ok(())
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl Response {
+    fn error(status: u16, message: &str) -> Response {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            headers: vec![],
+            body: format!("{}\n", message),
+        }
+    }
+
+    fn write_to<W: Write>(&self, stream: &mut W) -> io::Result<()> {
+        write!(stream, "HTTP/1.1 {} {}\r\n", self.status, reason(self.status))?;
+        write!(stream, "Content-Type: {}\r\n", self.content_type)?;
+        write!(stream, "Content-Length: {}\r\n", self.body.len())?;
+        for &(name, ref value) in &self.headers {
+            write!(stream, "{}: {}\r\n", name, value)?;
+        }
+        write!(stream, "Connection: close\r\n\r\n{}", self.body)?;
+        stream.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+/// the stream with one deadline for all of its reads and writes,
+/// so a client sending or reading a byte at a time can not hold on to the connection
+struct Deadline {
+    stream: TcpStream,
+    at: Instant,
+}
+
+impl Deadline {
+    fn new(stream: TcpStream, timeout: Duration) -> Deadline {
+        Deadline { stream, at: Instant::now() + timeout }
+    }
+
+    fn remaining(&self) -> io::Result<Duration> {
+        let now = Instant::now();
+        if now < self.at {
+            Ok(self.at - now)
+        } else {
+            Err(io::Error::new(io::ErrorKind::TimedOut, "the deadline passed"))
+        }
+    }
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.remaining()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for Deadline {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.remaining()?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+fn handle(stream: TcpStream, limits: &Limits, cache: &Mutex<Cache>, renders: &Arc<Slots>) -> io::Result<()> {
+    let request = Deadline::new(stream.try_clone()?, limits.timeout);
+    let response = match respond(request, limits, cache, renders) {
+        Ok(response) => response,
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+            Response::error(408, "the request was not received in time")
+        }
+        Err(e) => return Err(e),
+    };
+    // the response and the rest of the request share the next deadline
+    let mut stream = Deadline::new(stream, limits.timeout);
+    response.write_to(&mut stream)?;
+    // drain what is left of a rejected request before closing,
+    // otherwise the connection is reset before the client reads the response
+    stream.stream.shutdown(Shutdown::Write)?;
+    io::copy(&mut stream.take(limits.max_body as u64 + MAX_HEADER_LINE as u64), &mut io::sink())?;
+    Ok(())
+}
+
+fn respond(request: Deadline, limits: &Limits, cache: &Mutex<Cache>, renders: &Arc<Slots>) -> io::Result<Response> {
+    let mut reader = BufReader::new(request);
+    let request_line = match read_line(&mut reader)? {
+        Some(line) => line,
+        None => return Ok(Response::error(431, "the request line is too long")),
+    };
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let target = parts.next().unwrap_or("").to_string();
+
+    let mut content_length = None;
+    let mut expect_continue = false;
+    loop {
+        let line = match read_line(&mut reader)? {
+            Some(line) => line,
+            None => return Ok(Response::error(431, "a header is too long")),
+        };
+        if line.is_empty() {
+            break;
+        }
+        let mut header = line.splitn(2, ':');
+        let name = header.next().unwrap_or("").trim().to_lowercase();
+        let value = header.next().unwrap_or("").trim();
+        match name.as_str() {
+            "content-length" => match value.parse::<usize>() {
+                Ok(length) => content_length = Some(length),
+                Err(_) => return Ok(Response::error(400, "invalid Content-Length")),
+            },
+            "expect" => expect_continue = value.eq_ignore_ascii_case("100-continue"),
+            _ => (),
+        }
+    }
+
+    let (path, query) = match target.find('?') {
+        Some(i) => (&target[..i], &target[i + 1..]),
+        None => (target.as_str(), ""),
+    };
+    if path != "/render" {
+        return Ok(Response::error(404, "only /render is served"));
+    }
+    if method != "POST" {
+        let mut response = Response::error(405, "POST the diagram to /render");
+        response.headers.push(("Allow", "POST".into()));
+        return Ok(response);
+    }
+    let content_length = match content_length {
+        Some(length) => length,
+        None => return Ok(Response::error(411, "the Content-Length is required")),
+    };
+    if content_length > limits.max_body {
+        return Ok(Response::error(413, &format!("the diagram is larger than {} bytes", limits.max_body)));
+    }
+    let params = match Params::parse(query) {
+        Ok(params) => params,
+        Err(e) => return Ok(Response::error(400, &e)),
+    };
+    if expect_continue {
+        reader.get_mut().write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    let input = match String::from_utf8(body) {
+        Ok(input) => input,
+        Err(_) => return Ok(Response::error(400, "the diagram is not valid utf-8")),
+    };
+
+    let content_type = match params.get("format") {
+        Some("unicode") => "text/plain; charset=utf-8",
+        _ => "image/svg+xml",
+    };
+    let key = cache_key(&input, &params);
+    let cached = cache.lock().unwrap().get(key, &input, &params);
+    let (svg, hit) = match cached {
+        Some(svg) => (svg, true),
+        None => {
+            let slot = match renders.take() {
+                Some(slot) => slot,
+                None => return Ok(Response::error(503, "the server is busy rendering, try again later")),
+            };
+            let svg = match render_with_timeout(&input, &params, limits.timeout, slot) {
+                Some(svg) => Arc::new(svg),
+                None => return Ok(Response::error(503, "the diagram took too long to render")),
+            };
+            cache.lock().unwrap().insert(key, input, params, svg.clone());
+            (svg, false)
+        }
+    };
+    Ok(Response {
+        status: 200,
+        content_type,
+        headers: vec![("ETag", format!("\"{:016x}\"", key)),
+                      ("X-Cache", if hit { "hit" } else { "miss" }.into())],
+        body: svg.to_string(),
+    })
+}
+
+// read a line without the line ending, None when it is too long
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = vec![];
+    reader.take(MAX_HEADER_LINE as u64 + 1).read_until(b'\n', &mut line)?;
+    if line.len() > MAX_HEADER_LINE {
+        return Ok(None);
+    }
+    if line.last() != Some(&b'\n') {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the request ended early"));
+    }
+    Ok(Some(String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// the settings from the query parameters
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+struct Params(Vec<(String, String)>);
+
+impl Params {
+    fn parse(query: &str) -> Result<Params, String> {
+        let mut params = vec![];
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let mut kv = pair.splitn(2, '=');
+            let name = percent_decode(kv.next().unwrap_or(""))?;
+            let value = percent_decode(kv.next().unwrap_or(""))?;
+            params.push((name, value));
+        }
+        // the same settings in any order share the cache entry
+        params.sort();
+        let params = Params(params);
+        params.to_settings()?;
+        Ok(params)
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().rev().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn to_settings(&self) -> Result<(Settings, usize), String> {
+        for (name, _) in &self.0 {
+            match name.as_str() {
+                "format" | "theme" | "text_width" | "text_height" | "font_size" | "font_family"
+                | "exact_text_position" | "optimize" | "compact_path" | "debug" | "tab_width"
+                | "crop" | "margin" | "responsive" => (),
+                _ => return Err(format!("unknown parameter `{}`", name)),
+            }
+        }
+        let number = |name: &str| -> Result<Option<f32>, String> {
+            match self.get(name) {
+                None => Ok(None),
+                Some(value) => value.parse::<f32>().ok()
+                    .filter(|n| n.is_finite() && *n > 0.0)
+                    .map(Some)
+                    .ok_or_else(|| format!("`{}` should be a positive number", name)),
+            }
+        };
+        let boolean = |name: &str| -> Result<Option<bool>, String> {
+            match self.get(name) {
+                None => Ok(None),
+                Some("true") | Some("1") | Some("") => Ok(Some(true)),
+                Some("false") | Some("0") => Ok(Some(false)),
+                Some(_) => Err(format!("`{}` should be true or false", name)),
+            }
+        };
+
+        match self.get("format") {
+            None | Some("svg") | Some("unicode") => (),
+            Some(format) => return Err(format!("unknown format `{}`, expecting svg or unicode", format)),
+        }
+        let mut settings = Settings::with_size(number("text_width")?.unwrap_or(8.0),
+                                               number("text_height")?.unwrap_or(16.0));
+        match self.get("theme") {
+            None | Some("light") => (),
+            Some("dark") => settings.add_style(DARK_THEME),
+            Some(theme) => return Err(format!("unknown theme `{}`, expecting light or dark", theme)),
+        }
+        if let Some(font_size) = number("font_size")? {
+            settings.set_font_size(font_size);
+        }
+        if let Some(font_family) = self.get("font_family") {
+            // font names don't need markup, the quotes are kept for the css syntax
+            if font_family.contains(|c: char| c == '&' || c == '<' || c == '>' || c.is_control()) {
+                return Err("`font_family` should not contain `&`, `<`, `>` or control characters".into());
+            }
+            settings.set_font_family(font_family);
+        }
+        if let Some(exact_text_position) = boolean("exact_text_position")? {
+            settings.set_exact_text_position(exact_text_position);
+        }
+        if let Some(optimize) = boolean("optimize")? {
+            settings.set_optimize(optimize);
+        }
+        if let Some(compact_path) = boolean("compact_path")? {
+            settings.set_compact_path(compact_path);
+        }
+        if let Some(debug) = boolean("debug")? {
+            settings.set_debug(debug);
+        }
+        if let Some(crop) = boolean("crop")? {
+            settings.set_crop(crop);
+        }
+        if let Some(margin) = self.get("margin") {
+            let margin = margin.parse::<f32>().ok()
+                .filter(|n| n.is_finite() && *n >= 0.0)
+                .ok_or("`margin` should be a number, 0 or more")?;
+            settings.set_margin(margin);
+        }
+        if let Some(responsive) = boolean("responsive")? {
+            settings.set_responsive(responsive);
+        }
+        let tab_width = match self.get("tab_width") {
+            None => 8,
+            Some(value) => value.parse::<usize>().ok()
+                .filter(|n| (1..=MAX_TAB_WIDTH).contains(n))
+                .ok_or_else(|| format!("`tab_width` should be a whole number from 1 to {}", MAX_TAB_WIDTH))?,
+        };
+        Ok((settings, tab_width))
+    }
+}
+
+fn percent_decode(s: &str) -> Result<String, String> {
+    let bytes = s.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = s.get(i + 1..i + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| format!("invalid escape in `{}`", s))?;
+                decoded.push(hex);
+                i += 2;
+            }
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded).map_err(|_| format!("`{}` is not valid utf-8", s))
+}
+
+fn render(input: &str, params: &Params) -> String {
+    // the parameters are validated when they are parsed
+    let (settings, tab_width) = params.to_settings().unwrap_or_default();
+    let grid = Grid::from_str_with_tab_width(input, tab_width);
+    match params.get("format") {
+        Some("unicode") => grid.get_unicode(&settings),
+        _ => grid.get_svg(&settings).to_string(),
+    }
+}
+
+// render in a separate thread and give up waiting after the timeout,
+// the thread is left to finish on its own since it can not be interrupted,
+// it keeps the render slot until then
+fn render_with_timeout(input: &str, params: &Params, timeout: Duration, slot: Slot) -> Option<String> {
+    let (tx, rx) = mpsc::channel();
+    let input = input.to_string();
+    let params = params.clone();
+    thread::spawn(move || {
+        let rendered = render(&input, &params);
+        // given back before the result, so the slot is free when it is received
+        drop(slot);
+        let _ = tx.send(rendered);
+    });
+    rx.recv_timeout(timeout).ok()
+}
+
+fn cache_key(input: &str, params: &Params) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    input.hash(&mut hasher);
+    params.hash(&mut hasher);
+    hasher.finish()
+}
+
+struct Entry {
+    input: String,
+    params: Params,
+    svg: Arc<String>,
+}
+
+/// the most recently rendered svg, the oldest is evicted first
+struct Cache {
+    capacity: usize,
+    entries: HashMap<u64, Entry>,
+    order: VecDeque<u64>,
+}
+
+impl Cache {
+    fn new(capacity: usize) -> Cache {
+        Cache {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    // the input and params are compared too, in case of a hash collision
+    fn get(&self, key: u64, input: &str, params: &Params) -> Option<Arc<String>> {
+        self.entries.get(&key)
+            .filter(|entry| entry.input == input && entry.params == *params)
+            .map(|entry| entry.svg.clone())
+    }
+
+    fn insert(&mut self, key: u64, input: String, params: Params, svg: Arc<String>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(key, Entry { input, params, svg }).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn start(limits: Limits) -> SocketAddr {
+    let server = Server::bind("127.0.0.1:0", limits).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    addr
+}
+
+// the status, the headers and the body of the response
+#[cfg(test)]
+fn request(addr: SocketAddr, head: &str, body: &str) -> (u16, String, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "{}\r\nContent-Length: {}\r\n\r\n{}", head, body.len(), body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let mut parts = response.splitn(2, "\r\n\r\n");
+    let head = parts.next().unwrap().to_string();
+    let body = parts.next().unwrap_or("").to_string();
+    let status = head[9..12].parse().unwrap();
+    (status, head, body)
+}
+
+#[test]
+fn test_render(){
+    let addr = start(Limits::default());
+    let bob = "+--+\n|  |\n+--+";
+    let (status, head, body) = request(addr, "POST /render HTTP/1.1", bob);
+    assert_eq!(status, 200);
+    assert!(head.contains("Content-Type: image/svg+xml"));
+    assert!(head.contains("X-Cache: miss"));
+    assert_eq!(body, Grid::from_str(bob).get_svg(&Settings::default()).to_string());
+
+    let (status, head, cached) = request(addr, "POST /render HTTP/1.1", bob);
+    assert_eq!(status, 200);
+    assert!(head.contains("X-Cache: hit"));
+    assert_eq!(cached, body);
+}
+
+#[test]
+fn test_render_params(){
+    let addr = start(Limits::default());
+    let (status, head, body) =
+        request(addr, "POST /render?theme=dark&text_height=32&font_family=DejaVu+Sans%20Mono HTTP/1.1", "hello");
+    assert_eq!(status, 200, "{}", body);
+    assert!(head.contains("X-Cache: miss"));
+    assert!(body.contains(r#"font-size="28""#));
+    assert!(body.contains(r#"font-family="DejaVu Sans Mono""#));
+    assert!(body.contains("background-color: black;"));
+
+    // the same parameters in another order
+    let (_, head, _) =
+        request(addr, "POST /render?font_family=DejaVu+Sans+Mono&text_height=32&theme=dark HTTP/1.1", "hello");
+    assert!(head.contains("X-Cache: hit"));
+
+    assert_eq!(request(addr, "POST /render?colour=red HTTP/1.1", "").0, 400);
+    assert_eq!(request(addr, "POST /render?theme=neon HTTP/1.1", "").0, 400);
+    assert_eq!(request(addr, "POST /render?format=png HTTP/1.1", "").0, 400);
+
+    let (status, head, body) = request(addr, "POST /render?format=unicode HTTP/1.1", "+--+\n|  |\n+--+");
+    assert_eq!(status, 200);
+    assert!(head.contains("Content-Type: text/plain; charset=utf-8"));
+    assert_eq!(body, "┌──┐\n│  │\n└──┘\n");
+    assert_eq!(request(addr, "POST /render?text_width=-1 HTTP/1.1", "").0, 400);
+
+    let (status, _, body) = request(addr, "POST /render?margin=0&responsive HTTP/1.1", "+--+");
+    assert_eq!(status, 200);
+    assert!(body.contains(r#"viewBox="0 0 32 16" width="100%""#), "{}", body);
+    assert_eq!(request(addr, "POST /render?margin=-1 HTTP/1.1", "").0, 400);
+    assert_eq!(request(addr, "POST /render?tab_width=1e11 HTTP/1.1", "a\tb").0, 400);
+    assert_eq!(request(addr, "POST /render?tab_width=2.5 HTTP/1.1", "a\tb").0, 400);
+    assert_eq!(request(addr, "POST /render?tab_width=0 HTTP/1.1", "a\tb").0, 400);
+
+    let (status, _, body) = request(addr, "POST /render?font_family=A%26B HTTP/1.1", "a");
+    assert_eq!(status, 400);
+    assert!(body.contains("`font_family`"), "{}", body);
+    assert_eq!(request(addr, "POST /render?font_family=%3Cb%3E HTTP/1.1", "a").0, 400);
+    assert_eq!(request(addr, "POST /render?font_family=a%0Ab HTTP/1.1", "a").0, 400);
+    let (status, _, body) = request(addr, "POST /render?font_family=%27Times+New+Roman%27%2Cserif HTTP/1.1", "a");
+    assert_eq!(status, 200);
+    assert!(body.contains(r#"font-family="&apos;Times New Roman&apos;,serif""#), "{}", body);
+}
+
+#[test]
+fn test_limits(){
+    let addr = start(Limits { max_body: 10, ..Limits::default() });
+    let (status, head, _) = request(addr, "GET /render HTTP/1.1", "");
+    assert_eq!(status, 405);
+    assert!(head.contains("Allow: POST"));
+    assert_eq!(request(addr, "POST /index.html HTTP/1.1", "").0, 404);
+    assert_eq!(request(addr, "POST /render HTTP/1.1", "+---------+").0, 413);
+    assert_eq!(request(addr, "POST /render HTTP/1.1", "+--------+").0, 200);
+    assert_eq!(request(addr, &format!("POST /render?{} HTTP/1.1", "a".repeat(MAX_HEADER_LINE)), "").0, 431);
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "POST /render HTTP/1.1\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 411 "));
+}
+
+#[test]
+fn test_slow_request(){
+    let addr = start(Limits { timeout: Duration::from_millis(300), ..Limits::default() });
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut trickle = stream.try_clone().unwrap();
+    // a byte of a header every 50ms, each read is quick but the request never ends
+    thread::spawn(move || {
+        let _ = trickle.write_all(b"POST /render HTTP/1.1\r\nX-Slow: ");
+        for _ in 0..60 {
+            if trickle.write_all(b"a").is_err() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    });
+    let start = Instant::now();
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    assert!(response.starts_with("HTTP/1.1 408 "), "{}", response);
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn test_busy(){
+    let addr = start(Limits { max_renders: 0, ..Limits::default() });
+    assert_eq!(request(addr, "POST /render HTTP/1.1", "+--+").0, 503);
+
+    let addr = start(Limits { max_connections: 1, ..Limits::default() });
+    // the idle connection takes the only slot
+    let idle = TcpStream::connect(addr).unwrap();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 503 "), "{}", response);
+    drop(idle);
+}
+
+#[test]
+fn test_render_timeout(){
+    let bob = "+--+ ".repeat(200) + "\n";
+    let bob = bob.repeat(200);
+    let renders = Slots::new(1);
+    let slot = renders.take().unwrap();
+    assert_eq!(render_with_timeout(&bob, &Params::default(), Duration::from_nanos(1), slot), None);
+    // the render that timed out is still running
+    assert!(renders.take().is_none());
+    let renders = Slots::new(1);
+    assert_eq!(render_with_timeout("+--+", &Params::default(), Duration::from_secs(5), renders.take().unwrap()),
+               Some(render("+--+", &Params::default())));
+    assert!(renders.take().is_some());
+}
@@ -0,0 +1,26 @@
+// render the ascii diagram read from stdin to svg on stdout
+//
+//     cargo build --release
+//     cc examples/render.c -Iinclude -Ltarget/release -lsvgbob_ffi -o render
+//     LD_LIBRARY_PATH=target/release ./render < ../svgbob_cli/examples/long.bob
+#include <stdio.h>
+#include "svgbob.h"
+
+int main(void) {
+    static char text[1 << 20];
+    size_t len = fread(text, 1, sizeof(text) - 1, stdin);
+    text[len] = '\0';
+
+    svgbob_settings settings = svgbob_default_settings();
+    settings.font_family = "monospace";
+
+    char* svg = NULL;
+    int code = svgbob_render(text, &settings, &svg);
+    if (code != SVGBOB_OK) {
+        fprintf(stderr, "svgbob_render failed with error %d\n", code);
+        return 1;
+    }
+    puts(svg);
+    svgbob_free(svg);
+    return 0;
+}
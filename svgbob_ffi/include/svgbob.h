@@ -0,0 +1,75 @@
+#ifndef SVGBOB_H
+#define SVGBOB_H
+
+/* generated with cbindgen by tests/header.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// the svg is written to `out`
+#define SVGBOB_OK 0
+
+// `text` or `out` is NULL
+#define SVGBOB_NULL_POINTER 1
+
+// `text` or `font_family` is not valid UTF-8
+#define SVGBOB_INVALID_UTF8 2
+
+// a size in the settings is not a positive number, or the tab width is over 64
+#define SVGBOB_INVALID_SETTINGS 3
+
+// the rendering failed, this is a bug in svgbob
+#define SVGBOB_INTERNAL_ERROR 4
+
+// The fields of `Settings`,
+// start from `svgbob_default_settings` and change the fields needed
+typedef struct svgbob_settings {
+  float text_width;
+  float text_height;
+  // 0 to derive it from text_height
+  float font_size;
+  // NULL for the default font
+  const char *font_family;
+  bool optimize;
+  bool compact_path;
+  bool exact_text_position;
+  bool debug;
+  // 0 for the tab stops every 8 columns, at most 64
+  uint32_t tab_width;
+  bool crop;
+  // the space around the cropped drawing
+  float margin;
+  bool responsive;
+} svgbob_settings;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// the settings used when `svgbob_render` is called with NULL settings
+struct svgbob_settings svgbob_default_settings(void);
+
+// Render the NUL terminated ascii diagram `text` into svg.
+// `s` may be NULL for the default settings.
+// On success `*out` points to the NUL terminated svg, release it with `svgbob_free`,
+// on failure `*out` is set to NULL and an error code is returned.
+//
+// # Safety
+// `text` and `s->font_family` are NULL or valid NUL terminated strings,
+// `s` is NULL or points to valid settings and `out` is NULL or writable.
+int svgbob_render(const char *text, const struct svgbob_settings *s, char **out);
+
+// release the svg returned by `svgbob_render`, NULL is ignored
+//
+// # Safety
+// `svg` is NULL or was returned by `svgbob_render` and is not freed yet
+void svgbob_free(char *svg);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SVGBOB_H */
@@ -0,0 +1,230 @@
+//! The C interface of svgbob, see `include/svgbob.h`
+//!
+//! ```c
+//! svgbob_settings settings = svgbob_default_settings();
+//! char* svg = NULL;
+//! if (svgbob_render("+--+\n|  |\n+--+", &settings, &svg) == SVGBOB_OK) {
+//!     puts(svg);
+//!     svgbob_free(svg);
+//! }
+//! ```
+//!
+//! No panic crosses the boundary, every failure is reported as an error code.
+//!
+#![deny(warnings)]
+#![allow(non_camel_case_types)]
+extern crate svgbob;
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic;
+use std::ptr;
+
+use svgbob::Grid;
+use svgbob::Settings;
+use svgbob::MAX_TAB_WIDTH;
+
+/// the svg is written to `out`
+pub const SVGBOB_OK: c_int = 0;
+/// `text` or `out` is NULL
+pub const SVGBOB_NULL_POINTER: c_int = 1;
+/// `text` or `font_family` is not valid UTF-8
+pub const SVGBOB_INVALID_UTF8: c_int = 2;
+/// a size in the settings is not a positive number, or the tab width is over 64
+pub const SVGBOB_INVALID_SETTINGS: c_int = 3;
+/// the rendering failed, this is a bug in svgbob
+pub const SVGBOB_INTERNAL_ERROR: c_int = 4;
+
+/// The fields of `Settings`,
+/// start from `svgbob_default_settings` and change the fields needed
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct svgbob_settings {
+    pub text_width: f32,
+    pub text_height: f32,
+    /// 0 to derive it from text_height
+    pub font_size: f32,
+    /// NULL for the default font
+    pub font_family: *const c_char,
+    pub optimize: bool,
+    pub compact_path: bool,
+    pub exact_text_position: bool,
+    pub debug: bool,
+    /// 0 for the tab stops every 8 columns, at most 64
+    pub tab_width: u32,
+    pub crop: bool,
+    /// the space around the cropped drawing
+    pub margin: f32,
+    pub responsive: bool,
+}
+
+/// the settings used when `svgbob_render` is called with NULL settings
+#[no_mangle]
+pub extern "C" fn svgbob_default_settings() -> svgbob_settings {
+    svgbob_settings {
+        text_width: 8.0,
+        text_height: 16.0,
+        font_size: 0.0,
+        font_family: ptr::null(),
+        optimize: true,
+        compact_path: true,
+        exact_text_position: false,
+        debug: false,
+        tab_width: 0,
+        crop: true,
+        margin: 8.0,
+        responsive: false,
+    }
+}
+
+/// Render the NUL terminated ascii diagram `text` into svg.
+/// `s` may be NULL for the default settings.
+/// On success `*out` points to the NUL terminated svg, release it with `svgbob_free`,
+/// on failure `*out` is set to NULL and an error code is returned.
+///
+/// # Safety
+/// `text` and `s->font_family` are NULL or valid NUL terminated strings,
+/// `s` is NULL or points to valid settings and `out` is NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn svgbob_render(text: *const c_char, s: *const svgbob_settings, out: *mut *mut c_char) -> c_int {
+    if out.is_null() {
+        return SVGBOB_NULL_POINTER;
+    }
+    *out = ptr::null_mut();
+    if text.is_null() {
+        return SVGBOB_NULL_POINTER;
+    }
+    let text = match CStr::from_ptr(text).to_str() {
+        Ok(text) => text,
+        Err(_) => return SVGBOB_INVALID_UTF8,
+    };
+    let s = if s.is_null() { svgbob_default_settings() } else { *s };
+    let font_family = if s.font_family.is_null() {
+        None
+    } else {
+        match CStr::from_ptr(s.font_family).to_str() {
+            Ok(font_family) => Some(font_family),
+            Err(_) => return SVGBOB_INVALID_UTF8,
+        }
+    };
+    let settings = match to_settings(&s, font_family) {
+        Some(settings) => settings,
+        None => return SVGBOB_INVALID_SETTINGS,
+    };
+    let tab_width = if s.tab_width == 0 { 8 } else { s.tab_width as usize };
+
+    // nothing is used after a panic, so it is fine to assert the unwind safety
+    let rendered = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let mut svg = vec![];
+        Grid::from_str_with_tab_width(text, tab_width).write_svg(&settings, &mut svg).ok()?;
+        CString::new(svg).ok()
+    }));
+    match rendered {
+        Ok(Some(svg)) => {
+            *out = svg.into_raw();
+            SVGBOB_OK
+        }
+        _ => SVGBOB_INTERNAL_ERROR,
+    }
+}
+
+/// release the svg returned by `svgbob_render`, NULL is ignored
+///
+/// # Safety
+/// `svg` is NULL or was returned by `svgbob_render` and is not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn svgbob_free(svg: *mut c_char) {
+    if !svg.is_null() {
+        drop(CString::from_raw(svg));
+    }
+}
+
+fn to_settings(s: &svgbob_settings, font_family: Option<&str>) -> Option<Settings> {
+    let positive = |n: f32| n.is_finite() && n > 0.0;
+    if !positive(s.text_width) || !positive(s.text_height) || !(s.font_size == 0.0 || positive(s.font_size))
+        || !(s.margin == 0.0 || positive(s.margin))
+        || s.tab_width as usize > MAX_TAB_WIDTH {
+        return None;
+    }
+    let mut settings = Settings::with_size(s.text_width, s.text_height);
+    if s.font_size > 0.0 {
+        settings.set_font_size(s.font_size);
+    }
+    if let Some(font_family) = font_family {
+        settings.set_font_family(font_family);
+    }
+    settings.set_optimize(s.optimize);
+    settings.set_compact_path(s.compact_path);
+    settings.set_exact_text_position(s.exact_text_position);
+    settings.set_debug(s.debug);
+    settings.set_crop(s.crop);
+    settings.set_margin(s.margin);
+    settings.set_responsive(s.responsive);
+    Some(settings)
+}
+
+#[cfg(test)]
+fn render(text: &[u8], s: Option<&svgbob_settings>) -> (c_int, Option<String>) {
+    let text = CString::new(text).unwrap();
+    let mut out = ptr::null_mut();
+    unsafe {
+        let s = s.map_or(ptr::null(), |s| s as *const svgbob_settings);
+        let code = svgbob_render(text.as_ptr(), s, &mut out);
+        if out.is_null() {
+            return (code, None);
+        }
+        let svg = CStr::from_ptr(out).to_str().unwrap().to_string();
+        svgbob_free(out);
+        (code, Some(svg))
+    }
+}
+
+#[test]
+fn test_render(){
+    let (code, svg) = render(b"+--+\n|  |\n+--+", None);
+    assert_eq!(code, SVGBOB_OK);
+    assert_eq!(svg.unwrap(), Grid::from_str("+--+\n|  |\n+--+").get_svg(&Settings::default()).to_string());
+}
+
+#[test]
+fn test_render_settings(){
+    let font_family = CString::new("monospace").unwrap();
+    let settings = svgbob_settings {
+        text_height: 32.0,
+        font_family: font_family.as_ptr(),
+        ..svgbob_default_settings()
+    };
+    let (code, svg) = render(b"hello", Some(&settings));
+    assert_eq!(code, SVGBOB_OK);
+    let svg = svg.unwrap();
+    assert!(svg.contains(r#"font-family="monospace""#));
+    assert!(svg.contains(r#"font-size="28""#));
+}
+
+#[test]
+fn test_render_errors(){
+    assert_eq!(render(b"\xff\xfe", None), (SVGBOB_INVALID_UTF8, None));
+    let settings = svgbob_settings {
+        text_width: -1.0,
+        ..svgbob_default_settings()
+    };
+    assert_eq!(render(b"+", Some(&settings)), (SVGBOB_INVALID_SETTINGS, None));
+    let settings = svgbob_settings {
+        margin: -1.0,
+        ..svgbob_default_settings()
+    };
+    assert_eq!(render(b"+", Some(&settings)), (SVGBOB_INVALID_SETTINGS, None));
+    let settings = svgbob_settings {
+        tab_width: u32::MAX,
+        ..svgbob_default_settings()
+    };
+    assert_eq!(render(b"a\tb", Some(&settings)), (SVGBOB_INVALID_SETTINGS, None));
+    unsafe {
+        let mut out = ptr::null_mut();
+        assert_eq!(svgbob_render(ptr::null(), ptr::null(), &mut out), SVGBOB_NULL_POINTER);
+        assert!(out.is_null());
+        let text = CString::new("+").unwrap();
+        assert_eq!(svgbob_render(text.as_ptr(), ptr::null(), ptr::null_mut()), SVGBOB_NULL_POINTER);
+        svgbob_free(ptr::null_mut());
+    }
+}
@@ -0,0 +1,36 @@
+//! The committed `include/svgbob.h` is the one cbindgen generates
+//! from the extern functions in `src/lib.rs`, regenerate it with
+//!
+//! ```console
+//! $ SVGBOB_BLESS=1 cargo test --test header
+//! ```
+extern crate cbindgen;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const BLESS: &str = "SVGBOB_BLESS";
+
+#[test]
+fn header_is_up_to_date(){
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+    let mut generated = vec![];
+    cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("unable to generate the C header")
+        .write(&mut generated);
+    let generated = String::from_utf8(generated).unwrap();
+    let path = crate_dir.join("include/svgbob.h");
+    if env::var_os(BLESS).is_some() {
+        fs::write(&path, &generated).unwrap();
+        return;
+    }
+    let committed = fs::read_to_string(&path).unwrap_or_default();
+    assert!(committed == generated,
+            "{} is out of date, regenerate it with {}=1 cargo test --test header",
+            path.display(), BLESS);
+}
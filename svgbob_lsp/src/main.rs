@@ -0,0 +1,33 @@
+//! A language server for the `.bob` documents
+//!
+//! - the diagnostics of `svgbob lint` are published as the document changes
+//! - hovering a character shows how it is drawn, as `svgbob explain` does
+//! - the `svgbob/preview` request returns the svg of the current content
+//!
+//! It talks over stdin and stdout, the `tab_width` initialization option
+//! sets the tab stops, from 1 to 64.
+//!
+#![deny(warnings)]
+extern crate lsp_server;
+extern crate lsp_types;
+#[macro_use]
+extern crate serde_json;
+extern crate svgbob;
+
+mod server;
+
+use std::error::Error;
+
+use lsp_server::Connection;
+use lsp_types::InitializeParams;
+
+fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
+    let (connection, io_threads) = Connection::stdio();
+    let params = connection.initialize(serde_json::to_value(server::capabilities())?)?;
+    let params: InitializeParams = serde_json::from_value(params)?;
+    server::Server::new(&params).run(&connection)?;
+    // the io threads are done once the connection is dropped
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
@@ -0,0 +1,357 @@
+//! The state of the open `.bob` documents and the handling of the messages
+//!
+use std::collections::HashMap;
+use std::error::Error;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+                              Notification as _, PublishDiagnostics};
+use lsp_types::request::{HoverRequest, Request as _};
+use lsp_types::{Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams,
+                DidCloseTextDocumentParams, DidOpenTextDocumentParams, Hover, HoverContents,
+                HoverParams, HoverProviderCapability, InitializeParams, MarkupContent, MarkupKind,
+                Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+                TextDocumentIdentifier, TextDocumentSyncCapability, TextDocumentSyncKind, Uri};
+use serde_json::Value;
+
+use svgbob::Grid;
+use svgbob::Loc;
+use svgbob::MAX_TAB_WIDTH;
+use svgbob::Settings;
+use svgbob::Severity;
+
+/// the custom request returning the svg of the current content of a document,
+/// the params are `{"textDocument": {"uri": ...}}`, the result is `{"svg": ...}`
+pub const PREVIEW: &str = "svgbob/preview";
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// the open `.bob` documents
+pub struct Server {
+    documents: HashMap<Uri, String>,
+    tab_width: usize,
+}
+
+impl Server {
+    /// the tab width is read from the `tab_width` of the initialization options,
+    /// one outside of 1 to MAX_TAB_WIDTH is ignored with a warning on stderr
+    pub fn new(params: &InitializeParams) -> Server {
+        let tab_width = match params.initialization_options.as_ref().and_then(|options| options.get("tab_width")) {
+            None => 8,
+            Some(value) => match value.as_u64().filter(|n| (1..=MAX_TAB_WIDTH as u64).contains(n)) {
+                Some(tab_width) => tab_width as usize,
+                None => {
+                    eprintln!("svgbob-lsp: ignoring the tab_width {}, it should be from 1 to {}", value, MAX_TAB_WIDTH);
+                    8
+                }
+            },
+        };
+        Server {
+            documents: HashMap::new(),
+            tab_width,
+        }
+    }
+
+    /// handle the messages until the client shuts the server down
+    pub fn run(&mut self, connection: &Connection) -> Result<(), Box<dyn Error + Sync + Send>> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    connection.sender.send(self.respond(request).into())?;
+                }
+                Message::Notification(notification) => {
+                    for published in self.notify(notification) {
+                        connection.sender.send(published.into())?;
+                    }
+                }
+                Message::Response(_) => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn respond(&self, request: Request) -> Response {
+        let result = match request.method.as_str() {
+            HoverRequest::METHOD => serde_json::from_value(request.params)
+                .map(|params| self.hover(&params).and_then(|hover| serde_json::to_value(hover).ok())),
+            PREVIEW => serde_json::from_value(request.params.get("textDocument").cloned().unwrap_or(Value::Null))
+                .map(|document| self.preview(&document)),
+            _ => {
+                return Response::new_err(request.id,
+                                         ErrorCode::MethodNotFound as i32,
+                                         format!("unhandled method {}", request.method))
+            }
+        };
+        match result {
+            Ok(Some(result)) => Response::new_ok(request.id, result),
+            Ok(None) => Response::new_ok(request.id, Value::Null),
+            Err(e) => invalid_params(request.id, e.to_string()),
+        }
+    }
+
+    // the diagnostics to publish after the change
+    fn notify(&mut self, notification: Notification) -> Vec<Notification> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                if let Ok(params) = serde_json::from_value::<DidOpenTextDocumentParams>(notification.params) {
+                    let document = params.text_document;
+                    if document.language_id == "bob" || document.uri.path().as_str().ends_with(".bob") {
+                        self.documents.insert(document.uri.clone(), document.text);
+                        return vec![self.diagnostics(&document.uri)];
+                    }
+                }
+            }
+            DidChangeTextDocument::METHOD => {
+                if let Ok(params) = serde_json::from_value::<DidChangeTextDocumentParams>(notification.params) {
+                    let uri = params.text_document.uri;
+                    // the server asks for the full text on every change
+                    if let (Some(text), Some(change)) = (self.documents.get_mut(&uri), params.content_changes.into_iter().last()) {
+                        *text = change.text;
+                        return vec![self.diagnostics(&uri)];
+                    }
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                if let Ok(params) = serde_json::from_value::<DidCloseTextDocumentParams>(notification.params) {
+                    let uri = params.text_document.uri;
+                    if self.documents.remove(&uri).is_some() {
+                        return vec![publish(uri, vec![])];
+                    }
+                }
+            }
+            _ => (),
+        }
+        vec![]
+    }
+
+    fn diagnostics(&self, uri: &Uri) -> Notification {
+        let text = &self.documents[uri];
+        let lines: Vec<&str> = lines(text);
+        let diagnostics = Grid::from_str_with_tab_width(text, self.tab_width).diagnose().into_iter()
+            .map(|d| {
+                let line = lines.get(d.line - 1).cloned().unwrap_or("");
+                let (start, end) = cell_range(line, d.line - 1, self.tab_width, d.column - 1).unwrap_or((0, 0));
+                Diagnostic {
+                    range: Range::new(Position::new(d.line as u32 - 1, utf16_len(&line[..start])),
+                                      Position::new(d.line as u32 - 1, utf16_len(&line[..end]))),
+                    severity: Some(match d.severity {
+                        Severity::Error => DiagnosticSeverity::ERROR,
+                        Severity::Warning => DiagnosticSeverity::WARNING,
+                    }),
+                    source: Some("svgbob".into()),
+                    message: d.message,
+                    ..Diagnostic::default()
+                }
+            })
+            .collect();
+        publish(uri.clone(), diagnostics)
+    }
+
+    // how the character under the cursor is drawn, see `svgbob explain`
+    fn hover(&self, params: &HoverParams) -> Option<Hover> {
+        let position = &params.text_document_position_params;
+        let text = self.documents.get(&position.text_document.uri)?;
+        let row = position.position.line as usize;
+        let line = *lines(text).get(row)?;
+        let byte = utf16_to_byte(line, position.position.character as usize);
+        let (column, start, end) = cell_at(line, row, self.tab_width, byte)?;
+        let grid = Grid::from_str_with_tab_width(text, self.tab_width);
+        let explanation = grid.explain(&Loc::new(column as isize, row as isize), &Settings::compact())?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```text\n{}```", explanation),
+            }),
+            range: Some(Range::new(Position::new(row as u32, utf16_len(&line[..start])),
+                                   Position::new(row as u32, utf16_len(&line[..end])))),
+        })
+    }
+
+    fn preview(&self, document: &TextDocumentIdentifier) -> Option<Value> {
+        let text = self.documents.get(&document.uri)?;
+        let svg = Grid::from_str_with_tab_width(text, self.tab_width).get_svg(&Settings::compact());
+        Some(json!({ "svg": svg.to_string() }))
+    }
+}
+
+fn invalid_params(id: RequestId, message: String) -> Response {
+    Response::new_err(id, ErrorCode::InvalidParams as i32, message)
+}
+
+fn publish(uri: Uri, diagnostics: Vec<Diagnostic>) -> Notification {
+    Notification::new(PublishDiagnostics::METHOD.into(),
+                      PublishDiagnosticsParams::new(uri, diagnostics, None))
+}
+
+// the lines of the document, the byte order mark is kept
+// so the offsets are the offsets in the document
+fn lines(text: &str) -> Vec<&str> {
+    text.lines().collect()
+}
+
+fn utf16_len(s: &str) -> u32 {
+    s.encode_utf16().count() as u32
+}
+
+fn utf16_to_byte(line: &str, character: usize) -> usize {
+    let mut units = 0;
+    for (byte, ch) in line.char_indices() {
+        if units >= character {
+            return byte;
+        }
+        units += ch.len_utf16();
+    }
+    line.len()
+}
+
+// the cells of the line with their columns and the bytes they span
+fn cells(line: &str, row: usize, tab_width: usize) -> Vec<(usize, usize, usize)> {
+    // the grid doesn't see the byte order mark at the start of the document
+    let bom = if row == 0 && line.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+    let offsets = svgbob::cell_offsets(&line[bom..], tab_width);
+    let mut cells = Vec::with_capacity(offsets.len());
+    let mut column = 0;
+    for (i, &(offset, width)) in offsets.iter().enumerate() {
+        // the cells of an expanded tab all start at the tab
+        let end = offsets[i..].iter()
+            .map(|&(next, _)| next)
+            .find(|&next| next > offset)
+            .unwrap_or(line.len() - bom);
+        cells.push((column, bom + offset, bom + end));
+        column += width;
+    }
+    cells
+}
+
+// the bytes of the cell at the column
+fn cell_range(line: &str, row: usize, tab_width: usize, column: usize) -> Option<(usize, usize)> {
+    cells(line, row, tab_width).into_iter()
+        .take_while(|&(start_column, _, _)| start_column <= column)
+        .last()
+        .map(|(_, start, end)| (start, end))
+}
+
+// the column of the cell containing the byte and the bytes of the cell
+fn cell_at(line: &str, row: usize, tab_width: usize, byte: usize) -> Option<(usize, usize, usize)> {
+    cells(line, row, tab_width).into_iter().find(|&(_, start, end)| start <= byte && byte < end)
+}
+
+#[cfg(test)]
+fn start() -> Connection {
+    use std::thread;
+
+    let (server, client) = Connection::memory();
+    thread::spawn(move || {
+        let params = server.initialize(serde_json::to_value(capabilities()).unwrap()).unwrap();
+        let params: InitializeParams = serde_json::from_value(params).unwrap();
+        Server::new(&params).run(&server).unwrap();
+    });
+    client.sender.send(Request::new(0.into(), "initialize".into(), json!({
+        "capabilities": {},
+        "initializationOptions": {"tab_width": 4},
+    })).into()).unwrap();
+    client.receiver.recv().unwrap();
+    client.sender.send(Notification::new("initialized".into(), json!({})).into()).unwrap();
+    client
+}
+
+#[cfg(test)]
+fn open(client: &Connection, uri: &str, text: &str) -> Value {
+    client.sender.send(Notification::new(DidOpenTextDocument::METHOD.into(), json!({
+        "textDocument": {"uri": uri, "languageId": "bob", "version": 1, "text": text},
+    })).into()).unwrap();
+    match client.receiver.recv().unwrap() {
+        Message::Notification(published) => published.params,
+        message => panic!("expecting the diagnostics, got {:?}", message),
+    }
+}
+
+#[cfg(test)]
+fn request(client: &Connection, id: i32, method: &str, params: Value) -> Value {
+    client.sender.send(Request::new(id.into(), method.into(), params).into()).unwrap();
+    match client.receiver.recv().unwrap() {
+        Message::Response(Response { result: Some(result), .. }) => result,
+        message => panic!("expecting a response, got {:?}", message),
+    }
+}
+
+#[test]
+fn test_diagnostics(){
+    let client = start();
+    // the tab is expanded to column 4, the corner is the 2nd character
+    let published = open(&client, "file:///diagram.bob", "\t+--\n");
+    let diagnostics = &published["diagnostics"];
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["range"], json!({"start": {"line": 0, "character": 1}, "end": {"line": 0, "character": 2}}));
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert!(diagnostics[0]["message"].as_str().unwrap().contains("corner"));
+
+    client.sender.send(Notification::new(DidChangeTextDocument::METHOD.into(), json!({
+        "textDocument": {"uri": "file:///diagram.bob", "version": 2},
+        "contentChanges": [{"text": "+--+\n|  |\n+--+\n"}],
+    })).into()).unwrap();
+    match client.receiver.recv().unwrap() {
+        Message::Notification(published) => assert_eq!(published.params["diagnostics"], json!([])),
+        message => panic!("expecting the diagnostics, got {:?}", message),
+    }
+}
+
+#[test]
+fn test_hover_and_preview(){
+    let client = start();
+    open(&client, "file:///box.bob", "中 +--+\n");
+    let hover = request(&client, 1, HoverRequest::METHOD, json!({
+        "textDocument": {"uri": "file:///box.bob"},
+        "position": {"line": 0, "character": 2},
+    }));
+    // the wide character takes 2 columns
+    let explanation = hover["contents"]["value"].as_str().unwrap();
+    assert!(explanation.starts_with("```text\n1:4\n"), "{}", explanation);
+    assert_eq!(hover["range"], json!({"start": {"line": 0, "character": 2}, "end": {"line": 0, "character": 3}}));
+    let hover = request(&client, 2, HoverRequest::METHOD, json!({
+        "textDocument": {"uri": "file:///box.bob"},
+        "position": {"line": 3, "character": 0},
+    }));
+    assert_eq!(hover, Value::Null);
+
+    let preview = request(&client, 3, PREVIEW, json!({"textDocument": {"uri": "file:///box.bob"}}));
+    assert_eq!(preview["svg"].as_str().unwrap(),
+               Grid::from_str("中 +--+\n").get_svg(&Settings::compact()).to_string());
+    assert_eq!(request(&client, 4, PREVIEW, json!({"textDocument": {"uri": "file:///other.bob"}})), Value::Null);
+
+    client.sender.send(Request::new(5.into(), "shutdown".into(), Value::Null).into()).unwrap();
+    assert!(match client.receiver.recv().unwrap() {
+        Message::Response(response) => response.error.is_none(),
+        _ => false,
+    });
+}
+
+#[test]
+fn test_tab_width(){
+    let params = |options: Value| InitializeParams {
+        initialization_options: Some(options),
+        ..InitializeParams::default()
+    };
+    assert_eq!(Server::new(&params(json!({"tab_width": 4}))).tab_width, 4);
+    assert_eq!(Server::new(&params(json!({"tab_width": 100_000_000_000u64}))).tab_width, 8);
+    assert_eq!(Server::new(&params(json!({"tab_width": 0}))).tab_width, 8);
+}
+
+#[test]
+fn test_cells(){
+    assert_eq!(cell_range("a\tb", 0, 4, 2), Some((1, 2)));
+    assert_eq!(cell_range("a\tb", 0, 4, 4), Some((2, 3)));
+    assert_eq!(cell_at("\u{feff}a\u{301}b", 0, 8, 4), Some((0, 3, 6)));
+    assert_eq!(cell_at("\u{feff}a", 1, 8, 3), Some((0, 3, 4)));
+    assert_eq!(cell_at("ab", 0, 8, 2), None);
+    assert_eq!(utf16_to_byte("😀x", 2), 4);
+}
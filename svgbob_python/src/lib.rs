@@ -0,0 +1,250 @@
+//! The `svgbob` python module
+//!
+//! ```python
+//! import svgbob
+//! svg = svgbob.to_svg("+--+\n|  |\n+--+", font_family="monospace")
+//! for element in svgbob.elements("--->"):
+//!     print(element["type"], element["start"], element["end"])
+//! ```
+//!
+//! Both functions take the fields of `Settings` as keyword arguments:
+//! `text_width`, `text_height`, `font_size`, `font_family`, `exact_text_position`,
+//! `optimize`, `compact_path`, `debug`, `crop`, `margin`, `responsive` and `tab_width` (1 to 64).
+//!
+#![deny(warnings)]
+extern crate pyo3;
+extern crate svgbob;
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use svgbob::Element;
+use svgbob::Feature;
+use svgbob::Grid;
+use svgbob::Point;
+use svgbob::Settings;
+use svgbob::MAX_TAB_WIDTH;
+use svgbob::Stroke;
+
+/// convert the ascii diagram into svg
+#[pyfunction]
+#[pyo3(signature = (text, **settings))]
+fn to_svg(text: &str, settings: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    let (settings, tab_width) = to_settings(settings)?;
+    Ok(Grid::from_str_with_tab_width(text, tab_width).get_svg(&settings).to_string())
+}
+
+/// the elements the ascii diagram is drawn with, as dicts with a `type` of
+/// `line`, `arc`, `circle`, `polygon`, `path` or `text`
+#[pyfunction]
+#[pyo3(signature = (text, **settings))]
+fn elements<'py>(py: Python<'py>, text: &str, settings: Option<&Bound<'py, PyDict>>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let (settings, tab_width) = to_settings(settings)?;
+    Grid::from_str_with_tab_width(text, tab_width)
+        .elements(&settings)
+        .iter()
+        .map(|elm| element_dict(py, elm))
+        .collect()
+}
+
+fn to_settings(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<(Settings, usize)> {
+    let kwargs = match kwargs {
+        Some(kwargs) => kwargs,
+        None => return Ok((Settings::default(), 8)),
+    };
+    for key in kwargs.keys() {
+        let key: String = key.extract()?;
+        match key.as_str() {
+            "text_width" | "text_height" | "font_size" | "font_family" | "exact_text_position"
+            | "optimize" | "compact_path" | "debug" | "tab_width" | "crop" | "margin" | "responsive" => (),
+            _ => return Err(PyTypeError::new_err(format!("unexpected keyword argument '{}'", key))),
+        }
+    }
+    let number = |name: &str| -> PyResult<Option<f32>> {
+        match kwargs.get_item(name)? {
+            None => Ok(None),
+            Some(value) => {
+                let n: f32 = value.extract()?;
+                if n.is_finite() && n > 0.0 {
+                    Ok(Some(n))
+                } else {
+                    Err(PyValueError::new_err(format!("{} should be a positive number", name)))
+                }
+            }
+        }
+    };
+    let boolean = |name: &str| -> PyResult<Option<bool>> {
+        match kwargs.get_item(name)? {
+            None => Ok(None),
+            Some(value) => Ok(Some(value.extract()?)),
+        }
+    };
+
+    let mut settings = Settings::with_size(number("text_width")?.unwrap_or(8.0),
+                                           number("text_height")?.unwrap_or(16.0));
+    if let Some(font_size) = number("font_size")? {
+        settings.set_font_size(font_size);
+    }
+    if let Some(font_family) = kwargs.get_item("font_family")? {
+        let font_family: String = font_family.extract()?;
+        settings.set_font_family(&font_family);
+    }
+    if let Some(exact_text_position) = boolean("exact_text_position")? {
+        settings.set_exact_text_position(exact_text_position);
+    }
+    if let Some(optimize) = boolean("optimize")? {
+        settings.set_optimize(optimize);
+    }
+    if let Some(compact_path) = boolean("compact_path")? {
+        settings.set_compact_path(compact_path);
+    }
+    if let Some(debug) = boolean("debug")? {
+        settings.set_debug(debug);
+    }
+    if let Some(crop) = boolean("crop")? {
+        settings.set_crop(crop);
+    }
+    if let Some(margin) = kwargs.get_item("margin")? {
+        let margin: f32 = margin.extract()?;
+        if !(margin.is_finite() && margin >= 0.0) {
+            return Err(PyValueError::new_err("margin should be a number, 0 or more"));
+        }
+        settings.set_margin(margin);
+    }
+    if let Some(responsive) = boolean("responsive")? {
+        settings.set_responsive(responsive);
+    }
+    let tab_width = match kwargs.get_item("tab_width")? {
+        Some(tab_width) => {
+            let tab_width: i64 = tab_width.extract()?;
+            if !(1..=MAX_TAB_WIDTH as i64).contains(&tab_width) {
+                return Err(PyValueError::new_err(format!("tab_width should be from 1 to {}", MAX_TAB_WIDTH)));
+            }
+            tab_width as usize
+        }
+        None => 8,
+    };
+    Ok((settings, tab_width))
+}
+
+fn point(p: &Point) -> (f32, f32) {
+    (p.x(), p.y())
+}
+
+fn stroke(stroke: &Stroke) -> &'static str {
+    match *stroke {
+        Stroke::Solid => "solid",
+        Stroke::Dashed => "dashed",
+        Stroke::Heavy => "heavy",
+        Stroke::HeavyDashed => "heavy_dashed",
+    }
+}
+
+fn element_dict<'py>(py: Python<'py>, elm: &Element) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    match *elm {
+        Element::Line(ref start, ref end, ref line_stroke, ref feature) => {
+            dict.set_item("type", "line")?;
+            dict.set_item("start", point(start))?;
+            dict.set_item("end", point(end))?;
+            dict.set_item("stroke", stroke(line_stroke))?;
+            let feature = match *feature {
+                Feature::Arrow => Some("arrow"),
+                Feature::Circle => Some("circle"),
+                Feature::Nothing => None,
+            };
+            dict.set_item("feature", feature)?;
+        }
+        Element::Arc(ref start, ref end, radius, sweep) => {
+            dict.set_item("type", "arc")?;
+            dict.set_item("start", point(start))?;
+            dict.set_item("end", point(end))?;
+            dict.set_item("radius", radius)?;
+            dict.set_item("sweep", sweep)?;
+        }
+        Element::Circle(ref center, radius, ref class) => {
+            dict.set_item("type", "circle")?;
+            dict.set_item("center", point(center))?;
+            dict.set_item("radius", radius)?;
+            dict.set_item("class", class)?;
+        }
+        Element::Polygon(ref points, ref class) => {
+            dict.set_item("type", "polygon")?;
+            dict.set_item("points", points.iter().map(point).collect::<Vec<_>>())?;
+            dict.set_item("class", class)?;
+        }
+        Element::Path(ref start, ref end, ref d, ref path_stroke) => {
+            dict.set_item("type", "path")?;
+            dict.set_item("start", point(start))?;
+            dict.set_item("end", point(end))?;
+            dict.set_item("d", d)?;
+            dict.set_item("stroke", stroke(path_stroke))?;
+        }
+        Element::Text(ref loc, ref text) => {
+            dict.set_item("type", "text")?;
+            // the cell of the first character, starting at 0
+            dict.set_item("column", loc.x())?;
+            dict.set_item("row", loc.y())?;
+            dict.set_item("text", text)?;
+        }
+    }
+    Ok(dict)
+}
+
+#[pymodule(name = "svgbob")]
+fn svgbob_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(to_svg, m)?)?;
+    m.add_function(wrap_pyfunction!(elements, m)?)?;
+    Ok(())
+}
+
+#[test]
+fn test_to_svg(){
+    Python::attach(|py| {
+        let svg = to_svg("+--+", None).unwrap();
+        assert_eq!(svg, Grid::from_str("+--+").get_svg(&Settings::default()).to_string());
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("font_family", "monospace").unwrap();
+        kwargs.set_item("text_height", 32).unwrap();
+        let svg = to_svg("hello", Some(&kwargs)).unwrap();
+        assert!(svg.contains(r#"font-family="monospace""#));
+        assert!(svg.contains(r#"font-size="28""#));
+        // the font family is escaped in the attribute
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("font_family", "A&B<C 'D'").unwrap();
+        let svg = to_svg("a", Some(&kwargs)).unwrap();
+        assert!(svg.contains(r#"font-family="A&amp;B&lt;C &apos;D&apos;""#), "{}", svg);
+    });
+}
+
+#[test]
+fn test_invalid_settings(){
+    Python::attach(|py| {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("colour", "red").unwrap();
+        assert!(to_svg("", Some(&kwargs)).unwrap_err().is_instance_of::<PyTypeError>(py));
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("text_width", -1).unwrap();
+        assert!(to_svg("", Some(&kwargs)).unwrap_err().is_instance_of::<PyValueError>(py));
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("debug", "yes").unwrap();
+        assert!(to_svg("", Some(&kwargs)).is_err());
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("tab_width", 100_000_000_000i64).unwrap();
+        assert!(to_svg("a\tb", Some(&kwargs)).unwrap_err().is_instance_of::<PyValueError>(py));
+    });
+}
+
+#[test]
+fn test_elements(){
+    Python::attach(|py| {
+        let found = elements(py, "--->\nhi", None).unwrap();
+        assert_eq!(found.len(), 2);
+        let line = &found[0];
+        assert_eq!(line.get_item("type").unwrap().unwrap().extract::<String>().unwrap(), "line");
+        assert_eq!(line.get_item("feature").unwrap().unwrap().extract::<String>().unwrap(), "arrow");
+        let text = &found[1];
+        assert_eq!(text.get_item("text").unwrap().unwrap().extract::<String>().unwrap(), "hi");
+        assert_eq!(text.get_item("row").unwrap().unwrap().extract::<isize>().unwrap(), 1);
+    });
+}
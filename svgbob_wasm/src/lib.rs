@@ -0,0 +1,159 @@
+//! svgbob for the browser, built for `wasm32-unknown-unknown`
+//!
+//! ```js
+//! import init, { to_svg } from "./pkg/svgbob_wasm.js";
+//! await init();
+//! const svg = to_svg("+--+\n|  |\n+--+", '{"font_family": "monospace"}');
+//! ```
+//!
+//! The settings are a json object, all the fields are optional:
+//!
+//! | field               | type    | default |
+//! |---------------------|---------|---------|
+//! | text_width          | number  | 8       |
+//! | text_height         | number  | 16      |
+//! | font_size           | number  | 14      |
+//! | font_family         | string  | arial   |
+//! | exact_text_position | boolean | false   |
+//! | optimize            | boolean | true    |
+//! | compact_path        | boolean | true    |
+//! | debug               | boolean | false   |
+//! | crop                | boolean | true    |
+//! | margin              | number  | 8       |
+//! | responsive          | boolean | false   |
+//! | tab_width           | number  | 8       |
+//!
+//! `tab_width` is a whole number from 1 to 64.
+//!
+#![deny(warnings)]
+extern crate serde_json;
+extern crate svgbob;
+extern crate wasm_bindgen;
+
+use serde_json::Value;
+use svgbob::Grid;
+use svgbob::Settings;
+use svgbob::MAX_TAB_WIDTH;
+use wasm_bindgen::prelude::*;
+
+/// convert the ascii diagram into svg,
+/// settings_json is the json object of the settings, empty for the defaults.
+/// An invalid settings is thrown as an error message
+#[wasm_bindgen]
+pub fn to_svg(text: &str, settings_json: &str) -> Result<String, String> {
+    let (settings, tab_width) = parse_settings(settings_json)?;
+    let grid = Grid::from_str_with_tab_width(text, tab_width);
+    let mut svg = vec![];
+    grid.write_svg(&settings, &mut svg).map_err(|e| e.to_string())?;
+    String::from_utf8(svg).map_err(|e| e.to_string())
+}
+
+fn parse_settings(settings_json: &str) -> Result<(Settings, usize), String> {
+    if settings_json.trim().is_empty() {
+        return Ok((Settings::default(), 8));
+    }
+    let value: Value = serde_json::from_str(settings_json).map_err(|e| format!("invalid settings: {}", e))?;
+    let fields = value.as_object().ok_or("invalid settings: expecting a json object")?;
+
+    let number = |name: &str| -> Result<Option<f64>, String> {
+        match fields.get(name) {
+            None => Ok(None),
+            Some(value) => value.as_f64()
+                .filter(|n| n.is_finite() && *n > 0.0)
+                .map(Some)
+                .ok_or_else(|| format!("invalid settings: `{}` should be a positive number", name)),
+        }
+    };
+    let boolean = |name: &str| -> Result<Option<bool>, String> {
+        match fields.get(name) {
+            None => Ok(None),
+            Some(value) => value.as_bool()
+                .map(Some)
+                .ok_or_else(|| format!("invalid settings: `{}` should be true or false", name)),
+        }
+    };
+
+    for name in fields.keys() {
+        match name.as_str() {
+            "text_width" | "text_height" | "font_size" | "font_family" | "exact_text_position"
+            | "optimize" | "compact_path" | "debug" | "tab_width" | "crop" | "margin" | "responsive" => (),
+            _ => return Err(format!("invalid settings: unknown field `{}`", name)),
+        }
+    }
+
+    let text_width = number("text_width")?.unwrap_or(8.0);
+    let text_height = number("text_height")?.unwrap_or(16.0);
+    let mut settings = Settings::with_size(text_width as f32, text_height as f32);
+    if let Some(font_size) = number("font_size")? {
+        settings.set_font_size(font_size as f32);
+    }
+    if let Some(font_family) = fields.get("font_family") {
+        let font_family = font_family.as_str()
+            .ok_or("invalid settings: `font_family` should be a string")?;
+        settings.set_font_family(font_family);
+    }
+    if let Some(exact_text_position) = boolean("exact_text_position")? {
+        settings.set_exact_text_position(exact_text_position);
+    }
+    if let Some(optimize) = boolean("optimize")? {
+        settings.set_optimize(optimize);
+    }
+    if let Some(compact_path) = boolean("compact_path")? {
+        settings.set_compact_path(compact_path);
+    }
+    if let Some(debug) = boolean("debug")? {
+        settings.set_debug(debug);
+    }
+    if let Some(crop) = boolean("crop")? {
+        settings.set_crop(crop);
+    }
+    if let Some(margin) = fields.get("margin") {
+        let margin = margin.as_f64()
+            .filter(|n| n.is_finite() && *n >= 0.0)
+            .ok_or("invalid settings: `margin` should be a number, 0 or more")?;
+        settings.set_margin(margin as f32);
+    }
+    if let Some(responsive) = boolean("responsive")? {
+        settings.set_responsive(responsive);
+    }
+    let tab_width = match fields.get("tab_width") {
+        None => 8,
+        Some(value) => value.as_u64()
+            .filter(|n| (1..=MAX_TAB_WIDTH as u64).contains(n))
+            .ok_or_else(|| format!("invalid settings: `tab_width` should be a whole number from 1 to {}", MAX_TAB_WIDTH))?
+            as usize,
+    };
+    Ok((settings, tab_width))
+}
+
+#[test]
+fn test_default_settings(){
+    let bob = "+--+\n|  |\n+--+";
+    let svg = to_svg(bob, "").unwrap();
+    assert_eq!(svg, Grid::from_str(bob).get_svg(&Settings::default()).to_string());
+    assert_eq!(to_svg(bob, "{}").unwrap(), svg);
+}
+
+#[test]
+fn test_settings_json(){
+    let svg = to_svg("a\tb", r#"{"font_family": "monospace", "text_height": 32, "tab_width": 4}"#).unwrap();
+    assert!(svg.contains(r#"font-family="monospace""#));
+    assert!(svg.contains(r#"font-size="28""#));
+    assert!(svg.contains(r#"<tspan xml:space="preserve">a   b</tspan>"#));
+    let svg = to_svg("+--+", r#"{"margin": 0, "responsive": true}"#).unwrap();
+    assert!(svg.contains(r#"viewBox="0 0 32 16" width="100%""#));
+    // the font family is escaped in the attribute
+    let svg = to_svg("a", r#"{"font_family": "A&B<C \"D\""}"#).unwrap();
+    assert!(svg.contains(r#"font-family="A&amp;B&lt;C &quot;D&quot;""#), "{}", svg);
+}
+
+#[test]
+fn test_invalid_settings(){
+    assert!(to_svg("", "[1]").unwrap_err().contains("json object"));
+    assert!(to_svg("", r#"{"font_size": "big"}"#).unwrap_err().contains("font_size"));
+    assert!(to_svg("", r#"{"colour": "red"}"#).unwrap_err().contains("unknown field `colour`"));
+    assert!(to_svg("", "{").is_err());
+    assert!(to_svg("a\tb", r#"{"tab_width": 1e11}"#).unwrap_err().contains("tab_width"));
+    assert!(to_svg("a\tb", r#"{"tab_width": 2.5}"#).unwrap_err().contains("tab_width"));
+    assert!(to_svg("a\tb", r#"{"tab_width": 0}"#).unwrap_err().contains("tab_width"));
+}